@@ -1,6 +1,99 @@
 use crate::db;
 use anyhow::{Context, Result};
 use git2::{BranchType, Repository};
+use serde::Serialize;
+
+/// A local or remote branch of a repo (remote `HEAD` aliases are skipped).
+#[derive(Debug, Clone, Serialize)]
+pub struct Branch {
+    /// `local` or `remote`.
+    pub kind: String,
+    pub name: String,
+    pub refname: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitSummary {
+    pub oid: String,
+    pub summary: String,
+    pub author: String,
+    pub email: String,
+    pub time: i64,
+}
+
+/// Local branches first, then remote ones, each sorted by name.
+pub fn list_branches(repo_path: &str) -> Result<Vec<Branch>> {
+    let repo = Repository::open(repo_path).with_context(|| format!("open repo {}", repo_path))?;
+    let mut out = Vec::new();
+
+    for (kind, bt) in [("local", BranchType::Local), ("remote", BranchType::Remote)] {
+        let iter = repo.branches(Some(bt))?;
+        for b in iter {
+            let (branch, _) = b?;
+            let Some(name) = branch.name()?.map(|s| s.to_string()) else {
+                continue;
+            };
+            if kind == "remote" && (name.ends_with("/HEAD") || name == "HEAD") {
+                continue;
+            }
+            let Some(reference) = branch.get().name().map(|s| s.to_string()) else {
+                continue;
+            };
+            out.push(Branch {
+                kind: kind.to_string(),
+                name,
+                refname: reference,
+            });
+        }
+    }
+
+    out.sort_by(|a, b| {
+        (a.kind.as_str(), a.name.as_str()).cmp(&(b.kind.as_str(), b.name.as_str()))
+    });
+    out.dedup_by(|a, b| a.refname == b.refname);
+    Ok(out)
+}
+
+/// One page of the history reachable from `refname`, newest first. The flag tells whether
+/// there are more commits after this page.
+pub fn list_commits(
+    repo_path: &str,
+    refname: &str,
+    offset: usize,
+    limit: usize,
+) -> Result<(Vec<CommitSummary>, bool)> {
+    let repo = Repository::open(repo_path).with_context(|| format!("open repo {}", repo_path))?;
+    let obj = repo
+        .revparse_single(refname)
+        .with_context(|| format!("resolve ref {refname}"))?;
+    let oid = obj.id();
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(git2::Sort::TIME)?;
+    walk.push(oid)?;
+
+    let mut items = Vec::new();
+    let mut has_more = false;
+
+    for oid in walk.skip(offset) {
+        let oid = oid?;
+        if items.len() >= limit {
+            has_more = true;
+            break;
+        }
+        let commit = repo.find_commit(oid)?;
+        let author = commit.author();
+        items.push(CommitSummary {
+            oid: oid.to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+            author: author.name().unwrap_or("").to_string(),
+            email: author.email().unwrap_or("").to_string(),
+            time: commit.time().seconds(),
+        });
+    }
+
+    Ok((items, has_more))
+}
 
 pub fn build_commit_index_for_repo(
     repo_path: &str,
@@ -46,7 +139,7 @@ pub fn build_commit_index_for_repo(
         }
     }
 
-    tips.sort_by(|a, b| b.tip_time.unwrap_or(0).cmp(&a.tip_time.unwrap_or(0)));
+    tips.sort_by_key(|t| std::cmp::Reverse(t.tip_time.unwrap_or(0)));
     tips.truncate(branches_limit.max(1));
 
     let branches = tips
@@ -82,6 +175,7 @@ pub fn build_commit_index_for_repo(
                 email: author.email().map(|s| s.to_string()),
                 summary: commit.summary().map(|s| s.to_string()),
                 message: commit.message().map(|s| s.to_string()),
+                parent_count: commit.parent_count(),
             });
         }
     }
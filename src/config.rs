@@ -1,16 +1,105 @@
+use crate::{db, scan, tag_rules};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    #[serde(default)]
     pub roots: Vec<String>,
     #[serde(default = "default_ignore_dir_names")]
     pub ignore_dir_names: Vec<String>,
+    /// Gitignore-style patterns (anchored paths, `**`, `!` negation) evaluated while scanning.
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
     #[serde(default = "default_commit_index_branches")]
     pub commit_index_branches: usize,
     #[serde(default = "default_commit_index_commits_per_branch")]
     pub commit_index_commits_per_branch: usize,
+    /// Number of metadata readers used while scanning a root.
+    #[serde(default = "default_scan_concurrency")]
+    pub scan_concurrency: usize,
+    /// Optional per-root overrides, keyed by the root path as stored in `roots`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub root_settings: BTreeMap<String, RootSettings>,
+    /// `[[tag_rules]]` entries, re-evaluated for every repo a scan writes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tag_rules: Vec<TagRule>,
+    /// Repo paths hidden with `coderoom hide`; scans and the watcher skip them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_repos: Vec<String>,
+    /// Author names and emails that count as "me" in commit search (`is:mine`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub my_identities: Vec<String>,
+}
+
+/// Same values as an empty `config.toml`.
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            roots: Vec::new(),
+            ignore_dir_names: default_ignore_dir_names(),
+            ignore_patterns: Vec::new(),
+            commit_index_branches: default_commit_index_branches(),
+            commit_index_commits_per_branch: default_commit_index_commits_per_branch(),
+            scan_concurrency: default_scan_concurrency(),
+            root_settings: BTreeMap::new(),
+            tag_rules: Vec::new(),
+            excluded_repos: Vec::new(),
+            my_identities: Vec::new(),
+        }
+    }
+}
+
+/// Settings for one root; unset fields fall back to the global ones.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct RootSettings {
+    /// Shown instead of the path in the Web UI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    /// Added to the global `ignore_dir_names` for this root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_dir_names: Vec<String>,
+    /// Added to the global `ignore_patterns` for this root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_patterns: Vec<String>,
+    /// Tags applied to every repo found under this root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auto_tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_index_branches: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_index_commits_per_branch: Option<usize>,
+}
+
+/// Tags applied to every repo matching all of the conditions that are set.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct TagRule {
+    pub tags: Vec<String>,
+    /// Glob over the absolute repo path; `*` stays within one component, `**` spans several.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_glob: Option<String>,
+    /// Repo lives under this directory (usually one of `roots`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    /// Host of the origin remote, e.g. `github.com`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin_host: Option<String>,
+    /// First path component of the origin remote (user, org or group).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin_owner: Option<String>,
+    /// Detected language, compared case-insensitively.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Paths relative to the work tree that must all exist, e.g. `Dockerfile`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub has_files: Vec<String>,
+    /// Branch checked out at HEAD, compared case-insensitively.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<String>,
 }
 
 impl Config {
@@ -43,9 +132,172 @@ impl Config {
         let root = normalize_path(root);
         let before = self.roots.len();
         self.roots.retain(|r| r != &root);
+        self.root_settings.remove(&root);
         before != self.roots.len()
     }
 
+    /// Settings of a configured root (defaults when it has none).
+    pub fn root_settings(&self, root: &Path) -> RootSettings {
+        self.root_settings
+            .get(&normalize_path(root))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Replaces the settings of a configured root; empty settings are dropped.
+    pub fn set_root_settings(&mut self, root: &Path, mut settings: RootSettings) -> Result<()> {
+        let root = normalize_path(root);
+        if !self.roots.contains(&root) {
+            anyhow::bail!("not a configured root: {root}");
+        }
+        settings.label = settings.label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+        for list in [
+            &mut settings.ignore_dir_names,
+            &mut settings.ignore_patterns,
+            &mut settings.auto_tags,
+        ] {
+            *list = list
+                .iter()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+            dedup_keep_first(list);
+        }
+        settings.commit_index_branches = settings.commit_index_branches.map(|v| v.clamp(1, 200));
+        settings.commit_index_commits_per_branch =
+            settings.commit_index_commits_per_branch.map(|v| v.clamp(1, 500));
+        for pattern in &settings.ignore_patterns {
+            scan::validate_ignore_pattern(pattern)?;
+        }
+        if settings == RootSettings::default() {
+            self.root_settings.remove(&root);
+        } else {
+            self.root_settings.insert(root, settings);
+        }
+        Ok(())
+    }
+
+    /// The configured root containing `path`; the innermost one if roots are nested.
+    pub fn root_of(&self, path: &Path) -> Option<&str> {
+        self.roots
+            .iter()
+            .filter(|r| path.starts_with(r))
+            .max_by_key(|r| r.len())
+            .map(|r| r.as_str())
+    }
+
+    /// Global ignore rules plus the extra ones of `root`.
+    pub fn ignore_rules_for(&self, root: &Path) -> scan::IgnoreRules {
+        let extra = self.root_settings(root);
+        let mut names = self.ignore_dir_names.clone();
+        names.extend(extra.ignore_dir_names);
+        let mut patterns = self.ignore_patterns.clone();
+        patterns.extend(extra.ignore_patterns);
+        let mut rules = scan::IgnoreRules::new(&names, &patterns);
+        rules.excluded_paths = self.excluded_repos.iter().map(PathBuf::from).collect();
+        rules
+    }
+
+    /// Tag rules plus the auto-tags of `root`, ready to apply to repos found under it.
+    pub fn tag_rules_for(&self, root: &Path) -> Result<tag_rules::TagRules> {
+        tag_rules::TagRules::compile(&self.tag_rules, self.root_settings(root).auto_tags)
+    }
+
+    /// Scan options for `root`: `base` with the root's max depth (unless `base` sets one)
+    /// and tag rules filled in.
+    pub fn scan_options_for(&self, root: &Path, base: &scan::ScanOptions) -> Result<scan::ScanOptions> {
+        let settings = self.root_settings(root);
+        Ok(scan::ScanOptions {
+            max_depth: base.max_depth.or(settings.max_depth),
+            tag_rules: self.tag_rules_for(root)?,
+            ..base.clone()
+        })
+    }
+
+    /// (branches, commits per branch) for the commit index of the repo at `repo_path`.
+    pub fn commit_index_limits(&self, repo_path: &str) -> (usize, usize) {
+        let settings = self
+            .root_of(Path::new(repo_path))
+            .and_then(|r| self.root_settings.get(r));
+        (
+            settings
+                .and_then(|s| s.commit_index_branches)
+                .unwrap_or(self.commit_index_branches),
+            settings
+                .and_then(|s| s.commit_index_commits_per_branch)
+                .unwrap_or(self.commit_index_commits_per_branch),
+        )
+    }
+
+    /// Renames `from` (and tags below it) in tag rules and root auto-tags, so a renamed
+    /// or merged tag is not re-created under its old name by the next scan.
+    pub fn rename_tag(&mut self, from: &str, to: &str) -> bool {
+        let mut changed = false;
+        let lists = self
+            .tag_rules
+            .iter_mut()
+            .map(|r| &mut r.tags)
+            .chain(self.root_settings.values_mut().map(|s| &mut s.auto_tags));
+        for list in lists {
+            for tag in list.iter_mut() {
+                if let Some(new) = db::rebase_tag(tag, from, to) {
+                    *tag = new;
+                    changed = true;
+                }
+            }
+            dedup_keep_first(list);
+        }
+        changed
+    }
+
+    /// Whether a tag rule or root auto-tag applies `name` or a tag below it.
+    pub fn applies_tag(&self, name: &str) -> bool {
+        self.tag_rules
+            .iter()
+            .flat_map(|r| &r.tags)
+            .chain(self.root_settings.values().flat_map(|s| &s.auto_tags))
+            .any(|t| db::rebase_tag(t, name, "").is_some())
+    }
+
+    /// Excludes a repo from future scans; `path` is stored as given (repo paths in the
+    /// index are already canonical).
+    pub fn add_excluded_repo(&mut self, path: &str) -> bool {
+        if self.excluded_repos.iter().any(|p| p == path) {
+            return false;
+        }
+        self.excluded_repos.push(path.to_string());
+        self.excluded_repos.sort();
+        true
+    }
+
+    /// Finds a hidden repo by its exact path, or by its directory name when no path
+    /// matches; a name shared by several hidden repos is an error listing them.
+    pub fn find_excluded_repo(&self, input: &str) -> Result<String> {
+        let input = input.trim();
+        if let Some(p) = self.excluded_repos.iter().find(|p| *p == input) {
+            return Ok(p.clone());
+        }
+        let named: Vec<&String> = self
+            .excluded_repos
+            .iter()
+            .filter(|p| Path::new(p).file_name().is_some_and(|n| n == input))
+            .collect();
+        match named.as_slice() {
+            [p] => Ok(p.to_string()),
+            [] => anyhow::bail!("not hidden: {input}"),
+            _ => anyhow::bail!(
+                "{input} names several hidden repos; give the path:\n  {}",
+                named.iter().map(|p| p.as_str()).collect::<Vec<_>>().join("\n  ")
+            ),
+        }
+    }
+
+    pub fn remove_excluded_repo(&mut self, path: &str) -> bool {
+        let before = self.excluded_repos.len();
+        self.excluded_repos.retain(|p| p != path);
+        before != self.excluded_repos.len()
+    }
+
     pub fn add_ignore_dir_name(&mut self, name: &str) -> bool {
         let name = name.trim();
         if name.is_empty() {
@@ -69,6 +321,25 @@ impl Config {
     pub fn reset_ignore_dir_names(&mut self) {
         self.ignore_dir_names = default_ignore_dir_names();
     }
+
+    pub fn add_ignore_pattern(&mut self, pattern: &str) -> bool {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return false;
+        }
+        if self.ignore_patterns.iter().any(|s| s == pattern) {
+            return false;
+        }
+        self.ignore_patterns.push(pattern.to_string());
+        true
+    }
+
+    pub fn remove_ignore_pattern(&mut self, pattern: &str) -> bool {
+        let pattern = pattern.trim();
+        let before = self.ignore_patterns.len();
+        self.ignore_patterns.retain(|s| s != pattern);
+        before != self.ignore_patterns.len()
+    }
 }
 
 pub fn data_dir() -> Result<PathBuf> {
@@ -89,6 +360,12 @@ pub fn db_path(data_dir: &Path) -> PathBuf {
     data_dir.join("coderoom.db")
 }
 
+/// Drops repeated entries, keeping the first occurrence of each in place.
+fn dedup_keep_first(list: &mut Vec<String>) {
+    let mut seen = std::collections::HashSet::new();
+    list.retain(|s| seen.insert(s.clone()));
+}
+
 fn normalize_path(path: &Path) -> String {
     std::fs::canonicalize(path)
         .unwrap_or_else(|_| path.to_path_buf())
@@ -104,6 +381,10 @@ fn default_commit_index_commits_per_branch() -> usize {
     50
 }
 
+fn default_scan_concurrency() -> usize {
+    8
+}
+
 fn default_ignore_dir_names() -> Vec<String> {
     vec![
         ".cargo".into(),
@@ -125,3 +406,106 @@ fn default_ignore_dir_names() -> Vec<String> {
         "target".into(),
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn renaming_a_tag_merges_into_an_existing_one() {
+        let mut cfg = Config::default();
+        cfg.tag_rules.push(TagRule {
+            tags: vec!["a".into(), "b".into(), "c".into()],
+            ..TagRule::default()
+        });
+        cfg.root_settings.insert(
+            "/work".into(),
+            RootSettings {
+                auto_tags: vec!["a/x".into(), "b".into(), "c/x".into()],
+                ..RootSettings::default()
+            },
+        );
+        assert!(cfg.rename_tag("a", "c"));
+        assert_eq!(cfg.tag_rules[0].tags, vec!["c", "b"]);
+        assert_eq!(cfg.root_settings["/work"].auto_tags, vec!["c/x", "b"]);
+        assert!(!cfg.rename_tag("a", "c"));
+    }
+
+    #[test]
+    fn default_matches_an_empty_file() -> Result<()> {
+        let empty: Config = toml::from_str("")?;
+        let written = toml::to_string_pretty(&Config::default())?;
+        assert_eq!(written, toml::to_string_pretty(&empty)?);
+        let reread: Config = toml::from_str(&written)?;
+        assert_eq!(reread.scan_concurrency, 8);
+        assert_eq!((reread.commit_index_branches, reread.commit_index_commits_per_branch), (10, 50));
+        assert_eq!(reread.ignore_dir_names, default_ignore_dir_names());
+        Ok(())
+    }
+
+    #[test]
+    fn root_settings_override_globals_and_round_trip() -> Result<()> {
+        let dir = tempdir()?;
+        let root = std::fs::canonicalize(dir.path())?.join("work");
+        git2::Repository::init(root.join("app"))?;
+        git2::Repository::init(root.join("vendor/lib"))?;
+
+        let mut cfg = Config {
+            commit_index_branches: 10,
+            commit_index_commits_per_branch: 50,
+            ..Config::default()
+        };
+        assert!(cfg.set_root_settings(&root, RootSettings::default()).is_err());
+        cfg.add_root(&root);
+        cfg.set_root_settings(
+            &root,
+            RootSettings {
+                label: Some(" Work ".into()),
+                ignore_dir_names: vec!["vendor".into(), "build".into(), " vendor".into()],
+                auto_tags: vec!["work".into()],
+                commit_index_branches: Some(3),
+                ..RootSettings::default()
+            },
+        )?;
+        let path = dir.path().join("config.toml");
+        cfg.save(&path)?;
+        let cfg = Config::load_or_create(&path)?;
+        assert_eq!(cfg.root_settings(&root).label.as_deref(), Some("Work"));
+        assert_eq!(cfg.root_settings(&root).ignore_dir_names, vec!["vendor", "build"]);
+
+        let app = root.join("app").to_string_lossy().to_string();
+        assert_eq!(cfg.commit_index_limits(&app), (3, 50));
+        assert_eq!(cfg.commit_index_limits("/elsewhere/repo"), (10, 50));
+
+        let db = crate::db::Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        let base = scan::ScanOptions {
+            max_depth: None,
+            prune: false,
+            concurrency: 2,
+            full: false,
+            dry_run: false,
+            tag_rules: tag_rules::TagRules::default(),
+        };
+        let opts = cfg.scan_options_for(&root, &base)?;
+        let out = scan::scan_root(&db, &root, &opts, &cfg.ignore_rules_for(&root))?;
+        assert_eq!(out.indexed, 1);
+        assert_eq!(db.list_repo_tags(&app)?, vec!["work"]);
+        Ok(())
+    }
+
+    #[test]
+    fn hidden_repos_match_by_exact_path_or_unique_name() {
+        let mut cfg = Config::default();
+        for path in ["/src/api", "/work/api", "/src/web-frontend"] {
+            cfg.add_excluded_repo(path);
+        }
+        assert_eq!(cfg.find_excluded_repo("/work/api").unwrap(), "/work/api");
+        assert_eq!(cfg.find_excluded_repo("web-frontend").unwrap(), "/src/web-frontend");
+        assert!(cfg.find_excluded_repo("web").is_err());
+        assert!(cfg.find_excluded_repo("/src").is_err());
+        let shared = cfg.find_excluded_repo("api").unwrap_err().to_string();
+        assert!(shared.contains("/src/api") && shared.contains("/work/api"), "{shared}");
+    }
+}
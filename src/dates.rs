@@ -0,0 +1,104 @@
+use anyhow::{bail, Result};
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+
+/// Parses a point in time given on the command line or in a query: a relative age
+/// (`30m`, `12h`, `7d`, `2w`), a unix timestamp, or a local date / date-time
+/// (`2024-05-01`, `2024-05-01 14:30`, `2024-05-01T14:30:00`).
+pub fn parse_time(input: &str, now: i64) -> Result<i64> {
+    let s = input.trim();
+    if s.is_empty() {
+        bail!("empty time");
+    }
+
+    if let Some(unit) = s.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        if let Ok(n) = s[..s.len() - 1].parse::<i64>() {
+            let secs = match unit {
+                'm' => 60,
+                'h' => 3600,
+                'd' => 86_400,
+                'w' => 7 * 86_400,
+                _ => bail!("unknown time unit '{unit}' in '{s}' (use m, h, d or w)"),
+            };
+            return Ok(now - n * secs);
+        }
+    }
+
+    if s.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(s.parse()?);
+    }
+
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return local_ts(d.and_hms_opt(0, 0, 0).expect("midnight"), s);
+    }
+    for fmt in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+            return local_ts(dt, s);
+        }
+    }
+    bail!("cannot parse time '{s}' (try 7d, 12h, 2024-05-01 or 2024-05-01 14:30)")
+}
+
+/// [`parse_time`] for the end of a range: a bare date means the start of the next day,
+/// so `before:2024-05-01` still includes May 1st.
+pub fn parse_time_end(input: &str, now: i64) -> Result<i64> {
+    let s = input.trim();
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        let Some(next) = d.succ_opt() else { bail!("'{s}' is out of range") };
+        return local_ts(next.and_hms_opt(0, 0, 0).expect("midnight"), s);
+    }
+    parse_time(s, now)
+}
+
+fn local_ts(dt: NaiveDateTime, input: &str) -> Result<i64> {
+    match Local.from_local_datetime(&dt).earliest() {
+        Some(t) => Ok(t.timestamp()),
+        None => bail!("'{input}' does not exist in the local time zone"),
+    }
+}
+
+/// Local `YYYY-MM-DD HH:MM` for CLI output.
+pub fn format_ts(ts: i64) -> String {
+    match Local.timestamp_opt(ts, 0).single() {
+        Some(t) => t.format("%Y-%m-%d %H:%M").to_string(),
+        None => ts.to_string(),
+    }
+}
+
+/// Age of `ts` for tables: `just now`, `5m ago`, `3h ago`, `12d ago`, `4mo ago`, `2y ago`.
+/// Future times fall back to [`format_ts`].
+pub fn format_relative(ts: i64, now: i64) -> String {
+    let age = now - ts;
+    match age {
+        a if a < 0 => format_ts(ts),
+        a if a < 60 => "just now".to_string(),
+        a if a < 3600 => format!("{}m ago", a / 60),
+        a if a < 86_400 => format!("{}h ago", a / 3600),
+        a if a < 60 * 86_400 => format!("{}d ago", a / 86_400),
+        a if a < 365 * 86_400 => format!("{}mo ago", a / (30 * 86_400)),
+        a => format!("{}y ago", a / (365 * 86_400)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_relative_absolute_and_unix_times() -> Result<()> {
+        let now = 1_700_000_000;
+        assert_eq!(parse_time("7d", now)?, now - 7 * 86_400);
+        assert_eq!(parse_time("90m", now)?, now - 90 * 60);
+        assert_eq!(parse_time("1699999999", now)?, 1_699_999_999);
+        let day = parse_time("2024-05-01", now)?;
+        assert_eq!(parse_time("2024-05-01 01:30", now)?, day + 5400);
+        assert!(parse_time("3y", now).is_err());
+        assert_eq!(parse_time_end("2024-05-01", now)?, parse_time("2024-05-02", now)?);
+        assert_eq!(parse_time_end("2024-05-01 01:30", now)?, day + 5400);
+        assert_eq!(parse_time_end("7d", now)?, now - 7 * 86_400);
+        assert!(parse_time("yesterday-ish", now).is_err());
+        assert_eq!(format_relative(now - 30, now), "just now");
+        assert_eq!(format_relative(now - 7200, now), "2h ago");
+        assert_eq!(format_relative(now - 90 * 86_400, now), "3mo ago");
+        Ok(())
+    }
+}
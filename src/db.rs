@@ -1,6 +1,9 @@
+use crate::{query, rank, text};
 use anyhow::{Context, Result};
-use rusqlite::{params, Connection, OptionalExtension};
-use std::collections::HashSet;
+use rusqlite::types::Value;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 #[derive(Debug, Clone)]
@@ -12,6 +15,16 @@ pub struct RepoMeta {
     pub last_scan_ts: i64,
     pub readme_excerpt: Option<String>,
     pub origin_url: Option<String>,
+    pub fingerprint: Option<String>,
+    /// One of `normal`, `worktree`, `submodule`, `bare`.
+    pub kind: String,
+    /// Main repo of a worktree, or superproject of a submodule.
+    pub parent_path: Option<String>,
+    pub head_oid: Option<String>,
+    /// Stable across moves: root commit plus normalized origin (see `scan::repo_identity`).
+    pub identity: Option<String>,
+    /// Main language guessed from marker files (see `scan::detect_language`).
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -25,6 +38,90 @@ pub struct RepoRow {
     pub readme_excerpt: Option<String>,
     pub origin_url: Option<String>,
     pub last_access_ts: Option<i64>,
+    pub kind: String,
+    pub parent_path: Option<String>,
+    /// Set while the repo sits in the trash (pruned but not purged).
+    pub removed_ts: Option<i64>,
+    pub removed_reason: Option<String>,
+    pub language: Option<String>,
+    /// Sorted before everything else in listings.
+    pub pinned: bool,
+    /// Left out of listings and search unless asked for.
+    pub archived: bool,
+}
+
+/// A `rank_repos` hit.
+#[derive(Debug, Clone)]
+pub struct RankedRepo {
+    pub path: String,
+    pub name: String,
+    pub aliases: Vec<String>,
+    /// Match quality weighted by frecency; only meaningful relative to other hits.
+    pub score: f64,
+}
+
+/// A path the scanner could not read; the scan carries on without it.
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub path: String,
+    /// One of `walk`, `open`, `readme`, `metadata`.
+    pub kind: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanErrorRow {
+    pub root: String,
+    pub path: String,
+    pub kind: String,
+    pub message: String,
+    pub ts: i64,
+}
+
+/// A per-repo difference noticed while a scan wrote its results.
+#[derive(Debug, Clone)]
+pub struct RepoChange {
+    pub path: String,
+    pub name: String,
+    /// One of `new`, `moved`, `restored`, `removed`, `head_moved`, `new_commits`,
+    /// `branch_changed`, `origin_changed`.
+    pub kind: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanRun {
+    pub root: String,
+    pub started_ts: i64,
+    pub finished_ts: i64,
+    pub duration_ms: i64,
+    pub full: bool,
+    pub indexed: usize,
+    pub skipped: usize,
+    pub pruned: usize,
+    pub errors: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanRunRow {
+    pub id: i64,
+    pub run: ScanRun,
+    pub changes: Vec<RepoChange>,
+}
+
+/// A node of the tag tree: a tag, or a namespace (`team` for `team/payments`) that may
+/// not be a tag itself.
+#[derive(Debug, Clone)]
+pub struct TagCount {
+    pub name: String,
+    /// Repos carrying exactly this tag.
+    pub count: usize,
+    /// Repos carrying this tag or any tag below it.
+    pub total: usize,
+    pub color: Option<String>,
+    pub description: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +130,16 @@ pub struct RepoWithTags {
     pub tags: Vec<String>,
 }
 
+/// A full-text search hit: the repo, its bm25 rank (lower is better) and the text of
+/// each field the query matched, with the matches wrapped in [`MARK_START`]/[`MARK_END`].
+#[derive(Debug, Clone)]
+pub struct RepoHit {
+    pub repo: RepoRow,
+    pub tags: Vec<String>,
+    pub rank: f64,
+    pub highlights: Vec<(&'static str, String)>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Paged<T> {
     pub total: usize,
@@ -58,6 +165,7 @@ pub struct CommitIndexRow {
     pub email: Option<String>,
     pub summary: Option<String>,
     pub message: Option<String>,
+    pub parent_count: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -70,21 +178,408 @@ pub struct CommitHit {
     pub oid: String,
     pub time: Option<i64>,
     pub summary: Option<String>,
-    pub message: Option<String>,
+    pub author: Option<String>,
+    pub email: Option<String>,
+    /// bm25 rank scaled by recency, lower is better (0 without a query).
+    pub rank: f64,
+    /// `summary` (whole) and `message` (snippet) when the query matched them, marked
+    /// like [`RepoHit::highlights`].
+    pub highlights: Vec<(&'static str, String)>,
 }
 
 pub struct Db {
     conn: Connection,
 }
 
+const UPSERT_REPO_SQL: &str = r#"
+    INSERT INTO repos (path, name, default_branch, last_commit_ts, last_scan_ts, readme_excerpt, origin_url, fingerprint, kind, parent_path, head_oid, identity, language)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+    ON CONFLICT(path) DO UPDATE SET
+      name = excluded.name,
+      default_branch = excluded.default_branch,
+      last_commit_ts = excluded.last_commit_ts,
+      last_scan_ts = excluded.last_scan_ts,
+      readme_excerpt = excluded.readme_excerpt,
+      origin_url = excluded.origin_url,
+      fingerprint = excluded.fingerprint,
+      kind = excluded.kind,
+      parent_path = excluded.parent_path,
+      head_oid = excluded.head_oid,
+      identity = excluded.identity,
+      language = excluded.language,
+      removed_ts = NULL,
+      removed_reason = NULL
+"#;
+
+/// Columns mapped by `repo_row`; queries must alias `repos` as `r`.
+const REPO_COLS: &str = "r.id, r.path, r.name, r.default_branch, r.last_commit_ts, r.last_scan_ts, \
+    r.readme_excerpt, r.origin_url, r.last_access_ts, r.kind, r.parent_path, r.removed_ts, r.removed_reason, r.language, \
+    r.pinned, r.archived";
+const REPO_COL_COUNT: usize = 16;
+
+/// Excludes repos sitting in the trash.
+const LIVE_SQL: &str = "r.removed_ts IS NULL";
+
+/// Surround the matched words in search highlights; control characters never occur in
+/// indexed text, so callers can escape the rest and turn these into markup.
+pub const MARK_START: char = '\u{2}';
+pub const MARK_END: char = '\u{3}';
+
+/// Columns of `repos_fts` with their bm25 weight (a hit in the name counts most, then
+/// tags, README and notes, then the path) and whether a hit shows as a snippet rather
+/// than the whole text. `notes` holds the note and the custom fields.
+const REPO_FTS_COLUMNS: [(&str, f64, bool); 5] = [
+    ("name", 10.0, false),
+    ("tags", 5.0, false),
+    ("readme", 2.0, true),
+    ("notes", 1.5, true),
+    ("path", 1.0, false),
+];
+
+/// `repos_fts` values of repos aliased `r`, in column order after the rowid. Text goes
+/// through `split_cjk` (see `text::split_cjk`) so Chinese matches inside sentences.
+const REPO_FTS_VALUES: &str = "r.id, split_cjk(r.name), \
+    split_cjk(COALESCE((SELECT GROUP_CONCAT(t.name, ' ') FROM repo_tags rt JOIN tags t ON t.id = rt.tag_id \
+      WHERE rt.repo_id = r.id), '')), \
+    split_cjk(COALESCE(r.readme_excerpt, '')), \
+    split_cjk(COALESCE((SELECT body FROM repo_notes WHERE repo_id = r.id), '') || ' ' || \
+      COALESCE((SELECT GROUP_CONCAT(key || ' ' || value, ' ') FROM repo_fields WHERE repo_id = r.id), '')), \
+    split_cjk(r.path)";
+
+/// Bumped whenever what goes into the full-text indexes changes; older indexes and their
+/// triggers are dropped and rebuilt. Kept in `PRAGMA user_version`.
+const SEARCH_INDEX_VERSION: i64 = 1;
+
+/// Weights of the `commits_fts` columns (summary, message) in bm25.
+const COMMIT_FTS_WEIGHTS: &str = "2.0, 1.0";
+
+/// Age at which a commit's relevance counts half as much as a commit made today.
+const COMMIT_RECENCY_HALF_SECS: i64 = 180 * 86_400;
+
+/// Triggers keeping the external-content `commits_fts` in step with `commits`. Values
+/// must match what the `commits_fts_src` view returns.
+const COMMIT_FTS_TRIGGERS_SQL: &str = r#"
+    CREATE TRIGGER IF NOT EXISTS commits_fts_ai AFTER INSERT ON commits BEGIN
+      INSERT INTO commits_fts (rowid, summary, message)
+      VALUES (new.id, split_cjk(new.summary), split_cjk(new.message));
+    END;
+    CREATE TRIGGER IF NOT EXISTS commits_fts_ad AFTER DELETE ON commits BEGIN
+      INSERT INTO commits_fts (commits_fts, rowid, summary, message)
+      VALUES ('delete', old.id, split_cjk(old.summary), split_cjk(old.message));
+    END;
+    CREATE TRIGGER IF NOT EXISTS commits_fts_au AFTER UPDATE OF summary, message ON commits BEGIN
+      INSERT INTO commits_fts (commits_fts, rowid, summary, message)
+      VALUES ('delete', old.id, split_cjk(old.summary), split_cjk(old.message));
+      INSERT INTO commits_fts (rowid, summary, message)
+      VALUES (new.id, split_cjk(new.summary), split_cjk(new.message));
+    END;
+"#;
+
+/// Undoes `split_cjk` in highlighted text, merging the marks of neighbouring characters.
+fn join_cjk(marked: String) -> String {
+    if !marked.contains(text::CJK_SEPARATOR) {
+        return marked;
+    }
+    marked
+        .replace(&format!("{MARK_END}{}{MARK_START}", text::CJK_SEPARATOR), "")
+        .replace(text::CJK_SEPARATOR, "")
+}
+
+/// Re-indexes the repos whose id satisfies `cond` (`= new.id`, `IN (...)`).
+fn repo_fts_refresh_sql(cond: &str) -> String {
+    format!(
+        "DELETE FROM repos_fts WHERE rowid {cond}; \
+         INSERT INTO repos_fts (rowid, name, tags, readme, notes, path) \
+         SELECT {REPO_FTS_VALUES} FROM repos r WHERE r.id {cond};"
+    )
+}
+
+/// Triggers keeping `repos_fts` in step with repos, their tags, notes and fields.
+fn repo_fts_triggers_sql() -> String {
+    let triggers = [
+        ("repos_fts_ai", "AFTER INSERT ON repos", repo_fts_refresh_sql("= new.id")),
+        (
+            "repos_fts_au",
+            "AFTER UPDATE OF name, path, readme_excerpt ON repos WHEN old.name IS NOT new.name \
+             OR old.path IS NOT new.path OR old.readme_excerpt IS NOT new.readme_excerpt",
+            repo_fts_refresh_sql("= new.id"),
+        ),
+        ("repos_fts_ad", "AFTER DELETE ON repos", "DELETE FROM repos_fts WHERE rowid = old.id;".to_string()),
+        ("repo_tags_fts_ai", "AFTER INSERT ON repo_tags", repo_fts_refresh_sql("= new.repo_id")),
+        ("repo_tags_fts_ad", "AFTER DELETE ON repo_tags", repo_fts_refresh_sql("= old.repo_id")),
+        (
+            "tags_fts_au",
+            "AFTER UPDATE OF name ON tags",
+            repo_fts_refresh_sql("IN (SELECT repo_id FROM repo_tags WHERE tag_id = new.id)"),
+        ),
+        ("repo_notes_fts_ai", "AFTER INSERT ON repo_notes", repo_fts_refresh_sql("= new.repo_id")),
+        ("repo_notes_fts_au", "AFTER UPDATE ON repo_notes", repo_fts_refresh_sql("= new.repo_id")),
+        ("repo_notes_fts_ad", "AFTER DELETE ON repo_notes", repo_fts_refresh_sql("= old.repo_id")),
+        ("repo_fields_fts_ai", "AFTER INSERT ON repo_fields", repo_fts_refresh_sql("= new.repo_id")),
+        ("repo_fields_fts_au", "AFTER UPDATE ON repo_fields", repo_fts_refresh_sql("= new.repo_id")),
+        ("repo_fields_fts_ad", "AFTER DELETE ON repo_fields", repo_fts_refresh_sql("= old.repo_id")),
+    ];
+    triggers
+        .iter()
+        .map(|(name, event, body)| format!("CREATE TRIGGER IF NOT EXISTS {name} {event} BEGIN {body} END;\n"))
+        .collect()
+}
+
+/// Matches `t2.name` against parameter `?n`; a namespace matches the tags below it, so
+/// `team` selects `team/payments` too.
+fn tag_match_sql(n: usize) -> String {
+    format!("(t2.name = ?{n} OR substr(t2.name, 1, length(?{n}) + 1) = ?{n} || '/')")
+}
+
+/// Filter for listing and search: a repo must carry every tag in `all`, at least one
+/// tag in `any` (when given) and none of `not`, and match every entry of `fields`.
+#[derive(Debug, Clone, Default)]
+pub struct RepoFilter {
+    pub all: Vec<String>,
+    pub any: Vec<String>,
+    pub not: Vec<String>,
+    /// Custom field key, with the value it must equal (ignoring case); without a value
+    /// the repo only needs to have the field.
+    pub fields: Vec<(String, Option<String>)>,
+    /// Also return archived repos.
+    pub include_archived: bool,
+    /// Languages, ignoring case.
+    pub languages: AnyOf,
+    /// Directories the repo sits in (or is).
+    pub roots: AnyOf,
+    /// Substrings of the origin URL as written or normalized (`github.com/org/repo`),
+    /// ignoring case.
+    pub origins: AnyOf,
+    pub pinned: Option<bool>,
+    /// Only archived (or only unarchived) repos; overrides `include_archived`.
+    pub archived: Option<bool>,
+}
+
+/// Values of a repeatable search filter: a row must match one of `any` (when given) and
+/// none of `not`.
+#[derive(Debug, Clone, Default)]
+pub struct AnyOf {
+    pub any: Vec<String>,
+    pub not: Vec<String>,
+}
+
+impl AnyOf {
+    /// Conditions built from `cond(n)`, which tests parameter `?n`; pushes the values onto
+    /// `args`.
+    fn sql(&self, args: &mut Vec<Value>, cond: impl Fn(usize) -> String) -> Vec<String> {
+        let mut one = |v: &String| {
+            args.push(Value::from(v.clone()));
+            cond(args.len())
+        };
+        let mut parts = Vec::new();
+        if !self.any.is_empty() {
+            let any = self.any.iter().map(&mut one).collect::<Vec<_>>();
+            parts.push(format!("({})", any.join(" OR ")));
+        }
+        for v in &self.not {
+            parts.push(format!("NOT {}", one(v)));
+        }
+        parts
+    }
+}
+
+/// Filter for commit search, on commits aliased `c` of repos aliased `r`.
+#[derive(Debug, Clone, Default)]
+pub struct CommitFilter {
+    /// Substrings of the author name or email, ignoring case.
+    pub authors: AnyOf,
+    /// Substrings of the author email, ignoring case.
+    pub emails: AnyOf,
+    /// Whole author names or emails, ignoring case (`is:mine` with `my_identities`).
+    pub identities: AnyOf,
+    /// Substrings of the branch or ref name, ignoring case.
+    pub branches: AnyOf,
+    /// Substrings of the repo name or path, ignoring case.
+    pub repos: AnyOf,
+    /// Committed at or after this unix time.
+    pub after: Option<i64>,
+    /// Committed before this unix time.
+    pub before: Option<i64>,
+    /// Only merge commits, or only other commits.
+    pub merge: Option<bool>,
+}
+
+impl CommitFilter {
+    fn sql(&self, args: &mut Vec<Value>) -> String {
+        let mut parts = self.authors.sql(args, |n| {
+            format!(
+                "(instr(fold(COALESCE(c.author, '')), fold(?{n})) > 0 \
+                 OR instr(fold(COALESCE(c.email, '')), fold(?{n})) > 0)"
+            )
+        });
+        parts.extend(self.emails.sql(args, |n| format!("(instr(fold(COALESCE(c.email, '')), fold(?{n})) > 0)")));
+        parts.extend(self.identities.sql(args, |n| {
+            format!("(fold(COALESCE(c.author, '')) = fold(?{n}) OR fold(COALESCE(c.email, '')) = fold(?{n}))")
+        }));
+        parts.extend(self.branches.sql(args, |n| {
+            format!("(instr(fold(c.branch_name), fold(?{n})) > 0 OR instr(fold(c.refname), fold(?{n})) > 0)")
+        }));
+        parts.extend(self.repos.sql(args, |n| {
+            format!("(instr(fold(r.name), fold(?{n})) > 0 OR instr(fold(r.path), fold(?{n})) > 0)")
+        }));
+        if let Some(after) = self.after {
+            args.push(Value::from(after));
+            parts.push(format!("c.time >= ?{}", args.len()));
+        }
+        if let Some(before) = self.before {
+            args.push(Value::from(before));
+            parts.push(format!("c.time < ?{}", args.len()));
+        }
+        if let Some(merge) = self.merge {
+            parts.push(format!("{}(COALESCE(c.parent_count, 1) > 1)", if merge { "" } else { "NOT " }));
+        }
+        if parts.is_empty() {
+            "1".to_string()
+        } else {
+            format!("({})", parts.join(" AND "))
+        }
+    }
+}
+
+impl RepoFilter {
+    /// Condition on repos aliased `r`, pushing its parameters onto `args`.
+    fn sql(&self, args: &mut Vec<Value>) -> String {
+        let mut has_one_of = |tags: &[String]| {
+            let names = tags
+                .iter()
+                .map(|t| {
+                    args.push(Value::from(t.clone()));
+                    tag_match_sql(args.len())
+                })
+                .collect::<Vec<_>>()
+                .join(" OR ");
+            format!(
+                "EXISTS (SELECT 1 FROM repo_tags rt2 JOIN tags t2 ON t2.id = rt2.tag_id \
+                 WHERE rt2.repo_id = r.id AND ({names}))"
+            )
+        };
+        let mut parts = self
+            .all
+            .iter()
+            .map(|t| has_one_of(std::slice::from_ref(t)))
+            .collect::<Vec<_>>();
+        if !self.any.is_empty() {
+            parts.push(has_one_of(&self.any));
+        }
+        for t in &self.not {
+            parts.push(format!("NOT {}", has_one_of(std::slice::from_ref(t))));
+        }
+        for (key, value) in &self.fields {
+            args.push(Value::from(key.clone()));
+            let mut cond = format!("f2.key = ?{}", args.len());
+            if let Some(value) = value {
+                args.push(Value::from(value.clone()));
+                cond.push_str(&format!(" AND fold(f2.value) = fold(?{})", args.len()));
+            }
+            parts.push(format!(
+                "EXISTS (SELECT 1 FROM repo_fields f2 WHERE f2.repo_id = r.id AND {cond})"
+            ));
+        }
+        parts.extend(self.languages.sql(args, |n| format!("(fold(COALESCE(r.language, '')) = fold(?{n}))")));
+        let sep = std::path::MAIN_SEPARATOR;
+        parts.extend(self.roots.sql(args, |n| {
+            format!(
+                "(r.path = ?{n} OR substr(r.path, 1, length(rtrim(?{n}, '{sep}')) + 1) = rtrim(?{n}, '{sep}') || '{sep}')"
+            )
+        }));
+        // The identity ends in the normalized origin (`root@github.com/org/repo`), so
+        // `github.com/org` matches scp-style URLs too.
+        parts.extend(self.origins.sql(args, |n| {
+            format!(
+                "(instr(fold(COALESCE(r.origin_url, '')), fold(?{n})) > 0 \
+                 OR (instr(COALESCE(r.identity, ''), '@') > 0 \
+                     AND instr(substr(r.identity, instr(r.identity, '@') + 1), fold(?{n})) > 0))"
+            )
+        }));
+        if let Some(pinned) = self.pinned {
+            parts.push(format!("r.pinned = {}", pinned as i64));
+        }
+        match self.archived {
+            Some(archived) => parts.push(format!("r.archived = {}", archived as i64)),
+            None if !self.include_archived => parts.push("r.archived = 0".to_string()),
+            None => {}
+        }
+        if parts.is_empty() {
+            "1".to_string()
+        } else {
+            format!("({})", parts.join(" AND "))
+        }
+    }
+}
+
+/// Hides linked worktrees whose main repo is indexed; they are listed under it instead.
+const TOP_LEVEL_SQL: &str = "NOT (r.kind = 'worktree' AND EXISTS \
+    (SELECT 1 FROM repos p WHERE p.path = r.parent_path AND p.removed_ts IS NULL))";
+
+fn repo_row(r: &rusqlite::Row<'_>) -> rusqlite::Result<RepoRow> {
+    Ok(RepoRow {
+        id: r.get(0)?,
+        path: r.get(1)?,
+        name: r.get(2)?,
+        default_branch: r.get(3)?,
+        last_commit_ts: r.get(4)?,
+        last_scan_ts: r.get(5)?,
+        readme_excerpt: r.get(6)?,
+        origin_url: r.get(7)?,
+        last_access_ts: r.get(8)?,
+        kind: r.get(9)?,
+        parent_path: r.get(10)?,
+        removed_ts: r.get(11)?,
+        removed_reason: r.get(12)?,
+        language: r.get(13)?,
+        pinned: r.get(14)?,
+        archived: r.get(15)?,
+    })
+}
+
+/// Maps `REPO_COLS` followed by a comma-joined `tags` column.
+fn repo_with_tags_row(r: &rusqlite::Row<'_>) -> rusqlite::Result<RepoWithTags> {
+    let repo = repo_row(r)?;
+    let tags = r.get::<_, String>(REPO_COL_COUNT)?;
+    let tags = tags
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+    Ok(RepoWithTags { repo, tags })
+}
+
 impl Db {
     pub fn open(path: &Path) -> Result<Self> {
         let conn = Connection::open(path).with_context(|| format!("open db {}", path.display()))?;
         conn.pragma_update(None, "foreign_keys", "ON")?;
+        // The watcher and Web jobs may write at the same time as a CLI scan.
+        conn.busy_timeout(std::time::Duration::from_secs(10))?;
+        // Used by the search SQL and by the full-text index triggers.
+        let flags = FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC;
+        conn.create_scalar_function("fold", 1, flags, |ctx| {
+            Ok(ctx.get::<Option<String>>(0)?.map(|s| text::fold(&s)))
+        })?;
+        conn.create_scalar_function("split_cjk", 1, flags, |ctx| {
+            Ok(ctx.get::<Option<String>>(0)?.map(|s| text::split_cjk(&s)))
+        })?;
         Ok(Self { conn })
     }
 
     pub fn init_schema(&self) -> Result<()> {
+        let index_version: i64 = self.conn.pragma_query_value(None, "user_version", |r| r.get(0))?;
+        if index_version < SEARCH_INDEX_VERSION {
+            self.drop_search_indexes()?;
+        }
+        let commits_fts_existed = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'commits_fts'",
+                [],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
         self.conn.execute_batch(
             r#"
             CREATE TABLE IF NOT EXISTS repos (
@@ -96,17 +591,31 @@ impl Db {
               last_scan_ts    INTEGER NOT NULL,
               readme_excerpt  TEXT,
               origin_url      TEXT,
-              last_access_ts  INTEGER
+              last_access_ts  INTEGER,
+              fingerprint     TEXT,
+              kind            TEXT NOT NULL DEFAULT 'normal',
+              parent_path     TEXT,
+              head_oid        TEXT,
+              identity        TEXT,
+              removed_ts      INTEGER,
+              removed_reason  TEXT,
+              language        TEXT,
+              pinned          INTEGER NOT NULL DEFAULT 0,
+              archived        INTEGER NOT NULL DEFAULT 0
             );
 
             CREATE TABLE IF NOT EXISTS tags (
-              id    INTEGER PRIMARY KEY AUTOINCREMENT,
-              name  TEXT NOT NULL UNIQUE
+              id           INTEGER PRIMARY KEY AUTOINCREMENT,
+              name         TEXT NOT NULL UNIQUE,
+              color        TEXT,
+              description  TEXT
             );
 
+            -- source: `manual`, or `rule` for tags recomputed from config (tag rules, root auto-tags).
             CREATE TABLE IF NOT EXISTS repo_tags (
               repo_id INTEGER NOT NULL,
               tag_id  INTEGER NOT NULL,
+              source  TEXT NOT NULL DEFAULT 'manual',
               PRIMARY KEY (repo_id, tag_id),
               FOREIGN KEY (repo_id) REFERENCES repos(id) ON DELETE CASCADE,
               FOREIGN KEY (tag_id)  REFERENCES tags(id) ON DELETE CASCADE
@@ -139,6 +648,7 @@ impl Db {
               email       TEXT,
               summary     TEXT,
               message     TEXT,
+              parent_count INTEGER,
               UNIQUE(repo_id, refname, oid),
               FOREIGN KEY (repo_id) REFERENCES repos(id) ON DELETE CASCADE
             );
@@ -146,529 +656,655 @@ impl Db {
             CREATE INDEX IF NOT EXISTS idx_commits_repo_time ON commits(repo_id, time);
             CREATE INDEX IF NOT EXISTS idx_commits_repo_ref_time ON commits(repo_id, refname, time);
             CREATE INDEX IF NOT EXISTS idx_commits_branch_name ON commits(branch_name);
+
+            CREATE TABLE IF NOT EXISTS scan_errors (
+              id       INTEGER PRIMARY KEY AUTOINCREMENT,
+              root     TEXT NOT NULL,
+              path     TEXT NOT NULL,
+              kind     TEXT NOT NULL,
+              message  TEXT NOT NULL,
+              ts       INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_scan_errors_root ON scan_errors(root);
+
+            CREATE TABLE IF NOT EXISTS scan_runs (
+              id           INTEGER PRIMARY KEY AUTOINCREMENT,
+              root         TEXT NOT NULL,
+              started_ts   INTEGER NOT NULL,
+              finished_ts  INTEGER NOT NULL,
+              duration_ms  INTEGER NOT NULL,
+              full         INTEGER NOT NULL DEFAULT 0,
+              indexed      INTEGER NOT NULL,
+              skipped      INTEGER NOT NULL,
+              pruned       INTEGER NOT NULL,
+              errors       INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS repo_events (
+              id         INTEGER PRIMARY KEY AUTOINCREMENT,
+              run_id     INTEGER NOT NULL,
+              path       TEXT NOT NULL,
+              name       TEXT NOT NULL,
+              kind       TEXT NOT NULL,
+              old_value  TEXT,
+              new_value  TEXT,
+              detail     TEXT,
+              FOREIGN KEY (run_id) REFERENCES scan_runs(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_scan_runs_started ON scan_runs(started_ts);
+            CREATE INDEX IF NOT EXISTS idx_repo_events_run ON repo_events(run_id);
+
+            -- Markdown notes, one per repo.
+            CREATE TABLE IF NOT EXISTS repo_notes (
+              repo_id     INTEGER PRIMARY KEY,
+              body        TEXT NOT NULL,
+              updated_ts  INTEGER NOT NULL,
+              FOREIGN KEY (repo_id) REFERENCES repos(id) ON DELETE CASCADE
+            );
+
+            -- Custom key-value fields (owner, status, ticket, lifecycle, ...).
+            CREATE TABLE IF NOT EXISTS repo_fields (
+              repo_id  INTEGER NOT NULL,
+              key      TEXT NOT NULL,
+              value    TEXT NOT NULL,
+              PRIMARY KEY (repo_id, key),
+              FOREIGN KEY (repo_id) REFERENCES repos(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_repo_fields_key ON repo_fields(key, value);
+
+            -- One row per `open`; feeds the frecency rank used to resolve repo queries.
+            CREATE TABLE IF NOT EXISTS repo_access (
+              id       INTEGER PRIMARY KEY AUTOINCREMENT,
+              repo_id  INTEGER NOT NULL,
+              ts       INTEGER NOT NULL,
+              FOREIGN KEY (repo_id) REFERENCES repos(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_repo_access_repo ON repo_access(repo_id, ts);
+
+            -- User-defined short names; an exact alias always wins when resolving a query.
+            CREATE TABLE IF NOT EXISTS repo_aliases (
+              alias    TEXT PRIMARY KEY,
+              repo_id  INTEGER NOT NULL,
+              FOREIGN KEY (repo_id) REFERENCES repos(id) ON DELETE CASCADE
+            );
+
+            -- Full-text index of repos (rowid = repos.id), kept in sync by triggers.
+            CREATE VIRTUAL TABLE IF NOT EXISTS repos_fts USING fts5(
+              name, tags, readme, notes, path,
+              tokenize = 'unicode61 remove_diacritics 2'
+            );
+
+            -- Full-text index over the commit index; reads its text from `commits`.
+            CREATE VIEW IF NOT EXISTS commits_fts_src AS
+              SELECT id, split_cjk(summary) AS summary, split_cjk(message) AS message FROM commits;
+            CREATE VIRTUAL TABLE IF NOT EXISTS commits_fts USING fts5(
+              summary, message,
+              content = 'commits_fts_src', content_rowid = 'id',
+              tokenize = 'unicode61 remove_diacritics 2'
+            );
             "#,
         )?;
         // Schema migration for older DBs (SQLite has no IF NOT EXISTS for ADD COLUMN).
         let _ = self.conn.execute("ALTER TABLE repos ADD COLUMN origin_url TEXT", []);
+        let _ = self.conn.execute("ALTER TABLE repos ADD COLUMN fingerprint TEXT", []);
+        let _ = self.conn.execute(
+            "ALTER TABLE repos ADD COLUMN kind TEXT NOT NULL DEFAULT 'normal'",
+            [],
+        );
+        let _ = self.conn.execute("ALTER TABLE repos ADD COLUMN parent_path TEXT", []);
+        let _ = self.conn.execute("ALTER TABLE repos ADD COLUMN head_oid TEXT", []);
+        let _ = self.conn.execute("ALTER TABLE repos ADD COLUMN identity TEXT", []);
+        let _ = self.conn.execute("ALTER TABLE repos ADD COLUMN removed_ts INTEGER", []);
+        let _ = self.conn.execute("ALTER TABLE repos ADD COLUMN removed_reason TEXT", []);
+        let _ = self.conn.execute("ALTER TABLE repos ADD COLUMN language TEXT", []);
+        let _ = self.conn.execute(
+            "ALTER TABLE repos ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        let _ = self.conn.execute(
+            "ALTER TABLE repos ADD COLUMN archived INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        let _ = self.conn.execute("ALTER TABLE commits ADD COLUMN parent_count INTEGER", []);
+        let _ = self.conn.execute("ALTER TABLE tags ADD COLUMN color TEXT", []);
+        let _ = self.conn.execute("ALTER TABLE tags ADD COLUMN description TEXT", []);
+        let _ = self.conn.execute(
+            "ALTER TABLE repo_tags ADD COLUMN source TEXT NOT NULL DEFAULT 'manual'",
+            [],
+        );
+        // Seed the access history from the single timestamp older versions kept.
+        self.conn.execute(
+            "INSERT INTO repo_access (repo_id, ts) \
+             SELECT id, last_access_ts FROM repos \
+             WHERE last_access_ts IS NOT NULL AND NOT EXISTS (SELECT 1 FROM repo_access)",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_repos_identity ON repos(identity)",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_repos_parent ON repos(parent_path)",
+            [],
+        )?;
+        self.conn.execute_batch(&repo_fts_triggers_sql())?;
+        // Index repos from before the full-text index existed.
+        self.conn.execute(
+            &format!(
+                "INSERT INTO repos_fts (rowid, name, tags, readme, notes, path) \
+                 SELECT {REPO_FTS_VALUES} FROM repos r \
+                 WHERE r.id NOT IN (SELECT rowid FROM repos_fts)"
+            ),
+            [],
+        )?;
+        self.conn.execute_batch(COMMIT_FTS_TRIGGERS_SQL)?;
+        if !commits_fts_existed {
+            // Index commits stored before the full-text index existed.
+            self.conn.execute("INSERT INTO commits_fts (commits_fts) VALUES ('rebuild')", [])?;
+        }
+        self.conn.pragma_update(None, "user_version", SEARCH_INDEX_VERSION)?;
         Ok(())
     }
 
-    pub fn upsert_repo(&self, meta: &RepoMeta) -> Result<()> {
-        self.conn.execute(
-            r#"
-            INSERT INTO repos (path, name, default_branch, last_commit_ts, last_scan_ts, readme_excerpt, origin_url)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
-            ON CONFLICT(path) DO UPDATE SET
-              name = excluded.name,
-              default_branch = excluded.default_branch,
-              last_commit_ts = excluded.last_commit_ts,
-              last_scan_ts = excluded.last_scan_ts,
-              readme_excerpt = excluded.readme_excerpt,
-              origin_url = excluded.origin_url
-            "#,
-            params![
-                meta.path,
-                meta.name,
-                meta.default_branch,
-                meta.last_commit_ts,
-                meta.last_scan_ts,
-                meta.readme_excerpt,
-                meta.origin_url
-            ],
+    /// Drops both full-text indexes with their triggers; `init_schema` rebuilds them.
+    fn drop_search_indexes(&self) -> Result<()> {
+        let triggers = {
+            let mut stmt = self.conn.prepare(
+                "SELECT name FROM sqlite_master WHERE type = 'trigger' AND name GLOB '*_fts_a[iud]'",
+            )?;
+            let names = stmt.query_map([], |r| r.get::<_, String>(0))?;
+            names.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        for name in triggers {
+            self.conn.execute(&format!("DROP TRIGGER IF EXISTS \"{name}\""), [])?;
+        }
+        self.conn.execute_batch(
+            "DROP TABLE IF EXISTS repos_fts; DROP TABLE IF EXISTS commits_fts; DROP VIEW IF EXISTS commits_fts_src;",
         )?;
         Ok(())
     }
 
-    pub fn list_repos(&self, tag: Option<&str>, recent: bool) -> Result<Vec<RepoRow>> {
-        let mut rows = Vec::new();
-        if let Some(tag) = tag {
-            let order = if recent {
-                "ORDER BY COALESCE(r.last_access_ts, 0) DESC, r.name ASC"
-            } else {
-                "ORDER BY r.name ASC"
-            };
-            let sql = format!(
-                r#"
-                SELECT r.id, r.path, r.name, r.default_branch, r.last_commit_ts, r.last_scan_ts, r.readme_excerpt, r.origin_url, r.last_access_ts
-                FROM repos r
-                JOIN repo_tags rt ON rt.repo_id = r.id
-                JOIN tags t ON t.id = rt.tag_id
-                WHERE t.name = ?1
-                {order}
-                "#
-            );
-            let mut stmt = self.conn.prepare(&sql)?;
-            let iter = stmt.query_map([tag], |r| {
-                Ok(RepoRow {
-                    id: r.get(0)?,
-                    path: r.get(1)?,
-                    name: r.get(2)?,
-                    default_branch: r.get(3)?,
-                    last_commit_ts: r.get(4)?,
-                    last_scan_ts: r.get(5)?,
-                    readme_excerpt: r.get(6)?,
-                    origin_url: r.get(7)?,
-                    last_access_ts: r.get(8)?,
-                })
-            })?;
-            for r in iter {
-                rows.push(r?);
-            }
-        } else {
-            let order = if recent {
-                "ORDER BY COALESCE(last_access_ts, 0) DESC, name ASC"
-            } else {
-                "ORDER BY name ASC"
-            };
-            let sql = format!(
-                "SELECT id, path, name, default_branch, last_commit_ts, last_scan_ts, readme_excerpt, origin_url, last_access_ts FROM repos {order}"
-            );
-            let mut stmt = self.conn.prepare(&sql)?;
-            let iter = stmt.query_map([], |r| {
-                Ok(RepoRow {
-                    id: r.get(0)?,
-                    path: r.get(1)?,
-                    name: r.get(2)?,
-                    default_branch: r.get(3)?,
-                    last_commit_ts: r.get(4)?,
-                    last_scan_ts: r.get(5)?,
-                    readme_excerpt: r.get(6)?,
-                    origin_url: r.get(7)?,
-                    last_access_ts: r.get(8)?,
-                })
-            })?;
-            for r in iter {
-                rows.push(r?);
+    /// Upserts a batch of repos inside one transaction (used by the scan pipeline) and
+    /// returns how each repo differs from its previous row. HEAD moves are reported as
+    /// `head_moved`; the scanner refines them into `new_commits` where it can.
+    ///
+    /// A path seen for the first time whose identity matches a row whose path no longer
+    /// exists is treated as a move: that row is re-pointed, keeping its id and with it
+    /// tags, access history and the commit index. Rows in the trash are candidates too,
+    /// and any row written here leaves the trash (reported as `restored`).
+    pub fn upsert_repos(&self, metas: &[RepoMeta]) -> Result<Vec<RepoChange>> {
+        let mut changes = Vec::new();
+        if metas.is_empty() {
+            return Ok(changes);
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut prev_stmt = tx.prepare(
+                "SELECT default_branch, origin_url, head_oid, removed_ts FROM repos WHERE path = ?1",
+            )?;
+            let mut moved_stmt = tx.prepare(
+                "SELECT id, path FROM repos WHERE identity = ?1 AND path <> ?2 ORDER BY last_scan_ts DESC",
+            )?;
+            let mut stmt = tx.prepare(UPSERT_REPO_SQL)?;
+            for meta in metas {
+                let mut moved_from = None;
+                if let Some(identity) = &meta.identity {
+                    let exists = prev_stmt.exists([&meta.path])?;
+                    if !exists {
+                        let candidates = moved_stmt
+                            .query_map(params![identity, meta.path], |r| {
+                                Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?))
+                            })?
+                            .collect::<rusqlite::Result<Vec<_>>>()?;
+                        if let Some((id, old_path)) =
+                            candidates.into_iter().find(|(_, p)| !Path::new(p).exists())
+                        {
+                            tx.execute("UPDATE repos SET path = ?1 WHERE id = ?2", params![meta.path, id])?;
+                            moved_from = Some(old_path);
+                        }
+                    }
+                }
+                let prev = prev_stmt
+                    .query_row([&meta.path], |r| {
+                        Ok((
+                            r.get::<_, Option<String>>(0)?,
+                            r.get::<_, Option<String>>(1)?,
+                            r.get::<_, Option<String>>(2)?,
+                            r.get::<_, Option<i64>>(3)?,
+                        ))
+                    })
+                    .optional()?;
+                let change = |kind: &str, old: Option<String>, new: Option<String>| RepoChange {
+                    path: meta.path.clone(),
+                    name: meta.name.clone(),
+                    kind: kind.to_string(),
+                    old_value: old,
+                    new_value: new,
+                    detail: None,
+                };
+                if let Some(old_path) = &moved_from {
+                    changes.push(change("moved", Some(old_path.clone()), Some(meta.path.clone())));
+                }
+                match prev {
+                    None => changes.push(change("new", None, meta.head_oid.clone())),
+                    Some((branch, origin, head, removed_ts)) => {
+                        if removed_ts.is_some() && moved_from.is_none() {
+                            changes.push(change("restored", None, Some(meta.path.clone())));
+                        }
+                        // Rows written before head_oid existed have nothing to compare against.
+                        if head.is_some() && head != meta.head_oid {
+                            changes.push(change("head_moved", head, meta.head_oid.clone()));
+                        }
+                        if branch != meta.default_branch {
+                            changes.push(change("branch_changed", branch, meta.default_branch.clone()));
+                        }
+                        if origin != meta.origin_url {
+                            changes.push(change("origin_changed", origin, meta.origin_url.clone()));
+                        }
+                    }
+                }
+                stmt.execute(params![
+                    meta.path,
+                    meta.name,
+                    meta.default_branch,
+                    meta.last_commit_ts,
+                    meta.last_scan_ts,
+                    meta.readme_excerpt,
+                    meta.origin_url,
+                    meta.fingerprint,
+                    meta.kind,
+                    meta.parent_path,
+                    meta.head_oid,
+                    meta.identity,
+                    meta.language
+                ])?;
             }
         }
-        Ok(rows)
+        tx.commit()?;
+        Ok(changes)
     }
 
-    pub fn list_repos_with_tags(&self, tag: Option<&str>, recent: bool) -> Result<Vec<RepoWithTags>> {
-        let order = if recent {
-            "ORDER BY COALESCE(r.last_access_ts, 0) DESC, r.name ASC"
-        } else {
-            "ORDER BY r.name ASC"
-        };
-
-        let (sql, args): (String, Vec<String>) = if let Some(tag) = tag {
-            (
-                format!(
-                    r#"
-                    SELECT
-                      r.id, r.path, r.name, r.default_branch, r.last_commit_ts, r.last_scan_ts, r.readme_excerpt, r.origin_url, r.last_access_ts,
-                      COALESCE(GROUP_CONCAT(t.name, ','), '') AS tags
-                    FROM repos r
-                    LEFT JOIN repo_tags rt ON rt.repo_id = r.id
-                    LEFT JOIN tags t ON t.id = rt.tag_id
-                    WHERE EXISTS (
-                      SELECT 1 FROM repo_tags rt2
-                      JOIN tags t2 ON t2.id = rt2.tag_id
-                      WHERE rt2.repo_id = r.id AND t2.name = ?1
-                    )
-                    GROUP BY r.id
-                    {order}
-                    "#
-                ),
-                vec![tag.to_string()],
-            )
-        } else {
-            (
-                format!(
-                    r#"
-                    SELECT
-                      r.id, r.path, r.name, r.default_branch, r.last_commit_ts, r.last_scan_ts, r.readme_excerpt, r.origin_url, r.last_access_ts,
-                      COALESCE(GROUP_CONCAT(t.name, ','), '') AS tags
-                    FROM repos r
-                    LEFT JOIN repo_tags rt ON rt.repo_id = r.id
-                    LEFT JOIN tags t ON t.id = rt.tag_id
-                    GROUP BY r.id
-                    {order}
-                    "#
-                ),
-                vec![],
-            )
-        };
-
-        let mut stmt = self.conn.prepare(&sql)?;
-        let mut rows = Vec::new();
-        if args.is_empty() {
-            let iter = stmt.query_map([], |r| {
-                Ok((
-                    RepoRow {
-                        id: r.get(0)?,
-                        path: r.get(1)?,
-                        name: r.get(2)?,
-                        default_branch: r.get(3)?,
-                        last_commit_ts: r.get(4)?,
-                        last_scan_ts: r.get(5)?,
-                        readme_excerpt: r.get(6)?,
-                        origin_url: r.get(7)?,
-                        last_access_ts: r.get(8)?,
-                    },
-                    r.get::<_, String>(9)?,
-                ))
-            })?;
-            for row in iter {
-                let (repo, tags) = row?;
-                let tags = tags
-                    .split(',')
-                    .filter(|s| !s.trim().is_empty())
-                    .map(|s| s.to_string())
-                    .collect::<Vec<_>>();
-                rows.push(RepoWithTags { repo, tags });
-            }
-        } else {
-            let iter = stmt.query_map([args[0].as_str()], |r| {
-                Ok((
-                    RepoRow {
-                        id: r.get(0)?,
-                        path: r.get(1)?,
-                        name: r.get(2)?,
-                        default_branch: r.get(3)?,
-                        last_commit_ts: r.get(4)?,
-                        last_scan_ts: r.get(5)?,
-                        readme_excerpt: r.get(6)?,
-                        origin_url: r.get(7)?,
-                        last_access_ts: r.get(8)?,
-                    },
-                    r.get::<_, String>(9)?,
-                ))
-            })?;
-            for row in iter {
-                let (repo, tags) = row?;
-                let tags = tags
-                    .split(',')
-                    .filter(|s| !s.trim().is_empty())
-                    .map(|s| s.to_string())
-                    .collect::<Vec<_>>();
-                rows.push(RepoWithTags { repo, tags });
+    /// Bumps `last_scan_ts` for repos a scan found unchanged.
+    pub fn touch_repos(&self, paths: &[String], ts: i64) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare("UPDATE repos SET last_scan_ts = ?1 WHERE path = ?2")?;
+            for p in paths {
+                stmt.execute(params![ts, p])?;
             }
         }
-        Ok(rows)
+        tx.commit()?;
+        Ok(())
     }
 
-    pub fn list_repos_with_tags_paged(
-        &self,
-        tag: Option<&str>,
-        recent: bool,
-        page: usize,
-        per_page: usize,
-    ) -> Result<Paged<RepoWithTags>> {
-        let page = page.max(1);
-        let per_page = per_page.clamp(1, 200);
-        let offset = (page - 1) * per_page;
-
-        let total: usize = if let Some(tag) = tag {
-            self.conn.query_row(
-                r#"
-                SELECT COUNT(*)
-                FROM repos r
-                WHERE EXISTS (
-                  SELECT 1 FROM repo_tags rt2
-                  JOIN tags t2 ON t2.id = rt2.tag_id
-                  WHERE rt2.repo_id = r.id AND t2.name = ?1
-                )
-                "#,
-                [tag],
-                |r| r.get::<_, i64>(0),
-            )? as usize
-        } else {
-            self.conn
-                .query_row("SELECT COUNT(*) FROM repos", [], |r| r.get::<_, i64>(0))? as usize
-        };
-
-        let order = if recent {
-            "ORDER BY COALESCE(r.last_access_ts, 0) DESC, r.name ASC"
-        } else {
-            "ORDER BY r.name ASC"
-        };
-
-        let (sql, args): (String, Vec<String>) = if let Some(tag) = tag {
-            (
-                format!(
-                    r#"
-                    SELECT
-                      r.id, r.path, r.name, r.default_branch, r.last_commit_ts, r.last_scan_ts, r.readme_excerpt, r.origin_url, r.last_access_ts,
-                      COALESCE(GROUP_CONCAT(t.name, ','), '') AS tags
-                    FROM repos r
-                    LEFT JOIN repo_tags rt ON rt.repo_id = r.id
-                    LEFT JOIN tags t ON t.id = rt.tag_id
-                    WHERE EXISTS (
-                      SELECT 1 FROM repo_tags rt2
-                      JOIN tags t2 ON t2.id = rt2.tag_id
-                      WHERE rt2.repo_id = r.id AND t2.name = ?1
-                    )
-                    GROUP BY r.id
-                    {order}
-                    LIMIT ?2 OFFSET ?3
-                    "#
-                ),
-                vec![tag.to_string(), per_page.to_string(), offset.to_string()],
-            )
-        } else {
-            (
-                format!(
-                    r#"
-                    SELECT
-                      r.id, r.path, r.name, r.default_branch, r.last_commit_ts, r.last_scan_ts, r.readme_excerpt, r.origin_url, r.last_access_ts,
-                      COALESCE(GROUP_CONCAT(t.name, ','), '') AS tags
-                    FROM repos r
-                    LEFT JOIN repo_tags rt ON rt.repo_id = r.id
-                    LEFT JOIN tags t ON t.id = rt.tag_id
-                    GROUP BY r.id
-                    {order}
-                    LIMIT ?1 OFFSET ?2
-                    "#
-                ),
-                vec![per_page.to_string(), offset.to_string()],
-            )
-        };
-
-        let mut stmt = self.conn.prepare(&sql)?;
-        let mut items = Vec::new();
-
-        if tag.is_some() {
-            let iter = stmt.query_map(
-                params![args[0].as_str(), per_page as i64, offset as i64],
-                |r| {
-                    Ok((
-                        RepoRow {
-                            id: r.get(0)?,
-                            path: r.get(1)?,
-                            name: r.get(2)?,
-                            default_branch: r.get(3)?,
-                            last_commit_ts: r.get(4)?,
-                            last_scan_ts: r.get(5)?,
-                            readme_excerpt: r.get(6)?,
-                            origin_url: r.get(7)?,
-                            last_access_ts: r.get(8)?,
-                        },
-                        r.get::<_, String>(9)?,
-                    ))
-                },
+    /// Replaces the stored errors for `root` with those from its latest scan.
+    pub fn replace_scan_errors(&self, root: &str, errors: &[ScanError], ts: i64) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM scan_errors WHERE root = ?1", [root])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO scan_errors (root, path, kind, message, ts) VALUES (?1, ?2, ?3, ?4, ?5)",
             )?;
-            for row in iter {
-                let (repo, tags) = row?;
-                let tags = tags
-                    .split(',')
-                    .filter(|s| !s.trim().is_empty())
-                    .map(|s| s.to_string())
-                    .collect::<Vec<_>>();
-                items.push(RepoWithTags { repo, tags });
-            }
-        } else {
-            let iter = stmt.query_map(params![per_page as i64, offset as i64], |r| {
-                Ok((
-                    RepoRow {
-                        id: r.get(0)?,
-                        path: r.get(1)?,
-                        name: r.get(2)?,
-                        default_branch: r.get(3)?,
-                        last_commit_ts: r.get(4)?,
-                        last_scan_ts: r.get(5)?,
-                        readme_excerpt: r.get(6)?,
-                        origin_url: r.get(7)?,
-                        last_access_ts: r.get(8)?,
-                    },
-                    r.get::<_, String>(9)?,
-                ))
-            })?;
-            for row in iter {
-                let (repo, tags) = row?;
-                let tags = tags
-                    .split(',')
-                    .filter(|s| !s.trim().is_empty())
-                    .map(|s| s.to_string())
-                    .collect::<Vec<_>>();
-                items.push(RepoWithTags { repo, tags });
+            for e in errors {
+                stmt.execute(params![root, e.path, e.kind, e.message, ts])?;
             }
         }
-
-        Ok(Paged { total, items })
+        tx.commit()?;
+        Ok(())
     }
 
-    pub fn search_repos(&self, query: &str) -> Result<Vec<RepoRow>> {
-        let q = format!("%{}%", query);
+    pub fn list_scan_errors(&self, limit: usize) -> Result<Vec<ScanErrorRow>> {
         let mut stmt = self.conn.prepare(
-            r#"
-            SELECT DISTINCT r.id, r.path, r.name, r.default_branch, r.last_commit_ts, r.last_scan_ts, r.readme_excerpt, r.origin_url, r.last_access_ts
-            FROM repos r
-            LEFT JOIN repo_tags rt ON rt.repo_id = r.id
-            LEFT JOIN tags t ON t.id = rt.tag_id
-            WHERE r.name LIKE ?1 OR r.path LIKE ?1 OR COALESCE(r.readme_excerpt, '') LIKE ?1 OR COALESCE(t.name, '') LIKE ?1
-            ORDER BY r.name ASC
-            "#,
+            "SELECT root, path, kind, message, ts FROM scan_errors ORDER BY ts DESC, path ASC LIMIT ?1",
         )?;
-
-        let iter = stmt.query_map([q], |r| {
-            Ok(RepoRow {
-                id: r.get(0)?,
+        let iter = stmt.query_map([limit as i64], |r| {
+            Ok(ScanErrorRow {
+                root: r.get(0)?,
                 path: r.get(1)?,
-                name: r.get(2)?,
-                default_branch: r.get(3)?,
-                last_commit_ts: r.get(4)?,
-                last_scan_ts: r.get(5)?,
-                readme_excerpt: r.get(6)?,
-                origin_url: r.get(7)?,
-                last_access_ts: r.get(8)?,
+                kind: r.get(2)?,
+                message: r.get(3)?,
+                ts: r.get(4)?,
             })
         })?;
-
-        let mut rows = Vec::new();
-        for r in iter {
-            rows.push(r?);
+        let mut out = Vec::new();
+        for row in iter {
+            out.push(row?);
         }
-        Ok(rows)
+        Ok(out)
     }
 
-    pub fn search_repos_with_tags(&self, query: &str) -> Result<Vec<RepoWithTags>> {
-        let q = format!("%{}%", query);
-        let mut stmt = self.conn.prepare(
+    /// Stores a finished scan run and its change events; returns the run id.
+    pub fn record_scan_run(&self, run: &ScanRun, changes: &[RepoChange]) -> Result<i64> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
             r#"
-            SELECT
-              r.id, r.path, r.name, r.default_branch, r.last_commit_ts, r.last_scan_ts, r.readme_excerpt, r.last_access_ts,
-              COALESCE(GROUP_CONCAT(t.name, ','), '') AS tags
-            FROM repos r
-            LEFT JOIN repo_tags rt ON rt.repo_id = r.id
-            LEFT JOIN tags t ON t.id = rt.tag_id
-            WHERE r.name LIKE ?1 OR r.path LIKE ?1 OR COALESCE(r.readme_excerpt, '') LIKE ?1 OR COALESCE(t.name, '') LIKE ?1
-            GROUP BY r.id
-            ORDER BY r.name ASC
+            INSERT INTO scan_runs (root, started_ts, finished_ts, duration_ms, full, indexed, skipped, pruned, errors)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            "#,
+            params![
+                run.root,
+                run.started_ts,
+                run.finished_ts,
+                run.duration_ms,
+                run.full,
+                run.indexed as i64,
+                run.skipped as i64,
+                run.pruned as i64,
+                run.errors as i64
+            ],
+        )?;
+        let run_id = tx.last_insert_rowid();
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT INTO repo_events (run_id, path, name, kind, old_value, new_value, detail)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                "#,
+            )?;
+            for c in changes {
+                stmt.execute(params![run_id, c.path, c.name, c.kind, c.old_value, c.new_value, c.detail])?;
+            }
+        }
+        tx.commit()?;
+        Ok(run_id)
+    }
+
+    /// Scan runs started at or after `since_ts`, newest first, with their change events.
+    pub fn list_scan_runs(&self, since_ts: i64, limit: usize) -> Result<Vec<ScanRunRow>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, root, started_ts, finished_ts, duration_ms, full, indexed, skipped, pruned, errors
+            FROM scan_runs
+            WHERE started_ts >= ?1
+            ORDER BY started_ts DESC, id DESC
+            LIMIT ?2
             "#,
         )?;
+        let iter = stmt.query_map(params![since_ts, limit as i64], |r| {
+            Ok(ScanRunRow {
+                id: r.get(0)?,
+                run: ScanRun {
+                    root: r.get(1)?,
+                    started_ts: r.get(2)?,
+                    finished_ts: r.get(3)?,
+                    duration_ms: r.get(4)?,
+                    full: r.get(5)?,
+                    indexed: r.get::<_, i64>(6)? as usize,
+                    skipped: r.get::<_, i64>(7)? as usize,
+                    pruned: r.get::<_, i64>(8)? as usize,
+                    errors: r.get::<_, i64>(9)? as usize,
+                },
+                changes: Vec::new(),
+            })
+        })?;
+        let mut runs = Vec::new();
+        for row in iter {
+            runs.push(row?);
+        }
 
-        let iter = stmt.query_map([q], |r| {
-            Ok((
-                    RepoRow {
-                        id: r.get(0)?,
-                        path: r.get(1)?,
-                        name: r.get(2)?,
-                        default_branch: r.get(3)?,
-                        last_commit_ts: r.get(4)?,
-                        last_scan_ts: r.get(5)?,
-                        readme_excerpt: r.get(6)?,
-                        origin_url: r.get(7)?,
-                        last_access_ts: r.get(8)?,
-                    },
-                    r.get::<_, String>(9)?,
-                ))
+        let mut ev_stmt = self.conn.prepare(
+            r#"
+            SELECT path, name, kind, old_value, new_value, detail
+            FROM repo_events
+            WHERE run_id = ?1
+            ORDER BY kind ASC, name ASC
+            "#,
+        )?;
+        for run in &mut runs {
+            let iter = ev_stmt.query_map([run.id], |r| {
+                Ok(RepoChange {
+                    path: r.get(0)?,
+                    name: r.get(1)?,
+                    kind: r.get(2)?,
+                    old_value: r.get(3)?,
+                    new_value: r.get(4)?,
+                    detail: r.get(5)?,
+                })
             })?;
+            for c in iter {
+                run.changes.push(c?);
+            }
+        }
+        Ok(runs)
+    }
 
-        let mut rows = Vec::new();
+    /// Start time of the most recent scan run, if any.
+    pub fn last_scan_run_start(&self) -> Result<Option<i64>> {
+        let ts = self
+            .conn
+            .query_row("SELECT MAX(started_ts) FROM scan_runs", [], |r| r.get::<_, Option<i64>>(0))?;
+        Ok(ts)
+    }
+
+    pub fn repo_fingerprints(&self) -> Result<HashMap<String, String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, fingerprint FROM repos WHERE fingerprint IS NOT NULL AND removed_ts IS NULL")?;
+        let iter = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?;
+        let mut out = HashMap::new();
         for row in iter {
-            let (repo, tags) = row?;
-            let tags = tags
-                .split(',')
-                .filter(|s| !s.trim().is_empty())
-                .map(|s| s.to_string())
-                .collect::<Vec<_>>();
-            rows.push(RepoWithTags { repo, tags });
+            let (path, fp) = row?;
+            out.insert(path, fp);
+        }
+        Ok(out)
+    }
+
+    /// `(head_oid, identity)` of every live repo that has both, keyed by path.
+    pub fn repo_heads(&self) -> Result<HashMap<String, (String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, head_oid, identity FROM repos \
+             WHERE head_oid IS NOT NULL AND identity IS NOT NULL AND removed_ts IS NULL",
+        )?;
+        let iter = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, (r.get(1)?, r.get(2)?))))?;
+        Ok(iter.collect::<rusqlite::Result<HashMap<_, _>>>()?)
+    }
+
+    pub fn list_repos(&self, filter: &RepoFilter, recent: bool) -> Result<Vec<RepoRow>> {
+        let order = if recent {
+            "ORDER BY r.pinned DESC, COALESCE(r.last_access_ts, 0) DESC, r.name ASC"
+        } else {
+            "ORDER BY r.pinned DESC, r.name ASC"
+        };
+        let mut args = Vec::new();
+        let filter_sql = filter.sql(&mut args);
+        let sql = format!("SELECT {REPO_COLS} FROM repos r WHERE {filter_sql} AND {LIVE_SQL} {order}");
+        let mut stmt = self.conn.prepare(&sql)?;
+        let iter = stmt.query_map(params_from_iter(args), repo_row)?;
+        let mut rows = Vec::new();
+        for r in iter {
+            rows.push(r?);
         }
         Ok(rows)
     }
 
-    pub fn search_repos_with_tags_paged(
+    /// Lists top-level repos; linked worktrees of an indexed repo are folded under it
+    /// (see `worktrees_by_parent`) instead of being listed on their own.
+    pub fn list_repos_with_tags_paged(
         &self,
-        query: &str,
+        filter: &RepoFilter,
+        recent: bool,
         page: usize,
         per_page: usize,
     ) -> Result<Paged<RepoWithTags>> {
-        self.search_repos_with_tags_paged_filtered(query, true, true, true, true, page, per_page)
+        let page = page.max(1);
+        let per_page = per_page.clamp(1, 200);
+        let offset = (page - 1) * per_page;
+        let mut args = Vec::new();
+        let filter_sql = filter.sql(&mut args);
+
+        let sql = format!("SELECT COUNT(*) FROM repos r WHERE {filter_sql} AND {LIVE_SQL} AND {TOP_LEVEL_SQL}");
+        let total = self
+            .conn
+            .query_row(&sql, params_from_iter(&args), |r| r.get::<_, i64>(0))? as usize;
+
+        let order = if recent {
+            "ORDER BY r.pinned DESC, COALESCE(r.last_access_ts, 0) DESC, r.name ASC"
+        } else {
+            "ORDER BY r.pinned DESC, r.name ASC"
+        };
+        let limit = args.len() + 1;
+        let sql = format!(
+            r#"
+            SELECT
+              {REPO_COLS},
+              COALESCE(GROUP_CONCAT(t.name, ','), '') AS tags
+            FROM repos r
+            LEFT JOIN repo_tags rt ON rt.repo_id = r.id
+            LEFT JOIN tags t ON t.id = rt.tag_id
+            WHERE {filter_sql} AND {LIVE_SQL} AND {TOP_LEVEL_SQL}
+            GROUP BY r.id
+            {order}
+            LIMIT ?{limit} OFFSET ?{}
+            "#,
+            limit + 1
+        );
+        args.push(Value::from(per_page as i64));
+        args.push(Value::from(offset as i64));
+        let mut stmt = self.conn.prepare(&sql)?;
+        let iter = stmt.query_map(params_from_iter(args), repo_with_tags_row)?;
+        let mut items = Vec::new();
+        for row in iter {
+            items.push(row?);
+        }
+        Ok(Paged { total, items })
     }
 
+    /// Linked worktrees grouped by the path of their main repo.
+    pub fn worktrees_by_parent(&self, parents: &[String]) -> Result<HashMap<String, Vec<RepoRow>>> {
+        let mut out = HashMap::<String, Vec<RepoRow>>::new();
+        if parents.is_empty() {
+            return Ok(out);
+        }
+        let sql = format!(
+            "SELECT {REPO_COLS} FROM repos r WHERE r.kind = 'worktree' AND r.parent_path = ?1 AND {LIVE_SQL} \
+             ORDER BY r.name ASC"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        for parent in parents {
+            let iter = stmt.query_map([parent], repo_row)?;
+            for row in iter {
+                out.entry(parent.clone()).or_default().push(row?);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Every repo matching a parsed search, best match first.
+    pub fn search_repos(&self, q: &query::RepoQuery) -> Result<Vec<RepoRow>> {
+        let hits = self.search_repo_hits(fts_query(&q.text, &q.columns).as_deref(), &q.filter, -1, 0)?;
+        Ok(hits.items.into_iter().map(|h| h.repo).collect())
+    }
+
+    /// One page of a parsed repo search, ranked by bm25; without free text it lists every
+    /// repo passing the filters.
     pub fn search_repos_with_tags_paged_filtered(
         &self,
-        query: &str,
-        in_name: bool,
-        in_path: bool,
-        in_readme: bool,
-        in_tags: bool,
+        q: &query::RepoQuery,
         page: usize,
         per_page: usize,
-    ) -> Result<Paged<RepoWithTags>> {
+    ) -> Result<Paged<RepoHit>> {
         let page = page.max(1);
         let per_page = per_page.clamp(1, 200);
         let offset = (page - 1) * per_page;
-        let q = format!("%{}%", query);
+        let fts = fts_query(&q.text, &q.columns);
+        self.search_repo_hits(fts.as_deref(), &q.filter, per_page as i64, offset as i64)
+    }
 
-        let (in_name, in_path, in_readme, in_tags) = if !(in_name || in_path || in_readme || in_tags)
-        {
-            (true, true, true, true)
-        } else {
-            (in_name, in_path, in_readme, in_tags)
+    /// Runs an FTS5 `MATCH` expression (or lists everything for `None`); a negative
+    /// `limit` means no limit.
+    fn search_repo_hits(
+        &self,
+        fts: Option<&str>,
+        filter: &RepoFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Paged<RepoHit>> {
+        let mut args = Vec::<Value>::new();
+        let (from_sql, match_sql, rank_sql, marks_sql) = match fts {
+            Some(q) => {
+                args.push(Value::from(q.to_string()));
+                let weights = REPO_FTS_COLUMNS
+                    .iter()
+                    .map(|(_, w, _)| format!("{w:.1}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let marks = REPO_FTS_COLUMNS
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (_, _, snippet))| {
+                        if *snippet {
+                            format!("snippet(repos_fts, {i}, char(2), char(3), '…', 16)")
+                        } else {
+                            format!("highlight(repos_fts, {i}, char(2), char(3))")
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                (
+                    "repos_fts JOIN repos r ON r.id = repos_fts.rowid",
+                    "repos_fts MATCH ?1",
+                    format!("bm25(repos_fts, {weights})"),
+                    marks,
+                )
+            }
+            None => ("repos r", "1", "0.0".to_string(), vec!["NULL"; REPO_FTS_COLUMNS.len()].join(", ")),
         };
-
-        let mut where_parts = Vec::<&str>::new();
-        if in_name {
-            where_parts.push("r.name LIKE ?1");
-        }
-        if in_path {
-            where_parts.push("r.path LIKE ?1");
-        }
-        if in_readme {
-            where_parts.push("COALESCE(r.readme_excerpt, '') LIKE ?1");
-        }
-        if in_tags {
-            where_parts.push("COALESCE(t.name, '') LIKE ?1");
-        }
-        let where_sql = where_parts.join(" OR ");
+        let filter_sql = filter.sql(&mut args);
 
         let total_sql = format!(
-            r#"
-            SELECT COUNT(DISTINCT r.id)
-            FROM repos r
-            LEFT JOIN repo_tags rt ON rt.repo_id = r.id
-            LEFT JOIN tags t ON t.id = rt.tag_id
-            WHERE {where_sql}
-            "#
+            "SELECT COUNT(*) FROM {from_sql} WHERE {match_sql} AND {filter_sql} AND {LIVE_SQL}"
         );
         let total: usize = self
             .conn
-            .query_row(&total_sql, [q.as_str()], |r| r.get::<_, i64>(0))? as usize;
+            .query_row(&total_sql, params_from_iter(&args), |r| r.get::<_, i64>(0))? as usize;
 
         let sql = format!(
             r#"
             SELECT
-              r.id, r.path, r.name, r.default_branch, r.last_commit_ts, r.last_scan_ts, r.readme_excerpt, r.origin_url, r.last_access_ts,
-              COALESCE(GROUP_CONCAT(t.name, ','), '') AS tags
-            FROM repos r
-            LEFT JOIN repo_tags rt ON rt.repo_id = r.id
-            LEFT JOIN tags t ON t.id = rt.tag_id
-            WHERE {where_sql}
-            GROUP BY r.id
-            ORDER BY r.name ASC
-            LIMIT ?2 OFFSET ?3
-            "#
+              {REPO_COLS},
+              COALESCE((SELECT GROUP_CONCAT(t.name, ',') FROM repo_tags rt
+                        JOIN tags t ON t.id = rt.tag_id WHERE rt.repo_id = r.id), '') AS tags,
+              {rank_sql} AS score,
+              {marks_sql}
+            FROM {from_sql}
+            WHERE {match_sql} AND {filter_sql} AND {LIVE_SQL}
+            ORDER BY r.pinned DESC, score ASC, r.name ASC
+            LIMIT ?{} OFFSET ?{}
+            "#,
+            args.len() + 1,
+            args.len() + 2
         );
+        args.push(Value::from(limit));
+        args.push(Value::from(offset));
         let mut stmt = self.conn.prepare(&sql)?;
-
-        let iter = stmt.query_map(params![q, per_page as i64, offset as i64], |r| {
-            Ok((
-                RepoRow {
-                    id: r.get(0)?,
-                    path: r.get(1)?,
-                    name: r.get(2)?,
-                    default_branch: r.get(3)?,
-                    last_commit_ts: r.get(4)?,
-                    last_scan_ts: r.get(5)?,
-                    readme_excerpt: r.get(6)?,
-                    origin_url: r.get(7)?,
-                    last_access_ts: r.get(8)?,
-                },
-                r.get::<_, String>(9)?,
-            ))
+        let iter = stmt.query_map(params_from_iter(args), |r| {
+            let RepoWithTags { repo, tags } = repo_with_tags_row(r)?;
+            let mut highlights = Vec::new();
+            for (i, (column, _, _)) in REPO_FTS_COLUMNS.iter().enumerate() {
+                if let Some(text) = r.get::<_, Option<String>>(REPO_COL_COUNT + 2 + i)? {
+                    if text.contains(MARK_START) {
+                        highlights.push((*column, join_cjk(text)));
+                    }
+                }
+            }
+            Ok(RepoHit {
+                repo,
+                tags,
+                rank: r.get(REPO_COL_COUNT + 1)?,
+                highlights,
+            })
         })?;
 
         let mut items = Vec::new();
         for row in iter {
-            let (repo, tags) = row?;
-            let tags = tags
-                .split(',')
-                .filter(|s| !s.trim().is_empty())
-                .map(|s| s.to_string())
-                .collect::<Vec<_>>();
-            items.push(RepoWithTags { repo, tags });
+            items.push(row?);
         }
 
         Ok(Paged { total, items })
@@ -678,14 +1314,96 @@ impl Db {
         let repo_id = self
             .repo_id_by_path(repo_path)?
             .with_context(|| format!("repo not indexed: {repo_path}"))?;
-        let tag_id = self.ensure_tag(tag)?;
+        let tag_id = self.ensure_tag(&normalize_tag(tag)?)?;
+        // Tagging by hand keeps a tag that a rule also applies when the rule stops matching.
         self.conn.execute(
-            "INSERT OR IGNORE INTO repo_tags (repo_id, tag_id) VALUES (?1, ?2)",
+            "INSERT INTO repo_tags (repo_id, tag_id, source) VALUES (?1, ?2, 'manual') \
+             ON CONFLICT(repo_id, tag_id) DO UPDATE SET source = 'manual'",
             params![repo_id, tag_id],
         )?;
         Ok(())
     }
 
+    /// Replaces the rule-applied tags of each repo in `tags` (path -> tags computed from
+    /// tag rules and root auto-tags). Manual tags are left alone, and a rule never turns
+    /// a manual tag into a rule one.
+    pub fn replace_rule_tags(&self, tags: &[(String, Vec<String>)]) -> Result<()> {
+        if tags.is_empty() {
+            return Ok(());
+        }
+        let mut tag_ids = HashMap::new();
+        for tag in tags.iter().flat_map(|(_, t)| t) {
+            if !tag_ids.contains_key(tag) {
+                tag_ids.insert(tag.clone(), self.ensure_tag(tag)?);
+            }
+        }
+        // Only rows that change are touched: every insert or delete re-indexes the repo
+        // for search, and most scans leave rule tags as they were.
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut current_stmt = tx.prepare(
+                "SELECT tag_id FROM repo_tags WHERE source = 'rule' AND repo_id = (SELECT id FROM repos WHERE path = ?1)",
+            )?;
+            let mut delete_stmt = tx.prepare(
+                "DELETE FROM repo_tags WHERE source = 'rule' AND tag_id = ?2 \
+                 AND repo_id = (SELECT id FROM repos WHERE path = ?1)",
+            )?;
+            let mut insert_stmt = tx.prepare(
+                "INSERT OR IGNORE INTO repo_tags (repo_id, tag_id, source) \
+                 SELECT id, ?2, 'rule' FROM repos WHERE path = ?1",
+            )?;
+            for (path, repo_tags) in tags {
+                let wanted: HashSet<i64> = repo_tags.iter().map(|t| tag_ids[t]).collect();
+                let current = current_stmt
+                    .query_map([path], |r| r.get::<_, i64>(0))?
+                    .collect::<rusqlite::Result<HashSet<_>>>()?;
+                for tag_id in current.difference(&wanted) {
+                    delete_stmt.execute(params![path, tag_id])?;
+                }
+                for tag_id in wanted.difference(&current) {
+                    insert_stmt.execute(params![path, tag_id])?;
+                }
+            }
+        }
+        tx.commit()?;
+        self.prune_orphan_tags()?;
+        Ok(())
+    }
+
+    /// Rule-applied tags of every live repo, keyed by path.
+    pub fn rule_tags_by_repo(&self) -> Result<HashMap<String, Vec<String>>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT r.path, t.name
+            FROM repo_tags rt
+            JOIN repos r ON r.id = rt.repo_id
+            JOIN tags t ON t.id = rt.tag_id
+            WHERE rt.source = 'rule' AND r.removed_ts IS NULL
+            ORDER BY t.name ASC
+            "#,
+        )?;
+        let iter = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?;
+        let mut out = HashMap::<String, Vec<String>>::new();
+        for row in iter {
+            let (path, tag) = row?;
+            out.entry(path).or_default().push(tag);
+        }
+        Ok(out)
+    }
+
+    /// Live repos among `paths`, in no particular order.
+    pub fn repos_by_paths<'a>(&self, paths: impl IntoIterator<Item = &'a String>) -> Result<Vec<RepoRow>> {
+        let sql = format!("SELECT {REPO_COLS} FROM repos r WHERE r.path = ?1 AND {LIVE_SQL}");
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut out = Vec::new();
+        for path in paths {
+            if let Some(row) = stmt.query_row([path], repo_row).optional()? {
+                out.push(row);
+            }
+        }
+        Ok(out)
+    }
+
     pub fn remove_tag_from_repo(&self, repo_path: &str, tag: &str) -> Result<()> {
         let repo_id = self
             .repo_id_by_path(repo_path)?
@@ -699,9 +1417,10 @@ impl Db {
             "DELETE FROM repo_tags WHERE repo_id = ?1 AND tag_id = ?2",
             params![repo_id, tag_id],
         )?;
-        // Remove the tag itself if it is now orphaned.
+        // Remove the tag itself if it is now orphaned (and carries no color or description).
         self.conn.execute(
-            "DELETE FROM tags WHERE id = ?1 AND NOT EXISTS (SELECT 1 FROM repo_tags WHERE tag_id = ?1)",
+            "DELETE FROM tags WHERE id = ?1 AND color IS NULL AND description IS NULL \
+             AND NOT EXISTS (SELECT 1 FROM repo_tags WHERE tag_id = ?1)",
             params![tag_id],
         )?;
         Ok(())
@@ -717,21 +1436,170 @@ impl Db {
         Ok(out)
     }
 
-    pub fn list_tags_with_count(&self) -> Result<Vec<(String, usize)>> {
+    /// The tag tree in display order (namespaces right before their children), counting
+    /// live repos only. Nodes without any repo are left out.
+    pub fn list_tags_with_count(&self) -> Result<Vec<TagCount>> {
+        let mut meta = HashMap::new();
+        let mut stmt = self.conn.prepare("SELECT name, color, description FROM tags")?;
+        let iter = stmt.query_map([], |r| {
+            Ok((r.get::<_, String>(0)?, r.get::<_, Option<String>>(1)?, r.get::<_, Option<String>>(2)?))
+        })?;
+        for row in iter {
+            let (name, color, description) = row?;
+            meta.insert(name, (color, description));
+        }
+
         let mut stmt = self.conn.prepare(
             r#"
-            SELECT t.name, COUNT(rt.repo_id) AS c
+            SELECT t.name, r.id
             FROM tags t
-            LEFT JOIN repo_tags rt ON rt.tag_id = t.id
-            GROUP BY t.id
-            HAVING c > 0
-            ORDER BY t.name ASC
+            JOIN repo_tags rt ON rt.tag_id = t.id
+            JOIN repos r ON r.id = rt.repo_id AND r.removed_ts IS NULL
             "#,
         )?;
-        let iter = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)? as usize)))?;
-        let mut out = Vec::new();
+        let iter = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)?)))?;
+        let mut direct = HashMap::<String, usize>::new();
+        let mut subtree = HashMap::<String, HashSet<i64>>::new();
         for row in iter {
-            out.push(row?);
+            let (name, repo_id) = row?;
+            *direct.entry(name.clone()).or_default() += 1;
+            for (i, _) in name.match_indices('/') {
+                subtree.entry(name[..i].to_string()).or_default().insert(repo_id);
+            }
+            subtree.entry(name).or_default().insert(repo_id);
+        }
+
+        let mut out = subtree
+            .into_iter()
+            .map(|(name, repos)| {
+                let (color, description) = meta.remove(&name).unwrap_or_default();
+                TagCount {
+                    count: direct.get(&name).copied().unwrap_or(0),
+                    total: repos.len(),
+                    name,
+                    color,
+                    description,
+                }
+            })
+            .collect::<Vec<_>>();
+        out.sort_by(|a, b| a.name.split('/').cmp(b.name.split('/')));
+        Ok(out)
+    }
+
+    /// Sets or (with `None`) clears a tag's color; `name` may be a namespace that is not
+    /// a tag yet.
+    pub fn set_tag_color(&self, name: &str, color: Option<&str>) -> Result<()> {
+        let color = color.map(str::trim).filter(|c| !c.is_empty());
+        if let Some(c) = color {
+            let hex = c.strip_prefix('#').unwrap_or("");
+            if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+                anyhow::bail!("invalid color '{c}' (use #rgb or #rrggbb)");
+            }
+        }
+        let id = self.ensure_tag(&normalize_tag(name)?)?;
+        self.conn
+            .execute("UPDATE tags SET color = ?2 WHERE id = ?1", params![id, color])?;
+        self.prune_orphan_tags()?;
+        Ok(())
+    }
+
+    pub fn set_tag_description(&self, name: &str, description: Option<&str>) -> Result<()> {
+        let description = description.map(str::trim).filter(|d| !d.is_empty());
+        let id = self.ensure_tag(&normalize_tag(name)?)?;
+        self.conn
+            .execute("UPDATE tags SET description = ?2 WHERE id = ?1", params![id, description])?;
+        self.prune_orphan_tags()?;
+        Ok(())
+    }
+
+    /// Renames `from` and every tag below it (`from/x` becomes `to/x`); returns how many
+    /// tags were renamed. Fails if a new name is taken (use `merge_tags` for that).
+    pub fn rename_tag(&self, from: &str, to: &str) -> Result<usize> {
+        let (from, to) = (normalize_tag(from)?, normalize_tag(to)?);
+        let mut tags = self.tag_subtree(&from)?;
+        if tags.is_empty() {
+            anyhow::bail!("no such tag: {from}");
+        }
+        for (_, name) in &tags {
+            let target = rebase_tag(name, &from, &to).expect("subtree member");
+            let taken = self
+                .conn
+                .query_row("SELECT 1 FROM tags WHERE name = ?1", [&target], |_| Ok(()))
+                .optional()?
+                .is_some();
+            if taken && !tags.iter().any(|(_, n)| *n == target) {
+                anyhow::bail!("tag {target} already exists; merge instead");
+            }
+        }
+        // Longest names first: when renaming `a` to `a/b`, the old `a/b` must move on
+        // to `a/b/b` before `a` takes its name.
+        tags.sort_by_key(|(_, name)| std::cmp::Reverse(name.len()));
+        let tx = self.conn.unchecked_transaction()?;
+        for (id, name) in &tags {
+            let target = rebase_tag(name, &from, &to).expect("subtree member");
+            tx.execute("UPDATE tags SET name = ?2 WHERE id = ?1", params![id, target])?;
+        }
+        tx.commit()?;
+        Ok(tags.len())
+    }
+
+    /// Moves the repos of `from` (and of every tag below it) onto `into` (`from/x` onto
+    /// `into/x`) and deletes the old tags; returns how many tags were merged away.
+    pub fn merge_tags(&self, from: &str, into: &str) -> Result<usize> {
+        let (from, into) = (normalize_tag(from)?, normalize_tag(into)?);
+        if rebase_tag(&into, &from, "").is_some() {
+            anyhow::bail!("cannot merge {from} into itself");
+        }
+        let tags = self.tag_subtree(&from)?;
+        if tags.is_empty() {
+            anyhow::bail!("no such tag: {from}");
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        for (id, name) in &tags {
+            let target = rebase_tag(name, &from, &into).expect("subtree member");
+            let target_id = self.ensure_tag(&target)?;
+            // A manual link wins over a rule-applied one for the same repo.
+            tx.execute(
+                "INSERT INTO repo_tags (repo_id, tag_id, source) \
+                 SELECT repo_id, ?2, source FROM repo_tags WHERE tag_id = ?1 \
+                 ON CONFLICT(repo_id, tag_id) DO UPDATE SET source = \
+                   CASE WHEN excluded.source = 'manual' THEN 'manual' ELSE repo_tags.source END",
+                params![id, target_id],
+            )?;
+            tx.execute(
+                "UPDATE tags SET \
+                   color = COALESCE(color, (SELECT color FROM tags WHERE id = ?1)), \
+                   description = COALESCE(description, (SELECT description FROM tags WHERE id = ?1)) \
+                 WHERE id = ?2",
+                params![id, target_id],
+            )?;
+            tx.execute("DELETE FROM tags WHERE id = ?1", [id])?;
+        }
+        tx.commit()?;
+        Ok(tags.len())
+    }
+
+    /// Deletes `name` and every tag below it from all repos; returns how many tags went.
+    pub fn delete_tag(&self, name: &str) -> Result<usize> {
+        let tags = self.tag_subtree(&normalize_tag(name)?)?;
+        let tx = self.conn.unchecked_transaction()?;
+        for (id, _) in &tags {
+            tx.execute("DELETE FROM tags WHERE id = ?1", [id])?;
+        }
+        tx.commit()?;
+        Ok(tags.len())
+    }
+
+    /// Tags of every repo, keyed by repo id and sorted by name.
+    pub fn tags_by_repo_id(&self) -> Result<HashMap<i64, Vec<String>>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT rt.repo_id, t.name FROM repo_tags rt JOIN tags t ON t.id = rt.tag_id ORDER BY t.name",
+        )?;
+        let iter = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?;
+        let mut out = HashMap::<i64, Vec<String>>::new();
+        for row in iter {
+            let (id, tag) = row?;
+            out.entry(id).or_default().push(tag);
         }
         Ok(out)
     }
@@ -754,58 +1622,344 @@ impl Db {
         for t in iter {
             out.push(t?);
         }
-        Ok(out)
+        Ok(out)
+    }
+
+    /// The repo's markdown note, if it has one.
+    pub fn repo_note(&self, repo_path: &str) -> Result<Option<String>> {
+        let repo_id = self
+            .repo_id_by_path(repo_path)?
+            .with_context(|| format!("repo not indexed: {repo_path}"))?;
+        let body = self
+            .conn
+            .query_row("SELECT body FROM repo_notes WHERE repo_id = ?1", [repo_id], |r| r.get(0))
+            .optional()?;
+        Ok(body)
+    }
+
+    /// Replaces the repo's note; a blank `body` removes it.
+    pub fn set_repo_note(&self, repo_path: &str, body: &str) -> Result<()> {
+        let repo_id = self
+            .repo_id_by_path(repo_path)?
+            .with_context(|| format!("repo not indexed: {repo_path}"))?;
+        if body.trim().is_empty() {
+            self.conn.execute("DELETE FROM repo_notes WHERE repo_id = ?1", [repo_id])?;
+            return Ok(());
+        }
+        let ts = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "INSERT INTO repo_notes (repo_id, body, updated_ts) VALUES (?1, ?2, ?3) \
+             ON CONFLICT(repo_id) DO UPDATE SET body = excluded.body, updated_ts = excluded.updated_ts",
+            params![repo_id, body, ts],
+        )?;
+        Ok(())
+    }
+
+    /// Custom fields of a repo as (key, value), sorted by key.
+    pub fn list_repo_fields(&self, repo_path: &str) -> Result<Vec<(String, String)>> {
+        let repo_id = self
+            .repo_id_by_path(repo_path)?
+            .with_context(|| format!("repo not indexed: {repo_path}"))?;
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key, value FROM repo_fields WHERE repo_id = ?1 ORDER BY key ASC")?;
+        let iter = stmt.query_map([repo_id], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        let mut out = Vec::new();
+        for row in iter {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// Sets a custom field; a blank `value` removes it. `lifecycle` only takes the values
+    /// in `LIFECYCLES`.
+    pub fn set_repo_field(&self, repo_path: &str, key: &str, value: &str) -> Result<()> {
+        let repo_id = self
+            .repo_id_by_path(repo_path)?
+            .with_context(|| format!("repo not indexed: {repo_path}"))?;
+        let key = normalize_field_key(key)?;
+        let value = value.trim();
+        if value.is_empty() {
+            self.conn.execute(
+                "DELETE FROM repo_fields WHERE repo_id = ?1 AND key = ?2",
+                params![repo_id, key],
+            )?;
+            return Ok(());
+        }
+        // `field=` filters in the web API join several `key=value` entries with commas.
+        if value.contains(',') {
+            anyhow::bail!("field value must not contain ',': {value}");
+        }
+        if key == "lifecycle" && !LIFECYCLES.contains(&value) {
+            anyhow::bail!("lifecycle must be one of {}: {value}", LIFECYCLES.join(", "));
+        }
+        self.conn.execute(
+            "INSERT INTO repo_fields (repo_id, key, value) VALUES (?1, ?2, ?3) \
+             ON CONFLICT(repo_id, key) DO UPDATE SET value = excluded.value",
+            params![repo_id, key, value],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_repo_pinned(&self, repo_path: &str, pinned: bool) -> Result<()> {
+        let n = self.conn.execute(
+            "UPDATE repos SET pinned = ?1 WHERE path = ?2 AND removed_ts IS NULL",
+            params![pinned, repo_path],
+        )?;
+        if n == 0 {
+            anyhow::bail!("repo not indexed: {repo_path}");
+        }
+        Ok(())
+    }
+
+    pub fn set_repo_archived(&self, repo_path: &str, archived: bool) -> Result<()> {
+        let n = self.conn.execute(
+            "UPDATE repos SET archived = ?1 WHERE path = ?2 AND removed_ts IS NULL",
+            params![archived, repo_path],
+        )?;
+        if n == 0 {
+            anyhow::bail!("repo not indexed: {repo_path}");
+        }
+        Ok(())
+    }
+
+    /// Moves a repo excluded from scans to the trash, reason `hidden`. Its worktrees and
+    /// submodules stay listed; they are separate repos.
+    pub fn hide_repo(&self, repo_path: &str) -> Result<bool> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT name, head_oid FROM repos WHERE path = ?1 AND removed_ts IS NULL",
+                [repo_path],
+                |r| Ok((r.get::<_, String>(0)?, r.get::<_, Option<String>>(1)?)),
+            )
+            .optional()?;
+        let Some((name, head)) = row else { return Ok(false); };
+        self.trash_repos(&[removed_change(repo_path.to_string(), name, head, HIDDEN_REASON)])?;
+        Ok(true)
+    }
+
+    pub fn record_access(&self, repo_path: &str) -> Result<()> {
+        let ts = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "UPDATE repos SET last_access_ts = ?1 WHERE path = ?2",
+            params![ts, repo_path],
+        )?;
+        self.conn.execute(
+            "INSERT INTO repo_access (repo_id, ts) SELECT id, ?1 FROM repos WHERE path = ?2",
+            params![ts, repo_path],
+        )?;
+        // Only the most recent accesses matter for frecency; keep the table bounded.
+        self.conn.execute(
+            "DELETE FROM repo_access WHERE repo_id = (SELECT id FROM repos WHERE path = ?1) \
+             AND id NOT IN (SELECT a.id FROM repo_access a JOIN repos r ON r.id = a.repo_id \
+                            WHERE r.path = ?1 ORDER BY a.ts DESC, a.id DESC LIMIT ?2)",
+            params![repo_path, ACCESS_HISTORY_LIMIT],
+        )?;
+        Ok(())
+    }
+
+    /// Adds a short name that resolves straight to `repo_path`. Aliases are unique across
+    /// repos; re-adding one to the same repo is a no-op.
+    pub fn add_repo_alias(&self, repo_path: &str, alias: &str) -> Result<String> {
+        let alias = normalize_alias(alias)?;
+        let repo_id = self
+            .repo_id_by_path(repo_path)?
+            .with_context(|| format!("repo not indexed: {repo_path}"))?;
+        let owner: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT r.path FROM repo_aliases a JOIN repos r ON r.id = a.repo_id WHERE a.alias = ?1",
+                [&alias],
+                |r| r.get(0),
+            )
+            .optional()?;
+        match owner {
+            Some(p) if p == repo_path => {}
+            Some(p) => anyhow::bail!("alias '{alias}' already points to {p}"),
+            None => {
+                self.conn.execute(
+                    "INSERT INTO repo_aliases (alias, repo_id) VALUES (?1, ?2)",
+                    params![alias, repo_id],
+                )?;
+            }
+        }
+        Ok(alias)
+    }
+
+    /// Returns false when the alias did not exist.
+    pub fn remove_repo_alias(&self, alias: &str) -> Result<bool> {
+        let alias = normalize_alias(alias)?;
+        let n = self
+            .conn
+            .execute("DELETE FROM repo_aliases WHERE alias = ?1", [alias])?;
+        Ok(n > 0)
+    }
+
+    /// Path of the repo an alias points to, including repos in the trash.
+    pub fn alias_target(&self, alias: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT r.path FROM repo_aliases a JOIN repos r ON r.id = a.repo_id WHERE a.alias = ?1",
+                [alias.trim().to_lowercase()],
+                |r| r.get(0),
+            )
+            .optional()?)
+    }
+
+    /// `(alias, repo path)` pairs, sorted by alias.
+    pub fn list_repo_aliases(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT a.alias, r.path FROM repo_aliases a JOIN repos r ON r.id = a.repo_id \
+             WHERE r.removed_ts IS NULL ORDER BY a.alias",
+        )?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Moves repos whose path no longer exists to the trash and returns them as `removed`
+    /// changes. With `dry_run` nothing is written.
+    pub fn prune_missing_paths(&self, dry_run: bool) -> Result<Vec<RepoChange>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, name, head_oid FROM repos WHERE removed_ts IS NULL")?;
+        let iter = stmt.query_map([], |r| {
+            Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?, r.get::<_, Option<String>>(2)?))
+        })?;
+        let mut missing = Vec::new();
+        for row in iter {
+            let (path, name, head) = row?;
+            if !Path::new(&path).exists() {
+                missing.push(removed_change(path, name, head, "path no longer exists"));
+            }
+        }
+        if !dry_run {
+            self.trash_repos(&missing)?;
+        }
+        Ok(missing)
+    }
+
+    /// Moves repos at or under `root` that the scan did not see to the trash and returns
+    /// them as `removed` changes. With `dry_run` nothing is written.
+    pub fn prune_under_root(
+        &self,
+        root: &str,
+        keep: &HashSet<String>,
+        dry_run: bool,
+    ) -> Result<Vec<RepoChange>> {
+        let prefix = if root.ends_with(std::path::MAIN_SEPARATOR) {
+            root.to_string()
+        } else {
+            format!("{root}{}", std::path::MAIN_SEPARATOR)
+        };
+        // A plain prefix compare: `LIKE` would read `_` and `%` in the root as wildcards
+        // and ignore ASCII case.
+        let mut stmt = self.conn.prepare(
+            "SELECT path, name, head_oid FROM repos \
+             WHERE (path = ?1 OR substr(path, 1, length(?2)) = ?2) AND removed_ts IS NULL",
+        )?;
+        let iter = stmt.query_map([root, prefix.as_str()], |r| {
+            Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?, r.get::<_, Option<String>>(2)?))
+        })?;
+        let reason = format!("not found by scan of {root}");
+        let mut removed = Vec::new();
+        for row in iter {
+            let (path, name, head) = row?;
+            if !keep.contains(&path) {
+                removed.push(removed_change(path, name, head, &reason));
+            }
+        }
+        if !dry_run {
+            self.trash_repos(&removed)?;
+        }
+        Ok(removed)
     }
 
-    pub fn record_access(&self, repo_path: &str) -> Result<()> {
+    fn trash_repos(&self, removed: &[RepoChange]) -> Result<()> {
+        if removed.is_empty() {
+            return Ok(());
+        }
         let ts = chrono::Utc::now().timestamp();
-        self.conn.execute(
-            "UPDATE repos SET last_access_ts = ?1 WHERE path = ?2",
-            params![ts, repo_path],
-        )?;
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "UPDATE repos SET removed_ts = ?1, removed_reason = ?2 WHERE path = ?3 AND removed_ts IS NULL",
+            )?;
+            for c in removed {
+                stmt.execute(params![ts, c.detail, c.path])?;
+            }
+        }
+        tx.commit()?;
         Ok(())
     }
 
-    pub fn prune_missing_paths(&self) -> Result<usize> {
-        let mut stmt = self.conn.prepare("SELECT path FROM repos")?;
-        let iter = stmt.query_map([], |r| r.get::<_, String>(0))?;
-        let mut missing = Vec::new();
-        for p in iter {
-            let p = p?;
-            if !Path::new(&p).exists() {
-                missing.push(p);
-            }
-        }
-        let mut deleted = 0usize;
-        for p in missing {
-            deleted += self.conn.execute("DELETE FROM repos WHERE path = ?1", [p])? as usize;
+    /// Repos in the trash, most recently removed first.
+    pub fn list_trash(&self) -> Result<Vec<RepoRow>> {
+        let sql = format!(
+            "SELECT {REPO_COLS} FROM repos r WHERE r.removed_ts IS NOT NULL ORDER BY r.removed_ts DESC, r.name ASC"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let iter = stmt.query_map([], repo_row)?;
+        let mut rows = Vec::new();
+        for r in iter {
+            rows.push(r?);
         }
-        self.prune_orphan_tags()?;
-        Ok(deleted)
+        Ok(rows)
     }
 
-    pub fn prune_under_root(&self, root: &str, keep: &HashSet<String>) -> Result<usize> {
-        let prefix = if root.ends_with(std::path::MAIN_SEPARATOR) {
-            root.to_string()
-        } else {
-            format!("{root}{}", std::path::MAIN_SEPARATOR)
+    /// Takes a repo out of the trash, matched by exact path or exact name (ignoring case).
+    /// Returns the restored path; a name shared by several trashed repos is an error
+    /// listing them.
+    pub fn restore_repo(&self, input: &str) -> Result<Option<String>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT path FROM repos
+            WHERE removed_ts IS NOT NULL AND (path = ?1 OR fold(name) = fold(?1))
+            ORDER BY path = ?1 DESC, path
+            "#,
+        )?;
+        let matches = stmt
+            .query_map([input], |r| r.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let path = match matches.as_slice() {
+            [] => None,
+            [only] => Some(only.clone()),
+            [first, ..] if first == input => Some(first.clone()),
+            _ => anyhow::bail!(
+                "{input} names several repos in the trash; give the path:\n  {}",
+                matches.join("\n  ")
+            ),
         };
-        let like = format!("{prefix}%");
-        let mut stmt = self.conn.prepare("SELECT path FROM repos WHERE path LIKE ?1")?;
-        let iter = stmt.query_map([like], |r| r.get::<_, String>(0))?;
-        let mut deleted = 0usize;
-        for p in iter {
-            let p = p?;
-            if !keep.contains(&p) {
-                deleted += self.conn.execute("DELETE FROM repos WHERE path = ?1", [p])? as usize;
-            }
+        if let Some(path) = &path {
+            self.conn.execute(
+                "UPDATE repos SET removed_ts = NULL, removed_reason = NULL WHERE path = ?1",
+                [path],
+            )?;
         }
+        Ok(path)
+    }
+
+    /// Permanently deletes trashed repos (with their tags links and commit index): one
+    /// path, everything removed before `before_ts`, or the whole trash.
+    pub fn purge_trash(&self, path: Option<&str>, before_ts: Option<i64>) -> Result<usize> {
+        let n = self.conn.execute(
+            r#"
+            DELETE FROM repos
+            WHERE removed_ts IS NOT NULL
+              AND (?1 IS NULL OR path = ?1)
+              AND (?2 IS NULL OR removed_ts < ?2)
+            "#,
+            params![path, before_ts],
+        )?;
         self.prune_orphan_tags()?;
-        Ok(deleted)
+        Ok(n)
     }
 
     pub fn list_repo_paths(&self) -> Result<Vec<String>> {
-        let mut stmt = self.conn.prepare("SELECT path FROM repos ORDER BY name ASC")?;
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path FROM repos WHERE removed_ts IS NULL ORDER BY name ASC")?;
         let iter = stmt.query_map([], |r| r.get::<_, String>(0))?;
         let mut out = Vec::new();
         for p in iter {
@@ -840,8 +1994,8 @@ impl Db {
         {
             let mut stmt = tx.prepare(
                 r#"
-                INSERT INTO commits (repo_id, refname, branch_kind, branch_name, oid, time, author, email, summary, message)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                INSERT INTO commits (repo_id, refname, branch_kind, branch_name, oid, time, author, email, summary, message, parent_count)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
                 "#,
             )?;
             for c in commits {
@@ -855,7 +2009,8 @@ impl Db {
                     c.author,
                     c.email,
                     c.summary,
-                    c.message
+                    c.message,
+                    c.parent_count as i64
                 ])?;
             }
         }
@@ -864,12 +2019,11 @@ impl Db {
         Ok(())
     }
 
+    /// One page of a parsed commit search, ranked by bm25 weighted by recency; without
+    /// free text it lists the newest commits passing the filters.
     pub fn search_commits_paged(
         &self,
-        query: &str,
-        branch: Option<&str>,
-        in_summary: bool,
-        in_message: bool,
+        q: &query::CommitQuery,
         page: usize,
         per_page: usize,
     ) -> Result<Paged<CommitHit>> {
@@ -877,129 +2031,217 @@ impl Db {
         let per_page = per_page.clamp(1, 200);
         let offset = (page - 1) * per_page;
 
-        let q = format!("%{}%", query);
-        let b = branch.map(|s| format!("%{}%", s));
-
-        let (in_summary, in_message) = if !(in_summary || in_message) {
-            (true, true)
-        } else {
-            (in_summary, in_message)
+        let mut args = Vec::<Value>::new();
+        let (from_sql, match_sql, rank_sql, marks_sql) = match fts_query(&q.text, &q.columns) {
+            Some(q) => {
+                args.push(Value::from(q));
+                let now = chrono::Utc::now().timestamp();
+                (
+                    "commits_fts JOIN commits c ON c.id = commits_fts.rowid",
+                    "commits_fts MATCH ?1",
+                    format!(
+                        "bm25(commits_fts, {COMMIT_FTS_WEIGHTS}) \
+                         / (1.0 + MAX({now} - COALESCE(c.time, 0), 0) / {COMMIT_RECENCY_HALF_SECS}.0)"
+                    ),
+                    "highlight(commits_fts, 0, char(2), char(3)), \
+                     snippet(commits_fts, 1, char(2), char(3), '…', 24)",
+                )
+            }
+            None => ("commits c", "1", "0.0".to_string(), "NULL, NULL"),
         };
+        let filter_sql = q.filter.sql(&mut args);
+        let where_sql = format!("{match_sql} AND {filter_sql} AND {LIVE_SQL}");
 
-        let mut where_parts = Vec::<&str>::new();
-        if in_summary {
-            where_parts.push("c.summary LIKE ?1");
-        }
-        if in_message {
-            where_parts.push("COALESCE(c.message, '') LIKE ?1");
-        }
-        let where_sql = where_parts.join(" OR ");
+        let total_sql =
+            format!("SELECT COUNT(*) FROM {from_sql} JOIN repos r ON r.id = c.repo_id WHERE {where_sql}");
+        let total: usize = self
+            .conn
+            .query_row(&total_sql, params_from_iter(&args), |r| r.get::<_, i64>(0))? as usize;
 
-        let total: usize = if let Some(b) = &b {
-            let sql = format!(
-                r#"
-                SELECT COUNT(*)
-                FROM commits c
-                JOIN repos r ON r.id = c.repo_id
-                WHERE ({where_sql})
-                  AND (c.branch_name LIKE ?2 OR c.refname LIKE ?2)
-                "#
-            );
-            self.conn
-                .query_row(&sql, params![q, b], |r| r.get::<_, i64>(0))? as usize
-        } else {
-            let sql = format!(
-                r#"
-                SELECT COUNT(*)
-                FROM commits c
-                WHERE {where_sql}
-                "#
-            );
-            self.conn
-                .query_row(&sql, [q.as_str()], |r| r.get::<_, i64>(0))? as usize
-        };
+        let sql = format!(
+            r#"
+            SELECT r.name, r.path, c.branch_kind, c.branch_name, c.refname, c.oid, c.time, c.summary,
+                   c.author, c.email, {rank_sql} AS score, {marks_sql}
+            FROM {from_sql}
+            JOIN repos r ON r.id = c.repo_id
+            WHERE {where_sql}
+            ORDER BY score ASC, COALESCE(c.time, 0) DESC
+            LIMIT ?{} OFFSET ?{}
+            "#,
+            args.len() + 1,
+            args.len() + 2
+        );
+        args.push(Value::from(per_page as i64));
+        args.push(Value::from(offset as i64));
+        let mut stmt = self.conn.prepare(&sql)?;
+        let iter = stmt.query_map(params_from_iter(args), |r| {
+            let mut highlights = Vec::new();
+            for (i, column) in ["summary", "message"].into_iter().enumerate() {
+                if let Some(text) = r.get::<_, Option<String>>(11 + i)? {
+                    if text.contains(MARK_START) {
+                        highlights.push((column, join_cjk(text)));
+                    }
+                }
+            }
+            Ok(CommitHit {
+                repo_name: r.get(0)?,
+                repo_path: r.get(1)?,
+                branch_kind: r.get(2)?,
+                branch_name: r.get(3)?,
+                refname: r.get(4)?,
+                oid: r.get(5)?,
+                time: r.get(6)?,
+                summary: r.get(7)?,
+                author: r.get(8)?,
+                email: r.get(9)?,
+                rank: r.get(10)?,
+                highlights,
+            })
+        })?;
 
         let mut items = Vec::new();
-        if let Some(b) = b {
-            let sql = format!(
-                r#"
-                SELECT r.name, r.path, c.branch_kind, c.branch_name, c.refname, c.oid, c.time, c.summary, c.message
-                FROM commits c
-                JOIN repos r ON r.id = c.repo_id
-                WHERE ({where_sql})
-                  AND (c.branch_name LIKE ?2 OR c.refname LIKE ?2)
-                ORDER BY COALESCE(c.time, 0) DESC
-                LIMIT ?3 OFFSET ?4
-                "#
-            );
-            let mut stmt = self.conn.prepare(&sql)?;
-            let iter = stmt.query_map(params![q, b, per_page as i64, offset as i64], |r| {
-                Ok(CommitHit {
-                    repo_name: r.get(0)?,
-                    repo_path: r.get(1)?,
-                    branch_kind: r.get(2)?,
-                    branch_name: r.get(3)?,
-                    refname: r.get(4)?,
-                    oid: r.get(5)?,
-                    time: r.get(6)?,
-                    summary: r.get(7)?,
-                    message: r.get(8)?,
-                })
-            })?;
-            for row in iter {
-                items.push(row?);
+        for row in iter {
+            items.push(row?);
+        }
+
+        Ok(Paged { total, items })
+    }
+
+    /// Live repos matching `query`, best first. Every whitespace-separated term must fuzzy
+    /// match the name, an alias or the path; the mean match quality is then weighted by
+    /// frecency. A query equal to an alias puts that repo first regardless of score. An
+    /// empty query ranks every repo by frecency alone.
+    pub fn rank_repos(&self, query: &str, limit: usize) -> Result<Vec<RankedRepo>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut aliases: HashMap<i64, Vec<String>> = HashMap::new();
+        {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT repo_id, alias FROM repo_aliases ORDER BY alias")?;
+            let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (id, alias) = row?;
+                aliases.entry(id).or_default().push(alias);
             }
-        } else {
-            let sql = format!(
-                r#"
-                SELECT r.name, r.path, c.branch_kind, c.branch_name, c.refname, c.oid, c.time, c.summary, c.message
-                FROM commits c
-                JOIN repos r ON r.id = c.repo_id
-                WHERE {where_sql}
-                ORDER BY COALESCE(c.time, 0) DESC
-                LIMIT ?2 OFFSET ?3
-                "#
-            );
-            let mut stmt = self.conn.prepare(&sql)?;
-            let iter = stmt.query_map(params![q, per_page as i64, offset as i64], |r| {
-                Ok(CommitHit {
-                    repo_name: r.get(0)?,
-                    repo_path: r.get(1)?,
-                    branch_kind: r.get(2)?,
-                    branch_name: r.get(3)?,
-                    refname: r.get(4)?,
-                    oid: r.get(5)?,
-                    time: r.get(6)?,
-                    summary: r.get(7)?,
-                    message: r.get(8)?,
-                })
-            })?;
-            for row in iter {
-                items.push(row?);
+        }
+        let mut access: HashMap<i64, (i64, i64)> = HashMap::new();
+        {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT repo_id, COUNT(*), MAX(ts) FROM repo_access GROUP BY repo_id")?;
+            let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, (r.get(1)?, r.get(2)?))))?;
+            for row in rows {
+                let (id, stats) = row?;
+                access.insert(id, stats);
             }
         }
 
-        Ok(Paged { total, items })
+        let terms: Vec<&str> = query.split_whitespace().collect();
+        let whole = query.trim().to_lowercase();
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, path, name FROM repos WHERE removed_ts IS NULL")?;
+        let rows = stmt.query_map([], |r| {
+            Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?, r.get::<_, String>(2)?))
+        })?;
+        let mut ranked: Vec<(bool, RankedRepo)> = Vec::new();
+        'repos: for row in rows {
+            let (id, path, name) = row?;
+            let repo_aliases = aliases.remove(&id).unwrap_or_default();
+            let mut total = 0.0;
+            for term in &terms {
+                let best = std::iter::once(rank::fuzzy_score(term, &name))
+                    .chain(repo_aliases.iter().map(|a| rank::fuzzy_score(term, a)))
+                    .chain(std::iter::once(rank::fuzzy_score(term, &path).map(|s| s * 0.8)))
+                    .flatten()
+                    .fold(None, |acc: Option<f64>, s| Some(acc.map_or(s, |a| a.max(s))));
+                match best {
+                    Some(s) => total += s,
+                    None => continue 'repos,
+                }
+            }
+            let matched = if terms.is_empty() { 1.0 } else { total / terms.len() as f64 };
+            let (count, last) = access.get(&id).copied().unzip();
+            let frecency = rank::frecency(count.unwrap_or(0), last, now);
+            let alias_hit = repo_aliases.contains(&whole);
+            let score = rank::rank(matched, frecency);
+            ranked.push((alias_hit, RankedRepo { path, name, aliases: repo_aliases, score }));
+        }
+        ranked.sort_by(|(ah, a), (bh, b)| {
+            bh.cmp(ah)
+                .then(b.score.total_cmp(&a.score))
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        Ok(ranked.into_iter().take(limit).map(|(_, r)| r).collect())
     }
 
+    /// Resolves a CLI/API repo argument: an absolute path must be indexed as-is, anything
+    /// else is the best hit of [`Db::rank_repos`].
     pub fn resolve_repo_path(&self, input: &str) -> Result<Option<String>> {
         if Path::new(input).is_absolute() {
             let exists: Option<String> = self
                 .conn
-                .query_row("SELECT path FROM repos WHERE path = ?1", [input], |r| r.get(0))
+                .query_row(
+                    "SELECT path FROM repos WHERE path = ?1 AND removed_ts IS NULL",
+                    [input],
+                    |r| r.get(0),
+                )
                 .optional()?;
             return Ok(exists);
         }
-        let q = format!("%{}%", input);
-        let row: Option<String> = self
+        if input.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(self.rank_repos(input, 1)?.pop().map(|r| r.path))
+    }
+
+    /// Resolves the repo argument of a command that changes or reads one repo: an indexed
+    /// absolute path, an alias or a repo name, all exact (aliases and names ignore case).
+    /// Unlike [`Db::resolve_repo_path`] nothing is guessed, so a typo cannot hit another
+    /// repo; a miss, or a name shared by several repos, is an error listing candidates.
+    pub fn find_repo_path(&self, input: &str) -> Result<String> {
+        let input = input.trim();
+        if Path::new(input).is_absolute() {
+            return self
+                .resolve_repo_path(input)?
+                .with_context(|| format!("repo not indexed: {input}"));
+        }
+        let alias: Option<String> = self
             .conn
             .query_row(
-                "SELECT path FROM repos WHERE name LIKE ?1 OR path LIKE ?1 ORDER BY name ASC LIMIT 1",
-                [q],
+                "SELECT r.path FROM repo_aliases a JOIN repos r ON r.id = a.repo_id \
+                 WHERE a.alias = ?1 AND r.removed_ts IS NULL",
+                [input.to_lowercase()],
                 |r| r.get(0),
             )
             .optional()?;
-        Ok(row)
+        if let Some(path) = alias {
+            return Ok(path);
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT path FROM repos WHERE removed_ts IS NULL AND fold(name) = fold(?1) ORDER BY path",
+        )?;
+        let named = stmt
+            .query_map([input], |r| r.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        match named.as_slice() {
+            [path] => Ok(path.clone()),
+            [] => {
+                let close: Vec<String> = self.rank_repos(input, 3)?.into_iter().map(|r| r.path).collect();
+                if close.is_empty() {
+                    anyhow::bail!("repo not found: {input}");
+                }
+                anyhow::bail!(
+                    "repo not found: {input}; give a path, alias or exact name. Close matches:\n  {}",
+                    close.join("\n  ")
+                )
+            }
+            _ => anyhow::bail!(
+                "{input} names several repos; give the path:\n  {}",
+                named.join("\n  ")
+            ),
+        }
     }
 
     fn ensure_tag(&self, tag: &str) -> Result<i64> {
@@ -1011,6 +2253,19 @@ impl Db {
         Ok(id)
     }
 
+    /// `name` and the tags below it, as (id, name).
+    fn tag_subtree(&self, name: &str) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT id, name FROM tags t2 WHERE {}", tag_match_sql(1)))?;
+        let iter = stmt.query_map([name], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        let mut out = Vec::new();
+        for row in iter {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
     fn repo_id_by_path(&self, repo_path: &str) -> Result<Option<i64>> {
         let id: Option<i64> = self
             .conn
@@ -1021,18 +2276,151 @@ impl Db {
 
     fn prune_orphan_tags(&self) -> Result<usize> {
         let n = self.conn.execute(
-            "DELETE FROM tags WHERE NOT EXISTS (SELECT 1 FROM repo_tags WHERE tag_id = tags.id)",
+            "DELETE FROM tags WHERE color IS NULL AND description IS NULL \
+             AND NOT EXISTS (SELECT 1 FROM repo_tags WHERE tag_id = tags.id)",
             [],
         )?;
-        Ok(n as usize)
+        Ok(n)
+    }
+}
+
+/// Turns the free text of a query into an FTS5 expression: every word must match as a
+/// prefix (`pay` finds `payments`), every phrase as written, and no excluded term may
+/// match. Punctuation splits words the way the tokenizer does, so `payments-api` means
+/// the phrase `payments api`, and CJK characters split the same way as in the index, so
+/// `支付` is the phrase `支 付` and matches inside `修复支付超时`. `columns` limits the
+/// match to those FTS columns (none: all of them). `None` when nothing searchable is left.
+fn fts_query(text: &query::Text, columns: &[&str]) -> Option<String> {
+    let term = |t: &query::Term| {
+        let (chunk, star) = match t {
+            query::Term::Word(w) => (w.as_str(), "*"),
+            query::Term::Phrase(p) => (p.as_str(), ""),
+        };
+        let chunk = text::split_cjk(chunk);
+        let words: Vec<&str> = chunk.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+        (!words.is_empty()).then(|| format!("\"{}\"{star}", words.join(" ")))
+    };
+    let include: Vec<String> = text.include.iter().filter_map(term).collect();
+    if include.is_empty() {
+        return None;
+    }
+    let mut expr = include.join(" ");
+    for t in text.exclude.iter().filter_map(term) {
+        expr = format!("({expr}) NOT {t}");
+    }
+    Some(if columns.is_empty() {
+        expr
+    } else {
+        format!("{{{}}} : ({expr})", columns.join(" "))
+    })
+}
+
+/// Trims a tag and its `/`-separated segments, dropping empty ones. Commas are not
+/// allowed because tag lists travel comma-joined.
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let name = tag
+        .split('/')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+    if name.is_empty() {
+        anyhow::bail!("empty tag");
+    }
+    if name.contains(',') {
+        anyhow::bail!("tag must not contain ',': {name}");
+    }
+    Ok(name)
+}
+
+/// `removed_reason` of repos hidden with `hide_repo`.
+pub const HIDDEN_REASON: &str = "hidden";
+
+/// Accesses kept per repo in `repo_access`.
+const ACCESS_HISTORY_LIMIT: i64 = 200;
+
+/// Allowed values of the `lifecycle` field.
+pub const LIFECYCLES: &[&str] = &["active", "maintenance", "deprecated"];
+
+/// Trims and lower-cases a custom field key. `=` and `,` are reserved for field
+/// filters (`owner=alice,lifecycle=active`).
+pub fn normalize_field_key(key: &str) -> Result<String> {
+    let key = key.trim().to_lowercase();
+    if key.is_empty() {
+        anyhow::bail!("empty field key");
+    }
+    if key.contains(['=', ',']) {
+        anyhow::bail!("field key must not contain '=' or ',': {key}");
+    }
+    Ok(key)
+}
+
+/// Trims and lower-cases a repo alias; it must be a single word.
+pub fn normalize_alias(alias: &str) -> Result<String> {
+    let alias = alias.trim().to_lowercase();
+    if alias.is_empty() {
+        anyhow::bail!("empty alias");
+    }
+    if alias.contains(char::is_whitespace) || alias.contains('/') {
+        anyhow::bail!("alias must not contain whitespace or '/': {alias}");
+    }
+    Ok(alias)
+}
+
+/// Parses a field filter: `key=value`, or a bare `key` for "has the field".
+pub fn parse_field_filter(s: &str) -> Result<(String, Option<String>)> {
+    match s.split_once('=') {
+        Some((key, value)) => Ok((normalize_field_key(key)?, Some(value.trim().to_string()))),
+        None => Ok((normalize_field_key(s)?, None)),
+    }
+}
+
+/// Moves `name` from namespace `from` to `to`: `rebase_tag("a/b", "a", "x")` is `x/b`.
+/// `None` when `name` is neither `from` nor below it.
+pub fn rebase_tag(name: &str, from: &str, to: &str) -> Option<String> {
+    if name == from {
+        return Some(to.to_string());
+    }
+    let rest = name.strip_prefix(from)?.strip_prefix('/')?;
+    Some(if to.is_empty() { rest.to_string() } else { format!("{to}/{rest}") })
+}
+
+fn removed_change(path: String, name: String, head: Option<String>, reason: &str) -> RepoChange {
+    RepoChange {
+        path,
+        name,
+        kind: "removed".to_string(),
+        old_value: head,
+        new_value: None,
+        detail: Some(reason.to_string()),
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    /// A normal repo with only a path and a name; tests set other fields with
+    /// struct-update syntax.
+    pub(crate) fn repo(path: &str, name: &str) -> RepoMeta {
+        RepoMeta {
+            path: path.to_string(),
+            name: name.to_string(),
+            default_branch: None,
+            last_commit_ts: None,
+            last_scan_ts: 1,
+            readme_excerpt: None,
+            origin_url: None,
+            fingerprint: None,
+            kind: "normal".to_string(),
+            parent_path: None,
+            head_oid: None,
+            identity: None,
+            language: None,
+        }
+    }
+
     #[test]
     fn upsert_and_search() -> Result<()> {
         let dir = tempdir()?;
@@ -1040,16 +2428,15 @@ mod tests {
         let db = Db::open(&db_path)?;
         db.init_schema()?;
 
-        db.upsert_repo(&RepoMeta {
-            path: "/tmp/repo-a".to_string(),
-            name: "repo-a".to_string(),
+        db.upsert_repos(&[RepoMeta {
             default_branch: Some("main".to_string()),
             last_commit_ts: Some(123),
             last_scan_ts: 456,
             readme_excerpt: Some("hello world".to_string()),
-        })?;
+            ..repo("/tmp/repo-a", "repo-a")
+        }])?;
 
-        let rows = db.search_repos("hello")?;
+        let rows = db.search_repos(&query::parse_repo("hello")?)?;
         assert_eq!(rows.len(), 1);
         assert_eq!(rows[0].name, "repo-a");
         Ok(())
@@ -1063,14 +2450,7 @@ mod tests {
         db.init_schema()?;
 
         let repo_path = "/tmp/repo-b";
-        db.upsert_repo(&RepoMeta {
-            path: repo_path.to_string(),
-            name: "repo-b".to_string(),
-            default_branch: None,
-            last_commit_ts: None,
-            last_scan_ts: 1,
-            readme_excerpt: None,
-        })?;
+        db.upsert_repos(&[repo(repo_path, "repo-b")])?;
 
         db.add_tag_to_repo(repo_path, "backend")?;
         db.add_tag_to_repo(repo_path, "backend")?;
@@ -1084,4 +2464,454 @@ mod tests {
         assert_eq!(tags, vec!["rust".to_string()]);
         Ok(())
     }
+
+    #[test]
+    fn tag_namespaces_aggregate_and_move_together() -> Result<()> {
+        let dir = tempdir()?;
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        for name in ["a", "b"] {
+            db.upsert_repos(&[repo(&format!("/tmp/{name}"), name)])?;
+        }
+        db.add_tag_to_repo("/tmp/a", " team / payments ")?;
+        db.add_tag_to_repo("/tmp/a", "team/search")?;
+        db.add_tag_to_repo("/tmp/b", "team/search")?;
+        db.add_tag_to_repo("/tmp/b", "team-x")?;
+        db.set_tag_color("team", Some("#0af"))?;
+
+        let tree = db
+            .list_tags_with_count()?
+            .into_iter()
+            .map(|t| (t.name, t.count, t.total))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tree,
+            vec![
+                ("team".to_string(), 0, 2),
+                ("team/payments".to_string(), 1, 1),
+                ("team/search".to_string(), 2, 2),
+                ("team-x".to_string(), 1, 1),
+            ]
+        );
+        let team = RepoFilter {
+            all: vec!["team".to_string()],
+            ..RepoFilter::default()
+        };
+        assert_eq!(db.list_repos(&team, false)?.len(), 2);
+        assert!(db.set_tag_color("team", Some("blue")).is_err());
+
+        assert!(db.rename_tag("team/search", "team-x").is_err());
+        assert_eq!(db.rename_tag("team", "org")?, 3);
+        assert_eq!(db.list_repo_tags("/tmp/a")?, vec!["org/payments", "org/search"]);
+        assert_eq!(db.merge_tags("org/payments", "org/search")?, 1);
+        assert_eq!(db.list_repo_tags("/tmp/a")?, vec!["org/search"]);
+        assert_eq!(db.delete_tag("org")?, 2);
+        assert_eq!(db.list_repo_tags("/tmp/b")?, vec!["team-x"]);
+        Ok(())
+    }
+
+    #[test]
+    fn tag_filters_combine_all_any_and_not() -> Result<()> {
+        let dir = tempdir()?;
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        for name in ["a", "b"] {
+            db.upsert_repos(&[repo(&format!("/tmp/{name}"), name)])?;
+        }
+        db.add_tag_to_repo("/tmp/a", "team/payments")?;
+        db.add_tag_to_repo("/tmp/a", "team/search")?;
+        db.add_tag_to_repo("/tmp/b", "team/search")?;
+        db.add_tag_to_repo("/tmp/b", "team-x")?;
+
+        let filter = |all: &[&str], any: &[&str], not: &[&str]| RepoFilter {
+            all: all.iter().map(|s| s.to_string()).collect(),
+            any: any.iter().map(|s| s.to_string()).collect(),
+            not: not.iter().map(|s| s.to_string()).collect(),
+            ..RepoFilter::default()
+        };
+        let names = |f: RepoFilter| -> Result<Vec<String>> {
+            Ok(db.list_repos(&f, false)?.into_iter().map(|r| r.name).collect())
+        };
+        assert_eq!(names(filter(&["team/search"], &[], &["team-x"]))?, vec!["a"]);
+        assert_eq!(names(filter(&[], &["team/payments", "team-x"], &[]))?, vec!["a", "b"]);
+        assert_eq!(names(filter(&["team"], &["team-x"], &[]))?, vec!["b"]);
+        let search = query::RepoQuery {
+            filter: filter(&[], &[], &["team/payments"]),
+            ..query::RepoQuery::default()
+        };
+        let paged = db.search_repos_with_tags_paged_filtered(&search, 1, 10)?;
+        assert_eq!(paged.total, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn notes_and_fields_search_and_filter() -> Result<()> {
+        let dir = tempdir()?;
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        for name in ["a", "b"] {
+            db.upsert_repos(&[repo(&format!("/tmp/{name}"), name)])?;
+        }
+        db.set_repo_note("/tmp/a", "# Runbook\nrestart the **billing** worker")?;
+        db.set_repo_field("/tmp/a", " Owner ", "alice")?;
+        db.set_repo_field("/tmp/a", "lifecycle", "deprecated")?;
+        db.set_repo_field("/tmp/b", "owner", "bob")?;
+        assert!(db.set_repo_field("/tmp/b", "lifecycle", "retired").is_err());
+        assert!(db.set_repo_field("/tmp/b", "a=b", "x").is_err());
+        assert!(db.set_repo_field("/tmp/b", "owner", "alice,bob").is_err());
+        assert_eq!(
+            db.list_repo_fields("/tmp/a")?,
+            vec![
+                ("lifecycle".to_string(), "deprecated".to_string()),
+                ("owner".to_string(), "alice".to_string()),
+            ]
+        );
+
+        let hits = db.search_repos_with_tags_paged_filtered(&query::parse_repo("billing in:notes")?, 1, 10)?;
+        assert_eq!(hits.items.len(), 1);
+        assert_eq!(hits.items[0].repo.name, "a");
+        let hits = db.search_repos_with_tags_paged_filtered(
+            &query::parse_repo("billing in:name,tag,readme,path")?,
+            1,
+            10,
+        )?;
+        assert_eq!(hits.total, 0);
+
+        let names = |fields: &[&str]| -> Result<Vec<String>> {
+            let filter = RepoFilter {
+                fields: fields.iter().map(|f| parse_field_filter(f)).collect::<Result<_>>()?,
+                ..RepoFilter::default()
+            };
+            Ok(db.list_repos(&filter, false)?.into_iter().map(|r| r.name).collect())
+        };
+        assert_eq!(names(&["owner"])?, vec!["a", "b"]);
+        assert_eq!(names(&["owner=BOB"])?, vec!["b"]);
+        assert_eq!(names(&["owner", "lifecycle=deprecated"])?, vec!["a"]);
+
+        db.set_repo_note("/tmp/a", "  ")?;
+        db.set_repo_field("/tmp/a", "owner", "")?;
+        assert_eq!(db.repo_note("/tmp/a")?, None);
+        assert_eq!(names(&["owner"])?, vec!["b"]);
+        Ok(())
+    }
+
+    #[test]
+    fn pinned_repos_list_first_and_archived_ones_hide() -> Result<()> {
+        let dir = tempdir()?;
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        for name in ["a", "b", "c"] {
+            db.upsert_repos(&[repo(&format!("/tmp/{name}"), name)])?;
+        }
+        db.set_repo_pinned("/tmp/b", true)?;
+        db.set_repo_archived("/tmp/a", true)?;
+        let names = |filter: &RepoFilter| -> Result<Vec<(String, bool)>> {
+            let listed = db.list_repos_with_tags_paged(filter, false, 1, 10)?;
+            Ok(listed.items.into_iter().map(|r| (r.repo.name, r.repo.pinned)).collect())
+        };
+        let owned = |v: &[(&str, bool)]| v.iter().map(|(n, p)| (n.to_string(), *p)).collect::<Vec<_>>();
+        assert_eq!(names(&RepoFilter::default())?, owned(&[("b", true), ("c", false)]));
+        let all = RepoFilter {
+            include_archived: true,
+            ..RepoFilter::default()
+        };
+        assert_eq!(names(&all)?, owned(&[("b", true), ("a", false), ("c", false)]));
+        db.set_repo_archived("/tmp/a", false)?;
+        assert_eq!(names(&RepoFilter::default())?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn prune_only_touches_repos_under_the_root() -> Result<()> {
+        let dir = tempdir()?;
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        for path in ["/src/a_b", "/src/a_b/x", "/src/a_b/kept", "/src/aXb/y", "/SRC/a_b/z", "/src/a_bc"] {
+            db.upsert_repos(&[repo(path, path.rsplit('/').next().unwrap_or(path))])?;
+        }
+        let keep = HashSet::from(["/src/a_b/kept".to_string()]);
+        let pruned = |dry_run| -> Result<Vec<String>> {
+            let mut paths: Vec<String> =
+                db.prune_under_root("/src/a_b", &keep, dry_run)?.into_iter().map(|c| c.path).collect();
+            paths.sort();
+            Ok(paths)
+        };
+        assert_eq!(pruned(true)?, vec!["/src/a_b", "/src/a_b/x"]);
+        assert_eq!(pruned(false)?, vec!["/src/a_b", "/src/a_b/x"]);
+        assert_eq!(db.list_repos(&RepoFilter::default(), false)?.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn restore_needs_an_exact_path_or_unique_name() -> Result<()> {
+        let dir = tempdir()?;
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        for path in ["/src/api", "/work/api", "/src/api-gateway"] {
+            db.upsert_repos(&[repo(path, path.rsplit('/').next().unwrap_or(path))])?;
+            db.hide_repo(path)?;
+        }
+        assert_eq!(db.restore_repo("gateway")?, None);
+        let shared = db.restore_repo("API").unwrap_err().to_string();
+        assert!(shared.contains("/src/api") && shared.contains("/work/api"), "{shared}");
+        assert_eq!(db.restore_repo("/work/api")?.as_deref(), Some("/work/api"));
+        assert_eq!(db.restore_repo("api")?.as_deref(), Some("/src/api"));
+        assert_eq!(db.restore_repo("API-Gateway")?.as_deref(), Some("/src/api-gateway"));
+        assert!(db.list_trash()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_ranks_by_alias_fuzzy_match_and_frecency() -> Result<()> {
+        let dir = tempdir()?;
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        for name in ["api", "payments-api", "web-frontend"] {
+            db.upsert_repos(&[repo(&format!("/src/{name}"), name)])?;
+        }
+
+        assert_eq!(db.resolve_repo_path("api")?.as_deref(), Some("/src/api"));
+        assert_eq!(db.resolve_repo_path("wfe")?.as_deref(), Some("/src/web-frontend"));
+        assert_eq!(db.resolve_repo_path("src web")?.as_deref(), Some("/src/web-frontend"));
+        assert_eq!(db.resolve_repo_path("zzz")?, None);
+        assert_eq!(db.resolve_repo_path("/src/api")?.as_deref(), Some("/src/api"));
+
+        // Heavily used repos win looser matches.
+        for _ in 0..5 {
+            db.record_access("/src/payments-api")?;
+        }
+        assert_eq!(db.resolve_repo_path("api")?.as_deref(), Some("/src/payments-api"));
+        let ranked = db.rank_repos("", 10)?;
+        assert_eq!(ranked[0].name, "payments-api");
+        assert_eq!(ranked.len(), 3);
+
+        // An exact alias beats everything.
+        assert_eq!(db.add_repo_alias("/src/api", " Core ")?, "core");
+        db.add_repo_alias("/src/api", "core")?;
+        assert!(db.add_repo_alias("/src/web-frontend", "core").is_err());
+        assert!(db.add_repo_alias("/src/web-frontend", "two words").is_err());
+        db.add_repo_alias("/src/web-frontend", "api")?;
+        assert_eq!(db.resolve_repo_path("core")?.as_deref(), Some("/src/api"));
+        assert_eq!(db.resolve_repo_path("API")?.as_deref(), Some("/src/web-frontend"));
+        assert_eq!(db.list_repo_aliases()?.len(), 2);
+        assert!(db.remove_repo_alias("api")?);
+        assert!(!db.remove_repo_alias("api")?);
+        assert_eq!(db.resolve_repo_path("api")?.as_deref(), Some("/src/payments-api"));
+
+        // Commands that change a repo take exact names only: a typo fails and the repo
+        // the fuzzy match would have picked stays listed.
+        assert_eq!(db.find_repo_path("API")?, "/src/api");
+        assert_eq!(db.find_repo_path("core")?, "/src/api");
+        let miss = db.find_repo_path("paymnts").unwrap_err().to_string();
+        assert!(miss.contains("/src/payments-api"), "{miss}");
+        assert!(db.find_repo_path("/src/nope").is_err());
+        assert!(db.find_repo_path("payments-api/").is_err());
+        assert_eq!(db.list_repos(&RepoFilter::default(), false)?.len(), 3);
+        db.upsert_repos(&[repo("/work/api", "api")])?;
+        let shared = db.find_repo_path("api").unwrap_err().to_string();
+        assert!(shared.contains("/src/api") && shared.contains("/work/api"), "{shared}");
+        Ok(())
+    }
+
+    #[test]
+    fn full_text_search_ranks_by_field_and_stays_in_sync() -> Result<()> {
+        let dir = tempdir()?;
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        for (name, readme) in [
+            ("billing-service", "Charges cards"),
+            ("ledger", "Double-entry ledger used by the billing team"),
+            ("web", "Marketing site"),
+        ] {
+            db.upsert_repos(&[RepoMeta {
+                readme_excerpt: Some(readme.to_string()),
+                origin_url: (name == "ledger").then(|| "git@github.com:acme/ledger.git".to_string()),
+                identity: (name == "ledger").then(|| "c0ffee@github.com/acme/ledger".to_string()),
+                language: (name == "web").then(|| "TypeScript".to_string()),
+                ..repo(&format!("/src/{name}"), name)
+            }])?;
+        }
+        let search = |q: &str| -> Result<Vec<String>> {
+            let paged = db.search_repos_with_tags_paged_filtered(&query::parse_repo(q)?, 1, 10)?;
+            Ok(paged.items.into_iter().map(|h| h.repo.name).collect())
+        };
+
+        // A name hit outranks a README hit; words are prefixes and match in any order.
+        assert_eq!(search("billing")?, vec!["billing-service", "ledger"]);
+        assert_eq!(search("team bill")?, vec!["ledger"]);
+        assert_eq!(search("\"entry ledger\"")?, vec!["ledger"]);
+        assert!(search("\"ledger entry\"")?.is_empty());
+        assert_eq!(search("billing in:readme")?, vec!["ledger"]);
+        assert_eq!(search("")?.len(), 3);
+
+        // Query filters narrow the full-text hits or stand alone.
+        assert_eq!(search("lang:typescript")?, vec!["web"]);
+        assert_eq!(search("billing -lang:typescript -tag:x")?, vec!["billing-service", "ledger"]);
+        assert_eq!(search("origin:GITHUB.com/acme")?, vec!["ledger"]);
+        assert_eq!(search("root:/src/")?.len(), 3);
+        assert!(search("root:/sr")?.is_empty());
+        db.set_repo_pinned("/src/web", true)?;
+        assert_eq!(search("is:pinned")?, vec!["web"]);
+        assert_eq!(search("-is:pinned")?.len(), 2);
+        db.set_repo_pinned("/src/ledger", true)?;
+        assert_eq!(search("billing")?, vec!["ledger", "billing-service"]);
+        db.set_repo_pinned("/src/ledger", false)?;
+
+        let hits = db.search_repos_with_tags_paged_filtered(&query::parse_repo("charge")?, 1, 10)?;
+        assert_eq!(
+            hits.items[0].highlights,
+            vec![("readme", "\u{2}Charges\u{3} cards".to_string())]
+        );
+
+        // Tags, tag renames, notes and fields are indexed as they change.
+        db.add_tag_to_repo("/src/web", "team/growth")?;
+        assert_eq!(search("growth")?, vec!["web"]);
+        db.rename_tag("team/growth", "team/brand")?;
+        assert!(search("growth")?.is_empty());
+        assert_eq!(search("brand in:tag")?, vec!["web"]);
+        db.set_repo_note("/src/web", "deploys via netlify")?;
+        db.set_repo_field("/src/ledger", "owner", "alice")?;
+        assert_eq!(search("netlify")?, vec!["web"]);
+        assert_eq!(search("alice")?, vec!["ledger"]);
+        db.set_repo_note("/src/web", "deploys via vercel")?;
+        assert!(search("netlify")?.is_empty());
+
+        // Re-applying unchanged rule tags writes nothing, so no repo is re-indexed.
+        let rule_tags = vec![("/src/web".to_string(), vec!["stack/ts".to_string()])];
+        db.replace_rule_tags(&rule_tags)?;
+        let total_changes = || db.conn.query_row("SELECT total_changes()", [], |r| r.get::<_, i64>(0));
+        let before = total_changes()?;
+        db.replace_rule_tags(&rule_tags)?;
+        assert_eq!(total_changes()?, before);
+        assert_eq!(search("stack in:tag")?, vec!["web"]);
+        db.replace_rule_tags(&[("/src/web".to_string(), Vec::new())])?;
+        assert!(search("stack in:tag")?.is_empty());
+        assert_eq!(
+            fts_query(&query::parse_repo("pay-api \"a b\" -wip")?.text, &["name", "tags"]).as_deref(),
+            Some("{name tags} : ((\"pay api\"* \"a b\") NOT \"wip\"*)")
+        );
+        assert_eq!(fts_query(&query::parse_repo(" - :: ")?.text, &[]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn commit_search_uses_fts_with_recency() -> Result<()> {
+        let dir = tempdir()?;
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        db.upsert_repos(&[RepoMeta {
+            default_branch: Some("main".to_string()),
+            ..repo("/src/api", "api")
+        }])?;
+        let now = chrono::Utc::now().timestamp();
+        let commit = |oid: &str, branch: &str, time: i64, message: &str| CommitIndexRow {
+            refname: format!("refs/heads/{branch}"),
+            branch_kind: "local".to_string(),
+            branch_name: branch.to_string(),
+            oid: oid.to_string(),
+            time: Some(time),
+            author: Some(if oid == "body" { "Alice" } else { "Bob" }.to_string()),
+            email: Some("dev@example.com".to_string()),
+            summary: message.lines().next().map(str::to_string),
+            message: Some(message.to_string()),
+            parent_count: if oid == "merge" { 2 } else { 1 },
+        };
+        let index = |commits: &[CommitIndexRow]| db.replace_commit_index_for_repo("/src/api", &[], commits);
+        index(&[
+            commit("old", "main", now - 3 * 365 * 86_400, "Fix login timeout"),
+            commit("new", "main", now - 86_400, "Fix login timeout again"),
+            commit("body", "feature", now, "Refactor session\n\nThe login timeout was too short."),
+            commit("merge", "main", now - 2 * 86_400, "Merge branch 'feature'"),
+        ])?;
+        let search = |q: &str| db.search_commits_paged(&query::parse_commit(q, now, &[])?, 1, 10);
+        let oids = |q: &str| -> Result<Vec<String>> {
+            Ok(search(q)?.items.into_iter().map(|c| c.oid).collect())
+        };
+
+        // Equal relevance: the recent commit ranks above the old one.
+        assert_eq!(oids("timeout login in:summary")?, vec!["new", "old"]);
+        assert_eq!(oids("timeout log")?.len(), 3);
+        assert_eq!(oids("timeout -again")?, vec!["body", "old"]);
+        assert_eq!(oids("\"login timeout was\"")?, vec!["body"]);
+        assert_eq!(oids("login branch:FEAT")?, vec!["body"]);
+        assert_eq!(oids("")?, vec!["body", "new", "merge", "old"]);
+        let hit = &search("session")?.items[0];
+        assert_eq!(hit.highlights[0], ("summary", "Refactor \u{2}session\u{3}".to_string()));
+
+        // Filters work with and without free text.
+        assert_eq!(oids("author:alice")?, vec!["body"]);
+        assert_eq!(oids("timeout -author:ALICE")?, vec!["new", "old"]);
+        assert_eq!(oids("is:merge")?, vec!["merge"]);
+        assert_eq!(oids("-is:merge after:30d before:1h")?, vec!["new"]);
+        assert_eq!(oids("repo:API timeout")?.len(), 3);
+        assert!(oids("repo:web")?.is_empty());
+        assert_eq!(oids("email:EXAMPLE.com")?.len(), 4);
+        let mine = |q: &str, me: &[&str]| -> Result<Vec<String>> {
+            let me: Vec<String> = me.iter().map(|s| s.to_string()).collect();
+            let hits = db.search_commits_paged(&query::parse_commit(q, now, &me)?, 1, 10)?;
+            Ok(hits.items.into_iter().map(|c| c.oid).collect())
+        };
+        assert_eq!(mine("is:mine", &["ALICE", "alice@work.example"])?, vec!["body"]);
+        assert_eq!(mine("is:mine after:3d -is:merge", &["bob"])?, vec!["new"]);
+        assert!(mine("is:mine", &["Ali"])?.is_empty());
+
+        // Re-indexing replaces the old rows in the full-text index too.
+        index(&[commit("other", "main", now, "Bump deps")])?;
+        assert!(oids("login")?.is_empty());
+        assert_eq!(search("bump")?.total, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn search_matches_cjk_substrings_and_folds_unicode_case() -> Result<()> {
+        let dir = tempdir()?;
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        db.upsert_repos(&[RepoMeta {
+            default_branch: Some("main".to_string()),
+            readme_excerpt: Some("后端服务：订单与支付".to_string()),
+            language: Some("Ελληνικά".to_string()),
+            ..repo("/src/商城", "商城")
+        }])?;
+        let now = chrono::Utc::now().timestamp();
+        db.replace_commit_index_for_repo(
+            "/src/商城",
+            &[],
+            &[CommitIndexRow {
+                refname: "refs/heads/main".to_string(),
+                branch_kind: "local".to_string(),
+                branch_name: "main".to_string(),
+                oid: "a1".to_string(),
+                time: Some(now),
+                author: Some("ÉMILE Zoé".to_string()),
+                email: None,
+                summary: Some("修复支付超时问题".to_string()),
+                message: Some("修复支付超时问题".to_string()),
+                parent_count: 1,
+            }],
+        )?;
+        let repos = |q: &str| -> Result<Vec<RepoHit>> {
+            Ok(db.search_repos_with_tags_paged_filtered(&query::parse_repo(q)?, 1, 10)?.items)
+        };
+        let commits = |q: &str| db.search_commits_paged(&query::parse_commit(q, now, &[])?, 1, 10);
+
+        let hits = repos("订单")?;
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].highlights, vec![("readme", "后端服务：\u{2}订单\u{3}与支付".to_string())]);
+        assert_eq!(repos("商")?.len(), 1);
+        assert!(repos("单订")?.is_empty());
+        assert_eq!(repos("lang:ελληνικά")?.len(), 1);
+
+        let hit = &commits("支付 author:émile")?.items[0];
+        assert_eq!(hit.highlights[0], ("summary", "修复\u{2}支付\u{3}超时问题".to_string()));
+        assert_eq!(commits("\"超时\" repo:商城")?.total, 1);
+        assert_eq!(commits("author:ZOÉ")?.total, 1);
+
+        // An index built before CJK splitting is dropped and rebuilt on the next start.
+        db.conn.pragma_update(None, "user_version", 0)?;
+        db.init_schema()?;
+        assert_eq!(commits("超时")?.total, 1);
+        assert_eq!(repos("支付")?.len(), 1);
+        Ok(())
+    }
 }
@@ -0,0 +1,242 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use serde::Serialize;
+use std::any::Any;
+use std::collections::{BTreeMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// Finished jobs kept around for `/api/jobs`; older ones are dropped.
+const KEEP_FINISHED: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// Point-in-time view of a job, as streamed to the Web UI.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobSnapshot {
+    pub id: u64,
+    pub kind: String,
+    pub label: String,
+    pub state: JobState,
+    pub discovered: usize,
+    pub processed: usize,
+    pub current: Option<String>,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub started_ts: i64,
+    pub finished_ts: Option<i64>,
+}
+
+struct JobEntry {
+    cancel: Arc<AtomicBool>,
+    rx: watch::Receiver<JobSnapshot>,
+}
+
+#[derive(Default)]
+struct Inner {
+    next_id: u64,
+    jobs: BTreeMap<u64, JobEntry>,
+    busy_roots: HashSet<String>,
+}
+
+/// Runs long operations (scans, prunes, commit-index rebuilds) off the request path.
+#[derive(Clone, Default)]
+pub struct JobManager {
+    inner: Arc<Mutex<Inner>>,
+}
+
+/// Passed to a running job to report progress and observe cancellation.
+pub struct JobHandle {
+    cancel: Arc<AtomicBool>,
+    tx: watch::Sender<JobSnapshot>,
+}
+
+impl JobHandle {
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.cancel
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    pub fn progress(&self, discovered: usize, processed: usize, current: Option<&str>) {
+        self.tx.send_modify(|s| {
+            s.discovered = discovered;
+            s.processed = processed;
+            s.current = current.map(|c| c.to_string());
+        });
+    }
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts `work` on the blocking pool. Fails if any of `roots` is already being
+    /// worked on by another job. A panic in `work` fails the job like an error does, so
+    /// its roots are always released.
+    pub fn spawn<F>(&self, kind: &str, label: &str, roots: Vec<String>, work: F) -> Result<u64>
+    where
+        F: FnOnce(&JobHandle) -> Result<serde_json::Value> + Send + 'static,
+    {
+        let mut inner = self.inner.lock().expect("job manager lock");
+        if let Some(busy) = roots.iter().find(|r| inner.busy_roots.contains(*r)) {
+            bail!("another job is already working on {busy}");
+        }
+        inner.next_id += 1;
+        let id = inner.next_id;
+        let (tx, rx) = watch::channel(JobSnapshot {
+            id,
+            kind: kind.to_string(),
+            label: label.to_string(),
+            state: JobState::Running,
+            discovered: 0,
+            processed: 0,
+            current: None,
+            result: None,
+            error: None,
+            started_ts: Utc::now().timestamp(),
+            finished_ts: None,
+        });
+        let cancel = Arc::new(AtomicBool::new(false));
+        inner.busy_roots.extend(roots.iter().cloned());
+        inner.jobs.insert(
+            id,
+            JobEntry {
+                cancel: Arc::clone(&cancel),
+                rx,
+            },
+        );
+        prune_finished(&mut inner);
+        drop(inner);
+
+        let manager = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let handle = JobHandle { cancel, tx };
+            let outcome = std::panic::catch_unwind(AssertUnwindSafe(|| work(&handle)))
+                .unwrap_or_else(|panic| Err(anyhow!("job panicked: {}", panic_message(&*panic))));
+            let cancelled = handle.is_cancelled();
+            handle.tx.send_modify(|s| {
+                s.finished_ts = Some(Utc::now().timestamp());
+                s.current = None;
+                match outcome {
+                    Ok(v) => {
+                        s.state = JobState::Done;
+                        s.result = Some(v);
+                    }
+                    Err(_) if cancelled => s.state = JobState::Cancelled,
+                    Err(e) => {
+                        s.state = JobState::Failed;
+                        s.error = Some(format!("{e:#}"));
+                    }
+                }
+            });
+            let mut inner = manager.inner.lock().expect("job manager lock");
+            for r in &roots {
+                inner.busy_roots.remove(r);
+            }
+            // Dropping `handle` closes the channel, which ends any open event streams.
+        });
+        Ok(id)
+    }
+
+    pub fn list(&self) -> Vec<JobSnapshot> {
+        let inner = self.inner.lock().expect("job manager lock");
+        inner.jobs.values().rev().map(|j| j.rx.borrow().clone()).collect()
+    }
+
+    pub fn get(&self, id: u64) -> Option<JobSnapshot> {
+        let inner = self.inner.lock().expect("job manager lock");
+        inner.jobs.get(&id).map(|j| j.rx.borrow().clone())
+    }
+
+    pub fn subscribe(&self, id: u64) -> Option<watch::Receiver<JobSnapshot>> {
+        let inner = self.inner.lock().expect("job manager lock");
+        inner.jobs.get(&id).map(|j| j.rx.clone())
+    }
+
+    /// Requests cancellation; the job stops at its next checkpoint.
+    pub fn cancel(&self, id: u64) -> bool {
+        let inner = self.inner.lock().expect("job manager lock");
+        match inner.jobs.get(&id) {
+            Some(j) if j.rx.borrow().state == JobState::Running => {
+                j.cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause")
+}
+
+fn prune_finished(inner: &mut Inner) {
+    let finished = inner
+        .jobs
+        .iter()
+        .filter(|(_, j)| j.rx.borrow().state != JobState::Running)
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+    if finished.len() > KEEP_FINISHED {
+        for id in &finished[..finished.len() - KEEP_FINISHED] {
+            inner.jobs.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rejects_second_scan_of_busy_root_and_cancels() -> Result<()> {
+        let jobs = JobManager::new();
+        let id = jobs.spawn("scan", "r", vec!["/r".to_string()], |job| {
+            while !job.is_cancelled() {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            bail!("cancelled")
+        })?;
+        let again = jobs.spawn("scan", "r", vec!["/r".to_string()], |_| Ok(serde_json::Value::Null));
+        assert!(again.is_err());
+
+        let mut rx = jobs.subscribe(id).expect("job");
+        assert!(jobs.cancel(id));
+        while rx.changed().await.is_ok() {}
+        assert_eq!(rx.borrow().state, JobState::Cancelled);
+
+        // The root is released before the channel closes.
+        jobs.spawn("scan", "r", vec!["/r".to_string()], |_| Ok(serde_json::Value::Null))?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn panicking_job_fails_and_releases_its_roots() -> Result<()> {
+        let jobs = JobManager::new();
+        let id = jobs.spawn("scan", "r", vec!["/r".to_string()], |_| panic!("boom"))?;
+        let mut rx = jobs.subscribe(id).expect("job");
+        while rx.changed().await.is_ok() {}
+        let snapshot = jobs.get(id).expect("job");
+        assert_eq!(snapshot.state, JobState::Failed);
+        assert_eq!(snapshot.error.as_deref(), Some("job panicked: boom"));
+        jobs.spawn("scan", "r", vec!["/r".to_string()], |_| Ok(serde_json::Value::Null))?;
+        Ok(())
+    }
+}
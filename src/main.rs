@@ -1,15 +1,31 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 
 mod config;
 mod commits;
+mod dates;
 mod db;
+mod jobs;
+mod output;
+mod query;
+mod rank;
 mod scan;
+mod shell;
+mod tag_rules;
+mod text;
+mod tui;
+mod watch;
 mod web;
 
 #[derive(Parser, Debug)]
 #[command(name = "coderoom", version, about = "Local git repo indexer (offline)")]
 struct Cli {
+    /// 列表类命令的输出格式：终端下默认 table（对齐的表格、相对时间），管道中默认 tsv
+    #[arg(long, global = true, value_enum)]
+    format: Option<output::Format>,
+    /// 只输出这些字段（逗号分隔，按给定顺序），例如 name,path,tags；字段名与 JSON 输出一致
+    #[arg(long, global = true, value_delimiter = ',')]
+    fields: Vec<String>,
     #[command(subcommand)]
     command: Command,
 }
@@ -26,44 +42,163 @@ enum Command {
         /// 扫描深度限制（默认不限制）
         #[arg(long)]
         max_depth: Option<usize>,
-        /// 扫描完成后清理 root 下已删除/移动的仓库记录
+        /// 扫描完成后把 root 下已删除/移动的仓库移入回收站
         #[arg(long)]
         prune: bool,
+        /// 配合 --prune：只列出将被移入回收站的仓库，不做修改
+        #[arg(long, requires = "prune")]
+        dry_run: bool,
+        /// 完整扫描：忽略指纹，重新读取所有仓库
+        #[arg(long)]
+        full: bool,
     },
     /// 扫描 config.toml 里记录的全部 roots
     ScanAll {
         /// 扫描深度限制（默认不限制）
         #[arg(long)]
         max_depth: Option<usize>,
-        /// 扫描完成后清理每个 root 下已删除/移动的仓库记录
+        /// 扫描完成后把每个 root 下已删除/移动的仓库移入回收站
         #[arg(long)]
         prune: bool,
+        /// 配合 --prune：只列出将被移入回收站的仓库，不做修改
+        #[arg(long, requires = "prune")]
+        dry_run: bool,
+        /// 完整扫描：忽略指纹，重新读取所有仓库
+        #[arg(long)]
+        full: bool,
+    },
+    /// 查看扫描记录及变更（新仓库、删除、HEAD 移动、新提交、分支/origin 变化）
+    Changes {
+        /// 起始时间：相对时间（7d、12h）、日期（2024-05-01）或 unix 时间戳；默认只看最近一次扫描
+        #[arg(long)]
+        since: Option<String>,
+        /// 最多显示的扫描次数
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
     },
     /// 列出已索引仓库
     List {
-        /// 按标签过滤
-        #[arg(long)]
-        tag: Option<String>,
+        /// 必须带有的标签（可重复，全部满足）
+        #[arg(long = "tag")]
+        tag: Vec<String>,
+        /// 至少带有其中一个的标签（可重复）
+        #[arg(long = "any")]
+        any: Vec<String>,
+        /// 不能带有的标签（可重复）
+        #[arg(long = "not")]
+        not: Vec<String>,
+        /// 自定义字段过滤：key=value，或只写 key 表示有该字段（可重复）
+        #[arg(long = "field")]
+        field: Vec<String>,
         /// 按最近访问排序
         #[arg(long)]
         recent: bool,
+        /// 同时列出已归档的仓库
+        #[arg(long)]
+        archived: bool,
     },
-    /// 关键字搜索（仓库名/路径/README 摘要/标签）
+    /// 全文搜索（仓库名/标签/README 摘要/笔记与字段/路径），按相关度排序；默认不含归档仓库
     Search {
+        /// 词按前缀匹配、顺序不限；"引号" 内为短语，-词 排除；
+        /// 过滤：tag: lang: root: origin: field: is:pinned|archived in:name,tag,readme,notes,path（前加 - 取反）
+        #[arg(allow_hyphen_values = true)]
         query: String,
+        /// 同时搜索已归档的仓库
+        #[arg(long)]
+        archived: bool,
+    },
+    /// 搜索提交索引（需先 `coderoom commit-index --all`），按相关度与时间排序；不带关键词时按时间列出
+    Commits {
+        /// 词按前缀匹配，"引号" 内为短语，-词 排除；
+        /// 过滤：author: email: after: before: branch: repo: is:merge|mine in:summary,message（前加 - 取反）
+        #[arg(allow_hyphen_values = true)]
+        query: Option<String>,
+        /// 只看自己的提交（config.toml 中的 my_identities），等同于 is:mine
+        #[arg(long)]
+        mine: bool,
+        /// 最多显示的提交数（上限 200）
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
     },
     /// 标签管理
     Tag {
         #[command(subcommand)]
         command: TagCommand,
     },
+    /// 仓库笔记（Markdown）
+    Note {
+        #[command(subcommand)]
+        command: NoteCommand,
+    },
+    /// 仓库自定义字段（owner、status、ticket、lifecycle 等）
+    Field {
+        #[command(subcommand)]
+        command: FieldCommand,
+    },
+    /// 置顶仓库（列表中始终排在最前）
+    Pin {
+        /// 仓库路径、别名或仓库名（精确匹配，不区分大小写）
+        repo: String,
+    },
+    /// 取消置顶
+    Unpin {
+        /// 仓库路径、别名或仓库名（精确匹配，不区分大小写）
+        repo: String,
+    },
+    /// 归档仓库（默认不在列表与搜索中显示，`list --archived` 可查看）
+    Archive {
+        /// 仓库路径、别名或仓库名（精确匹配，不区分大小写）
+        repo: String,
+    },
+    /// 取消归档
+    Unarchive {
+        /// 仓库路径、别名或仓库名（精确匹配，不区分大小写）
+        repo: String,
+    },
+    /// 隐藏仓库：以后的扫描跳过该路径（写入 config.toml），并把它移入回收站
+    Hide {
+        /// 仓库路径、别名或仓库名（精确匹配，不区分大小写）
+        repo: String,
+    },
+    /// 取消隐藏：恢复扫描该路径，并从回收站恢复
+    Unhide {
+        /// 隐藏的仓库路径、别名或目录名（精确匹配；不传则列出所有隐藏的路径）
+        path: Option<String>,
+    },
     /// 记录一次访问，并输出仓库路径（用于 shell/编辑器集成）
+    ///
+    /// 非绝对路径按别名精确匹配优先，其余按模糊匹配（name、别名、路径）× frecency（访问频率 × 最近程度）排序，取第一名
     Open {
-        /// 仓库路径（也可以传 name 的子串，但建议用路径更可靠）
-        repo: String,
+        /// 查询词，可以是多个词（每个词都要匹配）；或仓库绝对路径
+        #[arg(required_unless_present = "list")]
+        query: Vec<String>,
+        /// 只列出排名后的候选（分数、名称、路径），不记录访问
+        #[arg(long)]
+        list: bool,
+        /// --list 时最多列出多少个
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// 全屏终端界面：模糊过滤、标签筛选、仓库详情、按分支浏览提交
+    ///
+    /// 回车打开仓库：退出界面，记录访问并输出路径（可用 `cd "$(coderoom tui)"`）
+    Tui,
+    /// 仓库别名：`coderoom open <别名>` 直接打开对应仓库
+    Alias {
+        #[command(subcommand)]
+        command: AliasCommand,
+    },
+    /// 把路径已不存在的仓库移入回收站（可用 `coderoom trash restore` 恢复）
+    Prune {
+        /// 只列出将被移入回收站的仓库，不做修改
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 回收站：查看、恢复或彻底删除被清理的仓库
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommand,
     },
-    /// 清理数据库中已不存在的仓库路径
-    Prune,
     /// 管理扫描 Roots（写入 ~/.coderoom/config.toml）
     Roots {
         #[command(subcommand)]
@@ -75,6 +210,18 @@ enum Command {
         host: String,
         #[arg(long, default_value_t = 8787)]
         port: u16,
+        /// 同时监听 roots 的文件变化并实时更新索引（同 `coderoom watch`）
+        #[arg(long)]
+        watch: bool,
+    },
+    /// 监听 roots 的文件变化（新克隆/删除的仓库、分支与提交变化，含 worktree），增量更新索引；config.toml 修改后自动重新加载
+    Watch {
+        /// 最后一次变化后等待多久再更新（毫秒）
+        #[arg(long, default_value_t = watch::DEFAULT_DEBOUNCE_MS)]
+        debounce_ms: u64,
+        /// 不重建变化仓库的提交索引
+        #[arg(long)]
+        no_commit_index: bool,
     },
     /// 构建/重建提交索引（用于提交内容搜索）
     CommitIndex {
@@ -91,21 +238,55 @@ enum Command {
         #[arg(long)]
         commits_per_branch: Option<usize>,
     },
-    /// 管理扫描时需要忽略的目录名（写入 ~/.coderoom/config.toml）
+    /// 管理扫描时需要忽略的目录名与路径模式（写入 ~/.coderoom/config.toml）
     Ignores {
         #[command(subcommand)]
         command: IgnoresCommand,
     },
+    /// 输出 shell 集成脚本：补全（含仓库名/标签/roots 动态补全）与 cd 到仓库的函数
+    ///
+    /// 在 rc 文件中加入：`eval "$(coderoom shell-init bash)"`（zsh 同理，需在 compinit 之后），
+    /// fish 用 `coderoom shell-init fish | source`
+    ShellInit {
+        shell: shell::InitShell,
+        /// cd 函数的名字
+        #[arg(long, default_value = "cr")]
+        cmd: String,
+    },
+    /// 输出静态补全脚本（只含子命令与参数，不含动态补全）
+    Completions { shell: clap_complete::Shell },
+    /// 供补全脚本调用：输出光标处可用的仓库名/别名、标签或 roots
+    #[command(hide = true)]
+    Complete {
+        /// 光标之前的命令行（以程序名开头）
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        words: Vec<String>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
 enum IgnoresCommand {
     /// 列出忽略目录名
-    List,
-    /// 添加一个忽略目录名（例如：.cargo_home）
-    Add { name: String },
-    /// 移除一个忽略目录名
-    Remove { name: String },
+    List {
+        /// 列出路径模式（gitignore 风格）而不是目录名
+        #[arg(long)]
+        patterns: bool,
+    },
+    /// 添加一个忽略目录名（例如：.cargo_home），或用 --pattern 添加路径模式
+    Add {
+        #[arg(required_unless_present = "pattern", conflicts_with = "pattern")]
+        name: Option<String>,
+        /// gitignore 风格的模式（例如：~/dev/vendor/**、*-backup、!keep-backup）
+        #[arg(long)]
+        pattern: Option<String>,
+    },
+    /// 移除一个忽略目录名，或用 --pattern 移除路径模式
+    Remove {
+        #[arg(required_unless_present = "pattern", conflicts_with = "pattern")]
+        name: Option<String>,
+        #[arg(long)]
+        pattern: Option<String>,
+    },
     /// 重置为默认忽略目录名列表
     Reset,
 }
@@ -131,6 +312,122 @@ enum TagCommand {
         #[arg(long)]
         repo: Option<String>,
     },
+    /// 重命名标签；`team` 下的 `team/x` 一并改名，规则与 root 自动标签同步更新
+    Rename {
+        from: String,
+        to: String,
+    },
+    /// 把一个标签（及其子标签）合并进另一个标签后删除
+    Merge {
+        from: String,
+        into: String,
+    },
+    /// 从所有仓库上删除标签（及其子标签）
+    Delete { name: String },
+    /// 设置标签（或命名空间）的颜色与说明；传空字符串清除
+    Set {
+        name: String,
+        /// 颜色，如 #e11d48
+        #[arg(long)]
+        color: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// 规则标签（config.toml 中的 [[tag_rules]]）
+    Rules {
+        #[command(subcommand)]
+        command: TagRulesCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TagRulesCommand {
+    /// 预览每条规则匹配到的仓库，以及重新计算后会增删的标签（不写入）
+    Test {
+        /// 只看一个仓库（路径、别名或仓库名）
+        #[arg(long)]
+        repo: Option<String>,
+    },
+    /// 立即按当前规则重新计算所有仓库的规则标签（扫描时也会自动计算）
+    Apply,
+}
+
+#[derive(Subcommand, Debug)]
+enum NoteCommand {
+    /// 输出仓库笔记
+    Show {
+        /// 仓库路径、别名或仓库名（精确匹配，不区分大小写）
+        repo: String,
+    },
+    /// 写入仓库笔记（覆盖原内容）
+    Set {
+        /// 仓库路径、别名或仓库名（精确匹配，不区分大小写）
+        repo: String,
+        /// 笔记内容（Markdown）
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        text: Option<String>,
+        /// 从文件读取笔记内容（`-` 表示标准输入）
+        #[arg(long)]
+        file: Option<String>,
+    },
+    /// 删除仓库笔记
+    Clear {
+        /// 仓库路径、别名或仓库名（精确匹配，不区分大小写）
+        repo: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum FieldCommand {
+    /// 列出仓库的自定义字段
+    List {
+        /// 仓库路径、别名或仓库名（精确匹配，不区分大小写）
+        repo: String,
+    },
+    /// 设置字段；值不能含逗号，lifecycle 只能是 active、maintenance、deprecated
+    Set {
+        /// 仓库路径、别名或仓库名（精确匹配，不区分大小写）
+        repo: String,
+        key: String,
+        value: String,
+    },
+    /// 删除字段
+    Unset {
+        /// 仓库路径、别名或仓库名（精确匹配，不区分大小写）
+        repo: String,
+        key: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AliasCommand {
+    /// 列出所有别名
+    List,
+    /// 给仓库添加别名（一个单词，不区分大小写，全局唯一）
+    Add {
+        /// 仓库路径、别名或仓库名（精确匹配，不区分大小写）
+        repo: String,
+        alias: String,
+    },
+    /// 删除别名
+    Remove { alias: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum TrashCommand {
+    /// 列出回收站中的仓库
+    List,
+    /// 恢复一个仓库（路径或仓库名，精确匹配；多个同名时需给出路径），标签与访问记录保持不变
+    Restore { repo: String },
+    /// 彻底删除回收站中的仓库（连同标签关联与提交索引）
+    Purge {
+        /// 只删除这个仓库（路径）
+        #[arg(long, conflicts_with = "older_than")]
+        repo: Option<String>,
+        /// 只删除移入回收站早于该时间的仓库：相对时间（30d）、日期或 unix 时间戳
+        #[arg(long)]
+        older_than: Option<String>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -138,6 +435,34 @@ enum RootsCommand {
     List,
     Add { root: String },
     Remove { root: String },
+    /// 设置某个 root 的专属配置（未设置的项沿用全局配置）
+    Set {
+        root: String,
+        /// 显示名称（传空字符串清除）
+        #[arg(long)]
+        label: Option<String>,
+        /// 扫描深度限制
+        #[arg(long)]
+        max_depth: Option<usize>,
+        /// 额外忽略的目录名（可重复）
+        #[arg(long = "ignore")]
+        ignore: Vec<String>,
+        /// 额外的忽略模式（gitignore 风格，可重复）
+        #[arg(long = "ignore-pattern")]
+        ignore_pattern: Vec<String>,
+        /// 自动给该 root 下所有仓库打的标签（可重复）
+        #[arg(long = "auto-tag")]
+        auto_tag: Vec<String>,
+        /// 提交索引：最近提交的分支数
+        #[arg(long)]
+        commit_branches: Option<usize>,
+        /// 提交索引：每个分支索引的提交数
+        #[arg(long)]
+        commits_per_branch: Option<usize>,
+        /// 先清空该 root 的全部专属配置
+        #[arg(long)]
+        clear: bool,
+    },
 }
 
 #[tokio::main]
@@ -147,6 +472,7 @@ async fn main() -> Result<()> {
     let cfg_path = config::config_path(&data_dir);
     let db_path = config::db_path(&data_dir);
     config::ensure_data_dir(&data_dir)?;
+    let printer = output::Printer::new(cli.format, cli.fields);
 
     match cli.command {
         Command::Init => {
@@ -163,34 +489,49 @@ async fn main() -> Result<()> {
             root,
             max_depth,
             prune,
+            dry_run,
+            full,
         } => {
             let mut cfg = config::Config::load_or_create(&cfg_path)?;
             let db = db::Db::open(&db_path)?;
             db.init_schema()?;
-            let ignore_dir_names: std::collections::HashSet<String> =
-                cfg.ignore_dir_names.iter().cloned().collect();
 
             let root_input = root;
             let root_buf = std::path::PathBuf::from(&root_input);
             let root_path = std::fs::canonicalize(&root_buf).unwrap_or(root_buf);
-            let repos = scan::discover_git_repos(&root_path, max_depth, &ignore_dir_names)
-                .with_context(|| format!("scan root {}", root_path.display()))?;
-            let mut keep = std::collections::HashSet::<String>::new();
-            for repo_root in repos {
-                let meta = scan::read_repo_metadata(&repo_root)?;
-                keep.insert(meta.path.clone());
-                db.upsert_repo(&meta)?;
-            }
-            let pruned = if prune {
-                db.prune_under_root(&root_path.to_string_lossy(), &keep)?
-            } else {
-                0
-            };
+            let ignores = cfg.ignore_rules_for(&root_path);
+            let opts = cfg.scan_options_for(
+                &root_path,
+                &scan::ScanOptions {
+                    max_depth,
+                    prune,
+                    concurrency: cfg.scan_concurrency,
+                    full,
+                    dry_run,
+                    tag_rules: tag_rules::TagRules::default(),
+                },
+            )?;
+            let out = scan::scan_root(&db, &root_path, &opts, &ignores)?;
             cfg.add_root(&root_path);
             cfg.save(&cfg_path)?;
-            println!("Indexed {} repos. Pruned {}.", keep.len(), pruned);
+            println!(
+                "Indexed {} repos ({} unchanged). Pruned {}. {} changes (see `coderoom changes`).",
+                out.indexed,
+                out.skipped,
+                out.pruned,
+                out.changes.len()
+            );
+            if dry_run {
+                print_would_prune(&out.would_prune);
+            }
+            print_scan_errors(&out.errors);
         }
-        Command::ScanAll { max_depth, prune } => {
+        Command::ScanAll {
+            max_depth,
+            prune,
+            dry_run,
+            full,
+        } => {
             let cfg = config::Config::load_or_create(&cfg_path)?;
             if cfg.roots.is_empty() {
                 println!("No roots configured. Use `coderoom roots add <dir>` or `coderoom scan --root <dir>`.");
@@ -198,49 +539,198 @@ async fn main() -> Result<()> {
             }
             let db = db::Db::open(&db_path)?;
             db.init_schema()?;
-            let ignore_dir_names: std::collections::HashSet<String> =
-                cfg.ignore_dir_names.iter().cloned().collect();
+            let base = scan::ScanOptions {
+                max_depth,
+                prune,
+                concurrency: cfg.scan_concurrency,
+                full,
+                dry_run,
+                tag_rules: tag_rules::TagRules::default(),
+            };
             let mut indexed = 0usize;
+            let mut skipped = 0usize;
             let mut pruned = 0usize;
-            for root in cfg.roots {
-                let root_path = std::fs::canonicalize(std::path::PathBuf::from(&root))
-                    .unwrap_or_else(|_| std::path::PathBuf::from(&root));
-                let repos = scan::discover_git_repos(&root_path, max_depth, &ignore_dir_names)
-                    .with_context(|| format!("scan root {}", root_path.display()))?;
-                let mut keep = std::collections::HashSet::<String>::new();
-                for repo_root in repos {
-                    let meta = scan::read_repo_metadata(&repo_root)?;
-                    keep.insert(meta.path.clone());
-                    db.upsert_repo(&meta)?;
-                }
-                indexed += keep.len();
-                if prune {
-                    pruned += db.prune_under_root(&root_path.to_string_lossy(), &keep)?;
+            let mut errors = Vec::new();
+            let mut changes = 0usize;
+            let mut would_prune = Vec::new();
+            for root in &cfg.roots {
+                let root_path = std::fs::canonicalize(std::path::PathBuf::from(root))
+                    .unwrap_or_else(|_| std::path::PathBuf::from(root));
+                let opts = cfg.scan_options_for(&root_path, &base)?;
+                let ignores = cfg.ignore_rules_for(&root_path);
+                let out = scan::scan_root(&db, &root_path, &opts, &ignores)?;
+                indexed += out.indexed;
+                skipped += out.skipped;
+                pruned += out.pruned;
+                errors.extend(out.errors);
+                changes += out.changes.len();
+                would_prune.extend(out.would_prune);
+            }
+            println!(
+                "Indexed {indexed} repos ({skipped} unchanged). Pruned {pruned}. {changes} changes (see `coderoom changes`)."
+            );
+            if dry_run {
+                print_would_prune(&would_prune);
+            }
+            print_scan_errors(&errors);
+        }
+        Command::Changes { since, limit } => {
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            let now = chrono::Utc::now().timestamp();
+            let (since_ts, limit) = match since {
+                Some(s) => (dates::parse_time(&s, now)?, limit),
+                None => match db.last_scan_run_start()? {
+                    Some(ts) => (ts, 1),
+                    None => {
+                        if printer.is_table() {
+                            println!("No scans recorded yet.");
+                        } else {
+                            printer.print(&output::Records::new(&["run_id"], &["run_id"]))?;
+                        }
+                        return Ok(());
+                    }
+                },
+            };
+            let runs = db.list_scan_runs(since_ts, limit)?;
+            if runs.is_empty() && printer.is_table() {
+                println!("No scans since {}.", dates::format_ts(since_ts));
+                return Ok(());
+            }
+            let mut records = output::Records::new(
+                &[
+                    "run_id", "started_ts", "root", "duration_ms", "indexed", "skipped", "pruned",
+                    "errors", "kind", "name", "path", "old_value", "new_value", "detail", "change",
+                ],
+                &["started_ts", "kind", "name", "change", "path"],
+            );
+            for r in runs {
+                let run = [
+                    serde_json::json!(r.id),
+                    serde_json::json!(r.run.started_ts),
+                    serde_json::json!(r.run.root),
+                    serde_json::json!(r.run.duration_ms),
+                    serde_json::json!(r.run.indexed),
+                    serde_json::json!(r.run.skipped),
+                    serde_json::json!(r.run.pruned),
+                    serde_json::json!(r.run.errors),
+                ];
+                // A run without changes still shows up, with empty change fields.
+                if r.changes.is_empty() {
+                    let mut row = run.to_vec();
+                    row.extend(std::iter::repeat_n(serde_json::Value::Null, 7));
+                    records.push(row);
+                }
+                for c in r.changes {
+                    // Human summary: the detail, or `old -> new` with short commit ids.
+                    let change = match (c.kind.as_str(), &c.detail) {
+                        (_, Some(detail)) => detail.clone(),
+                        ("new" | "removed" | "restored", _) => String::new(),
+                        _ => format!(
+                            "{} -> {}",
+                            c.old_value.as_deref().map(short_value).unwrap_or("-"),
+                            c.new_value.as_deref().map(short_value).unwrap_or("-")
+                        ),
+                    };
+                    let mut row = run.to_vec();
+                    row.extend([
+                        serde_json::json!(c.kind),
+                        serde_json::json!(c.name),
+                        serde_json::json!(c.path),
+                        serde_json::json!(c.old_value),
+                        serde_json::json!(c.new_value),
+                        serde_json::json!(c.detail),
+                        serde_json::json!(change),
+                    ]);
+                    records.push(row);
                 }
             }
-            println!("Indexed {indexed} repos. Pruned {pruned}.");
+            printer.print(&records)?;
         }
-        Command::List { tag, recent } => {
+        Command::List {
+            tag,
+            any,
+            not,
+            field,
+            recent,
+            archived,
+        } => {
             let db = db::Db::open(&db_path)?;
             db.init_schema()?;
-            let repos = db.list_repos(tag.as_deref(), recent)?;
+            let filter = db::RepoFilter {
+                all: tag,
+                any,
+                not,
+                fields: field
+                    .iter()
+                    .map(|f| db::parse_field_filter(f))
+                    .collect::<Result<_>>()?,
+                include_archived: archived,
+                ..db::RepoFilter::default()
+            };
+            let repos = db.list_repos(&filter, recent)?;
+            let parents = repos
+                .iter()
+                .filter(|r| r.kind != "worktree")
+                .map(|r| r.path.clone())
+                .collect::<Vec<_>>();
+            let mut worktrees = db.worktrees_by_parent(&parents)?;
+            let nested = worktrees
+                .values()
+                .flatten()
+                .map(|w| w.path.clone())
+                .collect::<std::collections::HashSet<_>>();
+            let tags = db.tags_by_repo_id()?;
+            let mut records = output::Records::new(output::REPO_FIELDS, LIST_DEFAULT_FIELDS);
+            let mut push = |r: db::RepoRow, nested: bool| {
+                let mut row = output::repo_values(&r, tags.get(&r.id).map(Vec::as_slice).unwrap_or_default());
+                if printer.is_table() {
+                    row[2] = serde_json::json!(display_name(&r, nested));
+                }
+                records.push(row);
+            };
             for r in repos {
-                println!(
-                    "{}\t{}\t{}\t{}",
-                    r.last_access_ts.unwrap_or(0),
-                    r.name,
-                    r.default_branch.unwrap_or_else(|| "-".to_string()),
-                    r.path
-                );
+                if nested.contains(&r.path) {
+                    continue;
+                }
+                // Worktrees follow their main repo (indented in tables).
+                let children = worktrees.remove(&r.path).unwrap_or_default();
+                push(r, false);
+                for w in children {
+                    push(w, true);
+                }
             }
+            printer.print(&records)?;
         }
-        Command::Search { query } => {
+        Command::Search { query, archived } => {
             let db = db::Db::open(&db_path)?;
             db.init_schema()?;
-            let repos = db.search_repos(&query)?;
-            for r in repos {
-                println!("{}\t{}", r.name, r.path);
+            let tags = db.tags_by_repo_id()?;
+            let mut records = output::Records::new(output::REPO_FIELDS, LIST_DEFAULT_FIELDS);
+            let mut query = query::parse_repo(&query)?;
+            query.filter.include_archived = archived;
+            for r in db.search_repos(&query)? {
+                records.push(output::repo_values(&r, tags.get(&r.id).map(Vec::as_slice).unwrap_or_default()));
+            }
+            printer.print(&records)?;
+        }
+        Command::Commits { query, mine, limit } => {
+            let cfg = config::Config::load_or_create(&cfg_path)?;
+            let mut search = query::parse_commit(
+                query.as_deref().unwrap_or_default(),
+                chrono::Utc::now().timestamp(),
+                &cfg.my_identities,
+            )?;
+            if mine {
+                query::add_mine(&mut search.filter, &cfg.my_identities, false)?;
             }
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            let mut records = output::Records::new(output::COMMIT_FIELDS, COMMITS_DEFAULT_FIELDS);
+            for c in db.search_commits_paged(&search, 1, limit)?.items {
+                records.push(output::commit_values(&c));
+            }
+            printer.print(&records)?;
         }
         Command::Tag { command } => {
             let db = db::Db::open(&db_path)?;
@@ -255,36 +745,418 @@ async fn main() -> Result<()> {
                     println!("OK");
                 }
                 TagCommand::List { repo } => {
+                    let counts = db
+                        .list_tags_with_count()?
+                        .into_iter()
+                        .map(|t| (t.name.clone(), t))
+                        .collect::<std::collections::HashMap<_, _>>();
                     let tags = match repo {
                         Some(repo) => db.list_repo_tags(&repo)?,
                         None => db.list_tags()?,
                     };
-                    for t in tags {
-                        println!("{t}");
+                    let mut records = output::Records::new(
+                        &["name", "count", "total", "color", "description"],
+                        &["name", "count", "color", "description"],
+                    );
+                    for name in tags {
+                        let t = counts.get(&name);
+                        records.push(vec![
+                            serde_json::json!(name),
+                            serde_json::json!(t.map_or(0, |t| t.count)),
+                            serde_json::json!(t.map_or(0, |t| t.total)),
+                            serde_json::json!(t.and_then(|t| t.color.clone())),
+                            serde_json::json!(t.and_then(|t| t.description.clone())),
+                        ]);
                     }
+                    printer.print(&records)?;
+                }
+                TagCommand::Rename { from, to } => {
+                    let n = db.rename_tag(&from, &to)?;
+                    println!("Renamed {n} tags.");
+                    sync_tag_rename(&cfg_path, &from, &to)?;
+                }
+                TagCommand::Merge { from, into } => {
+                    let n = db.merge_tags(&from, &into)?;
+                    println!("Merged {n} tags into {into}.");
+                    sync_tag_rename(&cfg_path, &from, &into)?;
+                }
+                TagCommand::Delete { name } => {
+                    let n = db.delete_tag(&name)?;
+                    println!("Deleted {n} tags.");
+                    let cfg = config::Config::load_or_create(&cfg_path)?;
+                    if cfg.applies_tag(&db::normalize_tag(&name)?) {
+                        println!("Note: tag rules or root auto-tags still apply it; the next scan brings it back.");
+                    }
+                }
+                TagCommand::Set {
+                    name,
+                    color,
+                    description,
+                } => {
+                    if color.is_none() && description.is_none() {
+                        anyhow::bail!("nothing to set; pass --color and/or --description");
+                    }
+                    if let Some(color) = color {
+                        db.set_tag_color(&name, Some(&color))?;
+                    }
+                    if let Some(description) = description {
+                        db.set_tag_description(&name, Some(&description))?;
+                    }
+                    println!("OK");
+                }
+                TagCommand::Rules { command } => {
+                    let cfg = config::Config::load_or_create(&cfg_path)?;
+                    let rules = tag_rules::TagRules::compile(&cfg.tag_rules, Vec::new())?;
+                    let mut evaluated = tag_rules::evaluate_all(&db, &cfg)?;
+                    match command {
+                        TagRulesCommand::Test { repo } => {
+                            if let Some(repo) = repo {
+                                let path = db.find_repo_path(&repo)?;
+                                evaluated.retain(|(r, _)| r.path == path);
+                            }
+                            if rules.rules().next().is_none() {
+                                println!("No [[tag_rules]] in {}.", cfg_path.display());
+                            }
+                            for (i, rule) in rules.rules().enumerate() {
+                                let matched = evaluated
+                                    .iter()
+                                    .filter(|(r, _)| rules.matches(i, r))
+                                    .collect::<Vec<_>>();
+                                println!(
+                                    "#{} {}\t{}\t{} repos",
+                                    i + 1,
+                                    rule.tags.join(","),
+                                    describe_tag_rule(rule),
+                                    matched.len()
+                                );
+                                for (r, _) in matched {
+                                    println!("  {}\t{}", r.name, r.path);
+                                }
+                            }
+                            let current = db.rule_tags_by_repo()?;
+                            let mut diff = Vec::new();
+                            for (r, want) in &evaluated {
+                                let have = db.list_repo_tags(&r.path)?;
+                                let rule_tags = current.get(&r.path).cloned().unwrap_or_default();
+                                for t in want.iter().filter(|t| !have.contains(t)) {
+                                    diff.push(format!("+{t}\t{}", r.path));
+                                }
+                                for t in rule_tags.iter().filter(|t| !want.contains(t)) {
+                                    diff.push(format!("-{t}\t{}", r.path));
+                                }
+                            }
+                            if diff.is_empty() {
+                                println!("Rule tags are up to date.");
+                            } else {
+                                println!("Applying would change {} tags:", diff.len());
+                                for line in diff {
+                                    println!("  {line}");
+                                }
+                            }
+                        }
+                        TagRulesCommand::Apply => {
+                            let tags = evaluated
+                                .into_iter()
+                                .map(|(r, tags)| (r.path, tags))
+                                .collect::<Vec<_>>();
+                            db.replace_rule_tags(&tags)?;
+                            println!("Recomputed rule tags of {} repos.", tags.len());
+                        }
+                    }
+                }
+            }
+        }
+        Command::Note { command } => {
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            match command {
+                NoteCommand::Show { repo } => {
+                    let path = db.find_repo_path(&repo)?;
+                    if let Some(note) = db.repo_note(&path)? {
+                        println!("{}", note.trim_end());
+                    }
+                }
+                NoteCommand::Set { repo, text, file } => {
+                    let path = db.find_repo_path(&repo)?;
+                    let body = match (text, file) {
+                        (_, Some(file)) if file == "-" => {
+                            std::io::read_to_string(std::io::stdin()).context("read note from stdin")?
+                        }
+                        (_, Some(file)) => {
+                            std::fs::read_to_string(&file).with_context(|| format!("read {file}"))?
+                        }
+                        (Some(text), None) => text,
+                        (None, None) => String::new(),
+                    };
+                    db.set_repo_note(&path, &body)?;
+                    println!("OK");
+                }
+                NoteCommand::Clear { repo } => {
+                    let path = db.find_repo_path(&repo)?;
+                    db.set_repo_note(&path, "")?;
+                    println!("OK");
+                }
+            }
+        }
+        Command::Field { command } => {
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            match command {
+                FieldCommand::List { repo } => {
+                    let path = db.find_repo_path(&repo)?;
+                    let mut records = output::Records::new(&["key", "value"], &["key", "value"]);
+                    for (key, value) in db.list_repo_fields(&path)? {
+                        records.push(vec![serde_json::json!(key), serde_json::json!(value)]);
+                    }
+                    printer.print(&records)?;
+                }
+                FieldCommand::Set { repo, key, value } => {
+                    let path = db.find_repo_path(&repo)?;
+                    db.set_repo_field(&path, &key, &value)?;
+                    println!("OK");
+                }
+                FieldCommand::Unset { repo, key } => {
+                    let path = db.find_repo_path(&repo)?;
+                    db.set_repo_field(&path, &key, "")?;
+                    println!("OK");
                 }
             }
         }
-        Command::Open { repo } => {
+        Command::Pin { repo } => {
             let db = db::Db::open(&db_path)?;
             db.init_schema()?;
-            let path = db.resolve_repo_path(&repo)?.context("repo not found")?;
+            let path = db.find_repo_path(&repo)?;
+            db.set_repo_pinned(&path, true)?;
+            println!("Pinned {path}");
+        }
+        Command::Unpin { repo } => {
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            let path = db.find_repo_path(&repo)?;
+            db.set_repo_pinned(&path, false)?;
+            println!("Unpinned {path}");
+        }
+        Command::Archive { repo } => {
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            let path = db.find_repo_path(&repo)?;
+            db.set_repo_archived(&path, true)?;
+            println!("Archived {path}");
+        }
+        Command::Unarchive { repo } => {
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            let path = db.find_repo_path(&repo)?;
+            db.set_repo_archived(&path, false)?;
+            println!("Unarchived {path}");
+        }
+        Command::Hide { repo } => {
+            let mut cfg = config::Config::load_or_create(&cfg_path)?;
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            let path = db.find_repo_path(&repo)?;
+            cfg.add_excluded_repo(&path);
+            cfg.save(&cfg_path)?;
+            db.hide_repo(&path)?;
+            println!("Hidden {path} (moved to the trash; `coderoom unhide` brings it back).");
+        }
+        Command::Unhide { path } => {
+            let mut cfg = config::Config::load_or_create(&cfg_path)?;
+            let Some(path) = path else {
+                let mut records = output::Records::new(&["path"], &["path"]);
+                for p in &cfg.excluded_repos {
+                    records.push(vec![serde_json::json!(p)]);
+                }
+                printer.print(&records)?;
+                return Ok(());
+            };
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            let hidden = match db.alias_target(&path)? {
+                Some(target) if cfg.excluded_repos.contains(&target) => target,
+                _ => cfg.find_excluded_repo(&path)?,
+            };
+            cfg.remove_excluded_repo(&hidden);
+            cfg.save(&cfg_path)?;
+            if db.list_trash()?.iter().any(|r| r.path == hidden) {
+                db.restore_repo(&hidden)?;
+            }
+            println!("Unhidden {hidden}");
+        }
+        Command::Open { query, list, limit } => {
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            let query = query.join(" ");
+            if list {
+                let fields = &["score", "name", "aliases", "path"];
+                let mut records = output::Records::new(fields, fields);
+                for r in db.rank_repos(&query, limit)? {
+                    records.push(vec![
+                        serde_json::json!(r.score),
+                        serde_json::json!(r.name),
+                        serde_json::json!(r.aliases),
+                        serde_json::json!(r.path),
+                    ]);
+                }
+                printer.print(&records)?;
+                return Ok(());
+            }
+            let path = db.resolve_repo_path(&query)?.context("repo not found")?;
             db.record_access(&path)?;
             println!("{}", path);
         }
-        Command::Prune => {
+        Command::ShellInit { shell, cmd } => {
+            let mut completions = Vec::new();
+            clap_complete::generate(shell.generator(), &mut Cli::command(), "coderoom", &mut completions);
+            print!("{}", shell::init_script(shell, &cmd, &String::from_utf8(completions)?));
+        }
+        Command::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "coderoom", &mut std::io::stdout());
+        }
+        Command::Complete { words } => {
+            // Building propagates global options (`--format`) into every subcommand.
+            let mut cli = Cli::command();
+            cli.build();
+            let Some(kind) = shell::value_kind(&cli, &words) else {
+                return Ok(());
+            };
+            let open_db = || -> Result<db::Db> {
+                let db = db::Db::open(&db_path)?;
+                db.init_schema()?;
+                Ok(db)
+            };
+            let values = match kind {
+                shell::ValueKind::Roots => config::Config::load_or_create(&cfg_path)?.roots,
+                shell::ValueKind::Hidden => config::Config::load_or_create(&cfg_path)?.excluded_repos,
+                shell::ValueKind::Tags => open_db()?.list_tags()?,
+                shell::ValueKind::Aliases => {
+                    open_db()?.list_repo_aliases()?.into_iter().map(|(alias, _)| alias).collect()
+                }
+                shell::ValueKind::Repos => {
+                    // Most frecent first; shells that keep the order show those on top.
+                    let mut seen = std::collections::HashSet::new();
+                    open_db()?
+                        .rank_repos("", usize::MAX)?
+                        .into_iter()
+                        .flat_map(|r| std::iter::once(r.name).chain(r.aliases))
+                        .filter(|v| seen.insert(v.clone()))
+                        .collect()
+                }
+            };
+            for v in values {
+                println!("{v}");
+            }
+        }
+        Command::Tui => {
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            if let Some(path) = tui::run(&db)? {
+                db.record_access(&path)?;
+                println!("{path}");
+            }
+        }
+        Command::Alias { command } => {
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            match command {
+                AliasCommand::List => {
+                    let mut records = output::Records::new(&["alias", "path"], &["alias", "path"]);
+                    for (alias, path) in db.list_repo_aliases()? {
+                        records.push(vec![serde_json::json!(alias), serde_json::json!(path)]);
+                    }
+                    printer.print(&records)?;
+                }
+                AliasCommand::Add { repo, alias } => {
+                    let path = db.find_repo_path(&repo)?;
+                    let alias = db.add_repo_alias(&path, &alias)?;
+                    println!("{alias}\t{path}");
+                }
+                AliasCommand::Remove { alias } => {
+                    if !db.remove_repo_alias(&alias)? {
+                        anyhow::bail!("alias not found: {alias}");
+                    }
+                    println!("OK");
+                }
+            }
+        }
+        Command::Prune { dry_run } => {
             let db = db::Db::open(&db_path)?;
             db.init_schema()?;
-            let deleted = db.prune_missing_paths()?;
-            println!("Pruned {deleted} missing repos.");
+            let removed = db.prune_missing_paths(dry_run)?;
+            if dry_run {
+                print_would_prune(&removed);
+            } else {
+                println!(
+                    "Moved {} missing repos to the trash (see `coderoom trash list`).",
+                    removed.len()
+                );
+            }
+        }
+        Command::Trash { command } => {
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            match command {
+                TrashCommand::List => {
+                    let tags = db.tags_by_repo_id()?;
+                    let mut records = output::Records::new(
+                        output::REPO_FIELDS,
+                        &["removed_ts", "name", "removed_reason", "path"],
+                    );
+                    for r in db.list_trash()? {
+                        records.push(output::repo_values(&r, tags.get(&r.id).map(Vec::as_slice).unwrap_or_default()));
+                    }
+                    printer.print(&records)?;
+                }
+                TrashCommand::Restore { repo } => {
+                    let path = db.restore_repo(&repo)?.context("repo not found in trash")?;
+                    // A restored hidden repo is scanned again.
+                    let mut cfg = config::Config::load_or_create(&cfg_path)?;
+                    if cfg.remove_excluded_repo(&path) {
+                        cfg.save(&cfg_path)?;
+                    }
+                    println!("Restored {path}");
+                }
+                TrashCommand::Purge { repo, older_than } => {
+                    let now = chrono::Utc::now().timestamp();
+                    let before = older_than
+                        .map(|s| dates::parse_time(&s, now))
+                        .transpose()?;
+                    let n = db.purge_trash(repo.as_deref(), before)?;
+                    println!("Purged {n} repos.");
+                }
+            }
         }
         Command::Roots { command } => {
             let mut cfg = config::Config::load_or_create(&cfg_path)?;
             match command {
                 RootsCommand::List => {
-                    for r in cfg.roots {
-                        println!("{r}");
+                    let mut records = output::Records::new(
+                        &[
+                            "root",
+                            "label",
+                            "max_depth",
+                            "ignore_dir_names",
+                            "ignore_patterns",
+                            "auto_tags",
+                            "commit_index_branches",
+                            "commit_index_commits_per_branch",
+                        ],
+                        &["root", "label", "max_depth", "auto_tags", "ignore_dir_names", "ignore_patterns"],
+                    );
+                    for r in &cfg.roots {
+                        let s = cfg.root_settings.get(r).cloned().unwrap_or_default();
+                        records.push(vec![
+                            serde_json::json!(r),
+                            serde_json::json!(s.label),
+                            serde_json::json!(s.max_depth),
+                            serde_json::json!(s.ignore_dir_names),
+                            serde_json::json!(s.ignore_patterns),
+                            serde_json::json!(s.auto_tags),
+                            serde_json::json!(s.commit_index_branches),
+                            serde_json::json!(s.commit_index_commits_per_branch),
+                        ]);
                     }
+                    printer.print(&records)?;
                 }
                 RootsCommand::Add { root } => {
                     cfg.add_root(std::path::Path::new(&root));
@@ -296,22 +1168,88 @@ async fn main() -> Result<()> {
                     cfg.save(&cfg_path)?;
                     println!("OK");
                 }
+                RootsCommand::Set {
+                    root,
+                    label,
+                    max_depth,
+                    ignore,
+                    ignore_pattern,
+                    auto_tag,
+                    commit_branches,
+                    commits_per_branch,
+                    clear,
+                } => {
+                    let root = std::path::Path::new(&root);
+                    let mut settings = if clear {
+                        config::RootSettings::default()
+                    } else {
+                        cfg.root_settings(root)
+                    };
+                    if label.is_some() {
+                        settings.label = label;
+                    }
+                    if max_depth.is_some() {
+                        settings.max_depth = max_depth;
+                    }
+                    if !ignore.is_empty() {
+                        settings.ignore_dir_names = ignore;
+                    }
+                    if !ignore_pattern.is_empty() {
+                        settings.ignore_patterns = ignore_pattern;
+                    }
+                    if !auto_tag.is_empty() {
+                        settings.auto_tags = auto_tag;
+                    }
+                    if commit_branches.is_some() {
+                        settings.commit_index_branches = commit_branches;
+                    }
+                    if commits_per_branch.is_some() {
+                        settings.commit_index_commits_per_branch = commits_per_branch;
+                    }
+                    cfg.set_root_settings(root, settings)?;
+                    cfg.save(&cfg_path)?;
+                    println!("OK");
+                }
             }
         }
-        Command::Serve { host, port } => {
+        Command::Serve { host, port, watch } => {
             let _cfg = config::Config::load_or_create(&cfg_path)?;
             let db = db::Db::open(&db_path)?;
             db.init_schema()?;
+            if watch {
+                let (cfg_path, db_path) = (cfg_path.clone(), db_path.clone());
+                std::thread::spawn(move || {
+                    let opts = watch::WatchOptions {
+                        debounce: std::time::Duration::from_millis(watch::DEFAULT_DEBOUNCE_MS),
+                        commit_index: true,
+                    };
+                    let log = |line: &str| eprintln!("[watch] {line}");
+                    if let Err(e) = watch::run(&cfg_path, &db_path, &opts, &log) {
+                        eprintln!("[watch] stopped: {e:#}");
+                    }
+                });
+            }
             web::serve(
                 web::AppState {
                     cfg_path,
                     db_path,
+                    jobs: jobs::JobManager::new(),
                 },
                 host,
                 port,
             )
             .await?;
         }
+        Command::Watch {
+            debounce_ms,
+            no_commit_index,
+        } => {
+            let opts = watch::WatchOptions {
+                debounce: std::time::Duration::from_millis(debounce_ms),
+                commit_index: !no_commit_index,
+            };
+            watch::run(&cfg_path, &db_path, &opts, &|line| println!("{line}"))?;
+        }
         Command::CommitIndex {
             all,
             repo,
@@ -320,20 +1258,19 @@ async fn main() -> Result<()> {
         } => {
             let mut cfg = config::Config::load_or_create(&cfg_path)?;
             if let Some(v) = branches {
-                cfg.commit_index_branches = v.max(1).min(200);
+                cfg.commit_index_branches = v.clamp(1, 200);
             }
             if let Some(v) = commits_per_branch {
-                cfg.commit_index_commits_per_branch = v.max(1).min(500);
+                cfg.commit_index_commits_per_branch = v.clamp(1, 500);
             }
             cfg.save(&cfg_path)?;
 
             let db = db::Db::open(&db_path)?;
             db.init_schema()?;
 
-            let targets: Vec<String> = if all || repo.is_none() {
-                db.list_repo_paths()?
-            } else {
-                vec![repo.unwrap()]
+            let targets: Vec<String> = match repo {
+                Some(p) if !all => vec![p],
+                _ => db.list_repo_paths()?,
             };
 
             let mut repos_indexed = 0usize;
@@ -341,11 +1278,9 @@ async fn main() -> Result<()> {
                 if !std::path::Path::new(&p).exists() {
                     continue;
                 }
-                let (branches, commits) = commits::build_commit_index_for_repo(
-                    &p,
-                    cfg.commit_index_branches,
-                    cfg.commit_index_commits_per_branch,
-                )?;
+                let (branch_limit, commit_limit) = cfg.commit_index_limits(&p);
+                let (branches, commits) =
+                    commits::build_commit_index_for_repo(&p, branch_limit, commit_limit)?;
                 db.replace_commit_index_for_repo(&p, &branches, &commits)?;
                 repos_indexed += 1;
             }
@@ -357,19 +1292,39 @@ async fn main() -> Result<()> {
         Command::Ignores { command } => {
             let mut cfg = config::Config::load_or_create(&cfg_path)?;
             match command {
-                IgnoresCommand::List => {
-                    for n in cfg.ignore_dir_names {
-                        println!("{n}");
+                IgnoresCommand::List { patterns } => {
+                    let (items, field): (_, &'static [&'static str]) = if patterns {
+                        (cfg.ignore_patterns, &["pattern"])
+                    } else {
+                        (cfg.ignore_dir_names, &["name"])
+                    };
+                    let mut records = output::Records::new(field, field);
+                    for n in items {
+                        records.push(vec![serde_json::json!(n)]);
                     }
+                    printer.print(&records)?;
                 }
-                IgnoresCommand::Add { name } => {
-                    if cfg.add_ignore_dir_name(&name) {
+                IgnoresCommand::Add { name, pattern } => {
+                    let changed = match (name, pattern) {
+                        (_, Some(pattern)) => {
+                            scan::validate_ignore_pattern(&pattern)?;
+                            cfg.add_ignore_pattern(&pattern)
+                        }
+                        (Some(name), None) => cfg.add_ignore_dir_name(&name),
+                        (None, None) => false,
+                    };
+                    if changed {
                         cfg.save(&cfg_path)?;
                     }
                     println!("OK");
                 }
-                IgnoresCommand::Remove { name } => {
-                    if cfg.remove_ignore_dir_name(&name) {
+                IgnoresCommand::Remove { name, pattern } => {
+                    let changed = match (name, pattern) {
+                        (_, Some(pattern)) => cfg.remove_ignore_pattern(&pattern),
+                        (Some(name), None) => cfg.remove_ignore_dir_name(&name),
+                        (None, None) => false,
+                    };
+                    if changed {
                         cfg.save(&cfg_path)?;
                     }
                     println!("OK");
@@ -385,3 +1340,110 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Columns of `list` and `search` in tables and TSV.
+const LIST_DEFAULT_FIELDS: &[&str] = &["name", "default_branch", "tags", "last_access_ts", "path"];
+
+/// Columns of `commits` in tables and TSV.
+const COMMITS_DEFAULT_FIELDS: &[&str] = &["commit_ts", "repo_name", "branch_name", "author", "summary"];
+
+/// Table-only decorations of a repo name: pin marker, kind, archived flag and the
+/// indent of worktrees listed under their main repo.
+fn display_name(r: &db::RepoRow, nested: bool) -> String {
+    let mut name = match r.kind.as_str() {
+        "normal" => r.name.clone(),
+        _ if nested => r.name.clone(),
+        kind => format!("{} [{kind}]", r.name),
+    };
+    if nested {
+        name = format!("  └ {name}");
+    }
+    if r.pinned {
+        name = format!("* {name}");
+    }
+    if r.archived {
+        name.push_str(" [archived]");
+    }
+    name
+}
+
+/// Keeps `config.toml` tag rules and auto-tags in step with a renamed or merged tag.
+fn sync_tag_rename(cfg_path: &std::path::Path, from: &str, to: &str) -> Result<()> {
+    let mut cfg = config::Config::load_or_create(cfg_path)?;
+    if cfg.rename_tag(&db::normalize_tag(from)?, &db::normalize_tag(to)?) {
+        cfg.save(cfg_path)?;
+        println!("Updated tag rules in {}.", cfg_path.display());
+    }
+    Ok(())
+}
+
+/// One-line summary of a tag rule's conditions for `tag rules test`.
+fn describe_tag_rule(rule: &config::TagRule) -> String {
+    let mut parts = Vec::new();
+    for (key, value) in [
+        ("path_glob", &rule.path_glob),
+        ("root", &rule.root),
+        ("origin_host", &rule.origin_host),
+        ("origin_owner", &rule.origin_owner),
+        ("language", &rule.language),
+        ("default_branch", &rule.default_branch),
+    ] {
+        if let Some(v) = value {
+            parts.push(format!("{key}={v}"));
+        }
+    }
+    if !rule.has_files.is_empty() {
+        parts.push(format!("has_files={}", rule.has_files.join(",")));
+    }
+    parts.join(" ")
+}
+
+/// Dry-run report for `prune --dry-run` and `scan --prune --dry-run`.
+fn print_would_prune(removed: &[db::RepoChange]) {
+    println!("Would move {} repos to the trash:", removed.len());
+    for c in removed {
+        println!("  {}\t{}\t{}", c.name, c.detail.as_deref().unwrap_or("-"), c.path);
+    }
+}
+
+/// Scan summary for paths that could not be read; the full list stays in `scan_errors`.
+fn print_scan_errors(errors: &[db::ScanError]) {
+    const SHOWN: usize = 20;
+    if errors.is_empty() {
+        return;
+    }
+    println!("{} paths could not be read:", errors.len());
+    for e in errors.iter().take(SHOWN) {
+        println!("  [{}] {}: {}", e.kind, e.path, e.message);
+    }
+    if errors.len() > SHOWN {
+        println!("  ... and {} more (see the Web UI scan errors panel)", errors.len() - SHOWN);
+    }
+}
+
+/// Abbreviates commit ids in change output; other values are printed as-is.
+fn short_value(v: &str) -> &str {
+    if v.len() == 40 && v.chars().all(|c| c.is_ascii_hexdigit()) {
+        &v[..10]
+    } else {
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negated_query_terms_parse_as_values() {
+        let cli = Cli::try_parse_from(["coderoom", "search", "-tag:archived"]).expect("parse");
+        assert!(matches!(cli.command, Command::Search { query, archived: false } if query == "-tag:archived"));
+        let cli = Cli::try_parse_from(["coderoom", "search", "-tag:x api"]).expect("parse");
+        assert!(matches!(cli.command, Command::Search { query, .. } if query == "-tag:x api"));
+        let cli = Cli::try_parse_from(["coderoom", "commits", "-is:merge", "--mine", "--limit", "3"]).expect("parse");
+        assert!(matches!(
+            cli.command,
+            Command::Commits { query: Some(query), mine: true, limit: 3 } if query == "-is:merge"
+        ));
+    }
+}
@@ -0,0 +1,333 @@
+//! Listing output for the CLI in the format picked with the global `--format` and
+//! `--fields` options. Field names are stable and follow the DB structs (`RepoRow`,
+//! `RepoWithTags`, `TagCount`, ...), so scripts can rely on them.
+
+use crate::{dates, db};
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use std::io::{IsTerminal, Write};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Aligned columns with relative dates (default on a terminal).
+    Table,
+    /// Tab-separated values without a header (default when piped).
+    Tsv,
+    /// One JSON array.
+    Json,
+    /// One JSON object per line.
+    Jsonl,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+/// Widest a table cell gets before it is cut with `…`.
+const MAX_CELL_WIDTH: usize = 60;
+
+/// Rows of one listing. Every row has a value for each of `columns`; `default` is what
+/// `table`/`tsv`/`csv` show without `--fields` (JSON shows every column).
+pub struct Records {
+    columns: &'static [&'static str],
+    default: &'static [&'static str],
+    rows: Vec<Vec<Value>>,
+}
+
+impl Records {
+    pub fn new(columns: &'static [&'static str], default: &'static [&'static str]) -> Self {
+        Records { columns, default, rows: Vec::new() }
+    }
+
+    pub fn push(&mut self, row: Vec<Value>) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+}
+
+pub struct Printer {
+    format: Format,
+    fields: Vec<String>,
+}
+
+impl Printer {
+    pub fn new(format: Option<Format>, fields: Vec<String>) -> Self {
+        let format = format.unwrap_or(if std::io::stdout().is_terminal() {
+            Format::Table
+        } else {
+            Format::Tsv
+        });
+        Printer { format, fields }
+    }
+
+    /// Whether output is for people rather than scripts (free-form notes are fine).
+    pub fn is_table(&self) -> bool {
+        self.format == Format::Table
+    }
+
+    pub fn print(&self, records: &Records) -> Result<()> {
+        let mut out = std::io::stdout().lock();
+        self.write(records, &mut out, chrono::Utc::now().timestamp())?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn select(&self, records: &Records) -> Result<Vec<usize>> {
+        let names: Vec<&str> = if !self.fields.is_empty() {
+            self.fields.iter().map(|f| f.trim()).filter(|f| !f.is_empty()).collect()
+        } else if matches!(self.format, Format::Json | Format::Jsonl) {
+            records.columns.to_vec()
+        } else {
+            records.default.to_vec()
+        };
+        names
+            .iter()
+            .map(|name| match records.columns.iter().position(|c| c == name) {
+                Some(i) => Ok(i),
+                None => bail!(
+                    "unknown field '{name}' (available: {})",
+                    records.columns.join(", ")
+                ),
+            })
+            .collect()
+    }
+
+    fn write(&self, records: &Records, out: &mut impl Write, now: i64) -> Result<()> {
+        let cols = self.select(records)?;
+        let object = |row: &[Value]| -> Result<String> {
+            let parts = cols
+                .iter()
+                .map(|&i| Ok(format!("{}:{}", json!(records.columns[i]), serde_json::to_string(&row[i])?)))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(format!("{{{}}}", parts.join(",")))
+        };
+        match self.format {
+            Format::Json => {
+                if records.rows.is_empty() {
+                    writeln!(out, "[]")?;
+                    return Ok(());
+                }
+                writeln!(out, "[")?;
+                for (n, row) in records.rows.iter().enumerate() {
+                    let sep = if n + 1 < records.rows.len() { "," } else { "" };
+                    writeln!(out, "  {}{sep}", object(row)?)?;
+                }
+                writeln!(out, "]")?;
+            }
+            Format::Jsonl => {
+                for row in &records.rows {
+                    writeln!(out, "{}", object(row)?)?;
+                }
+            }
+            Format::Tsv => {
+                for row in &records.rows {
+                    let cells: Vec<String> = cols
+                        .iter()
+                        .map(|&i| plain(&row[i]).replace(['\t', '\n', '\r'], " "))
+                        .collect();
+                    writeln!(out, "{}", cells.join("\t"))?;
+                }
+            }
+            Format::Csv => {
+                let header: Vec<&str> = cols.iter().map(|&i| records.columns[i]).collect();
+                writeln!(out, "{}", header.join(","))?;
+                for row in &records.rows {
+                    let cells: Vec<String> = cols.iter().map(|&i| csv_cell(&plain(&row[i]))).collect();
+                    writeln!(out, "{}", cells.join(","))?;
+                }
+            }
+            Format::Table => {
+                let header: Vec<String> = cols.iter().map(|&i| records.columns[i].to_string()).collect();
+                let body: Vec<Vec<String>> = records
+                    .rows
+                    .iter()
+                    .map(|row| cols.iter().map(|&i| human(records.columns[i], &row[i], now)).collect())
+                    .collect();
+                let mut widths: Vec<usize> = header.iter().map(|h| h.width()).collect();
+                for row in &body {
+                    for (w, cell) in widths.iter_mut().zip(row) {
+                        *w = (*w).max(cell.width());
+                    }
+                }
+                for row in std::iter::once(&header).chain(&body) {
+                    let mut line = String::new();
+                    for (n, (cell, w)) in row.iter().zip(&widths).enumerate() {
+                        if n + 1 == row.len() {
+                            line.push_str(cell);
+                        } else {
+                            line.push_str(cell);
+                            line.push_str(&" ".repeat(w - cell.width() + 2));
+                        }
+                    }
+                    writeln!(out, "{}", line.trim_end())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Value for `tsv`/`csv`: nulls are empty, lists are comma-joined, timestamps stay numeric.
+fn plain(v: &Value) -> String {
+    match v {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(plain).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+fn csv_cell(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Value for `table`: relative dates for `*_ts` fields, `-` for nulls, one line, capped width.
+fn human(field: &str, v: &Value, now: i64) -> String {
+    let s = match v {
+        Value::Null => "-".to_string(),
+        Value::Number(n) if field.ends_with("_ts") => match n.as_i64() {
+            Some(ts) => dates::format_relative(ts, now),
+            None => n.to_string(),
+        },
+        Value::Number(n) if n.is_f64() => format!("{:.2}", n.as_f64().unwrap_or_default()),
+        Value::Bool(b) => if *b { "yes" } else { "no" }.to_string(),
+        Value::Array(items) if items.is_empty() => "-".to_string(),
+        Value::Array(items) => items.iter().map(plain).collect::<Vec<_>>().join(", "),
+        other => plain(other),
+    };
+    let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    truncate(&s, MAX_CELL_WIDTH)
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.width() <= max {
+        return s.to_string();
+    }
+    // Whole graphemes only, so an emoji or accented letter is never cut in half.
+    let mut out = String::new();
+    for g in s.graphemes(true) {
+        if out.width() + g.width() >= max {
+            break;
+        }
+        out.push_str(g);
+    }
+    out.push('…');
+    out
+}
+
+/// Every `RepoRow` field plus the repo's tags.
+pub const REPO_FIELDS: &[&str] = &[
+    "id",
+    "path",
+    "name",
+    "default_branch",
+    "last_commit_ts",
+    "last_scan_ts",
+    "readme_excerpt",
+    "origin_url",
+    "last_access_ts",
+    "kind",
+    "parent_path",
+    "removed_ts",
+    "removed_reason",
+    "language",
+    "pinned",
+    "archived",
+    "tags",
+];
+
+pub fn repo_values(r: &db::RepoRow, tags: &[String]) -> Vec<Value> {
+    vec![
+        json!(r.id),
+        json!(r.path),
+        json!(r.name),
+        json!(r.default_branch),
+        json!(r.last_commit_ts),
+        json!(r.last_scan_ts),
+        json!(r.readme_excerpt),
+        json!(r.origin_url),
+        json!(r.last_access_ts),
+        json!(r.kind),
+        json!(r.parent_path),
+        json!(r.removed_ts),
+        json!(r.removed_reason),
+        json!(r.language),
+        json!(r.pinned),
+        json!(r.archived),
+        json!(tags),
+    ]
+}
+
+/// Every `CommitHit` field except the highlights.
+pub const COMMIT_FIELDS: &[&str] = &[
+    "repo_name",
+    "repo_path",
+    "branch_kind",
+    "branch_name",
+    "refname",
+    "oid",
+    "commit_ts",
+    "author",
+    "email",
+    "summary",
+    "rank",
+];
+
+pub fn commit_values(c: &db::CommitHit) -> Vec<Value> {
+    vec![
+        json!(c.repo_name),
+        json!(c.repo_path),
+        json!(c.branch_kind),
+        json!(c.branch_name),
+        json!(c.refname),
+        json!(c.oid),
+        json!(c.time),
+        json!(c.author),
+        json!(c.email),
+        json!(c.summary),
+        json!(c.rank),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(format: Format, fields: &[&str], records: &Records) -> Result<String> {
+        let printer = Printer::new(Some(format), fields.iter().map(|f| f.to_string()).collect());
+        let mut out = Vec::new();
+        printer.write(records, &mut out, 1_700_000_000)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn formats_and_field_selection() -> Result<()> {
+        let mut records = Records::new(&["name", "last_access_ts", "tags", "pinned"], &["name", "last_access_ts"]);
+        records.push(vec![json!("api"), json!(1_700_000_000 - 3 * 86_400), json!(["go", "x,y"]), json!(true)]);
+        records.push(vec![json!("网站"), Value::Null, json!([]), json!(false)]);
+
+        assert_eq!(
+            render(Format::Table, &[], &records)?,
+            "name  last_access_ts\napi   3d ago\n网站  -\n"
+        );
+        assert_eq!(render(Format::Tsv, &["tags", "name"], &records)?, "go,x,y\tapi\n\t网站\n");
+        assert_eq!(
+            render(Format::Csv, &["name", "tags"], &records)?,
+            "name,tags\napi,\"go,x,y\"\n网站,\n"
+        );
+        let jsonl = render(Format::Jsonl, &[], &records)?;
+        assert_eq!(
+            jsonl.lines().next(),
+            Some(r#"{"name":"api","last_access_ts":1699740800,"tags":["go","x,y"],"pinned":true}"#)
+        );
+        let parsed: Value = serde_json::from_str(&render(Format::Json, &["name"], &records)?)?;
+        assert_eq!(parsed, json!([{"name": "api"}, {"name": "网站"}]));
+        assert!(render(Format::Tsv, &["nope"], &records).is_err());
+        Ok(())
+    }
+}
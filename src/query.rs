@@ -0,0 +1,355 @@
+//! The search query language shared by `coderoom search` and the Web UI. Free words and
+//! `"phrases"` go to the full-text index; `key:value` filters narrow the results; a
+//! leading `-` excludes either. Repo and commit searches know different keys:
+//!
+//! ```text
+//! tag:backend lang:rust root:~/work origin:github.com/acme "retry policy" -tag:archived
+//! author:alice after:2025-01-01 before:2025-06-01 branch:main repo:payments is:merge fix
+//! is:mine email:@acme.com after:30d
+//! ```
+
+use crate::{dates, db};
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::{Path, MAIN_SEPARATOR};
+
+/// Free text of a query.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Text {
+    /// Words (matched as prefixes) and phrases that must all match.
+    pub include: Vec<Term>,
+    /// Words and phrases that must not match.
+    pub exclude: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Word(String),
+    Phrase(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RepoQuery {
+    pub text: Text,
+    /// `repos_fts` columns picked with `in:`; empty means all.
+    pub columns: Vec<&'static str>,
+    pub filter: db::RepoFilter,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CommitQuery {
+    pub text: Text,
+    /// `commits_fts` columns picked with `in:`; empty means all.
+    pub columns: Vec<&'static str>,
+    pub filter: db::CommitFilter,
+}
+
+pub const REPO_KEYS: &[&str] = &["tag", "lang", "root", "origin", "field", "is", "in"];
+pub const COMMIT_KEYS: &[&str] = &["author", "email", "after", "before", "branch", "repo", "is", "in"];
+
+/// Searchable repo fields as `in:` takes them, with their `repos_fts` column.
+const REPO_COLUMNS: &[(&str, &str)] = &[
+    ("name", "name"),
+    ("tag", "tags"),
+    ("tags", "tags"),
+    ("readme", "readme"),
+    ("notes", "notes"),
+    ("path", "path"),
+];
+const COMMIT_COLUMNS: &[(&str, &str)] = &[("summary", "summary"), ("message", "message")];
+
+enum Token {
+    Term { negate: bool, term: Term },
+    Filter { negate: bool, key: String, value: String },
+}
+
+/// Splits a query into terms and `key:value` filters. A word only counts as a filter
+/// when the part before the colon is a plain lowercase key and it is not a URL, so
+/// `http://x` and `C++:` stay words.
+fn lex(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+    while !rest.is_empty() {
+        let negate = rest.len() > 1 && rest.starts_with('-') && !rest[1..].starts_with(char::is_whitespace);
+        if negate {
+            rest = &rest[1..];
+        }
+        if let Some(after) = rest.strip_prefix('"') {
+            let end = after.find('"').context("unclosed quote in search query")?;
+            tokens.push(Token::Term {
+                negate,
+                term: Term::Phrase(after[..end].to_string()),
+            });
+            rest = after[end + 1..].trim_start();
+            continue;
+        }
+        let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let key = rest[..word_end]
+            .split_once(':')
+            .map(|(k, _)| k)
+            .filter(|k| !k.is_empty() && k.chars().all(|c| c.is_ascii_lowercase()))
+            .filter(|k| !rest[k.len() + 1..].starts_with("//"));
+        match key {
+            Some(key) => {
+                let after = &rest[key.len() + 1..];
+                let (value, tail) = match after.strip_prefix('"') {
+                    Some(quoted) => {
+                        let end = quoted
+                            .find('"')
+                            .with_context(|| format!("unclosed quote after {key}:"))?;
+                        (&quoted[..end], &quoted[end + 1..])
+                    }
+                    None => {
+                        let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    }
+                };
+                if value.trim().is_empty() {
+                    bail!("missing value after {key}:");
+                }
+                tokens.push(Token::Filter {
+                    negate,
+                    key: key.to_string(),
+                    value: value.trim().to_string(),
+                });
+                rest = tail.trim_start();
+            }
+            None => {
+                tokens.push(Token::Term {
+                    negate,
+                    term: Term::Word(rest[..word_end].to_string()),
+                });
+                rest = rest[word_end..].trim_start();
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parses tokens, handing each filter to `filter` and collecting the free text.
+fn parse(
+    input: &str,
+    keys: &[&str],
+    mut filter: impl FnMut(&str, &str, bool) -> Result<()>,
+) -> Result<Text> {
+    let mut text = Text::default();
+    for token in lex(input)? {
+        match token {
+            Token::Term { negate: false, term } => text.include.push(term),
+            Token::Term { negate: true, term } => text.exclude.push(term),
+            Token::Filter { negate, key, value } => {
+                if !keys.contains(&key.as_str()) {
+                    bail!("unknown filter {key}: (use one of {})", keys.join(", "));
+                }
+                // One flat message, since API errors only show the outermost context.
+                filter(&key, &value, negate).map_err(|e| anyhow!("in {key}:{value}: {e:#}"))?;
+            }
+        }
+    }
+    if text.include.is_empty() && !text.exclude.is_empty() {
+        bail!("excluding words needs at least one word to search for");
+    }
+    Ok(text)
+}
+
+fn no_negation(key: &str, negate: bool) -> Result<()> {
+    if negate {
+        bail!("{key}: cannot be negated");
+    }
+    Ok(())
+}
+
+fn add(values: &mut db::AnyOf, value: &str, negate: bool) {
+    let list = if negate { &mut values.not } else { &mut values.any };
+    list.push(value.to_string());
+}
+
+fn columns(value: &str, known: &[(&str, &'static str)], out: &mut Vec<&'static str>) -> Result<()> {
+    for name in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let Some((_, column)) = known.iter().find(|(n, _)| *n == name) else {
+            let names: Vec<&str> = known.iter().map(|(n, _)| *n).collect();
+            bail!("unknown field {name} (use {})", names.join(", "));
+        };
+        if !out.contains(column) {
+            out.push(column);
+        }
+    }
+    Ok(())
+}
+
+/// Parses a repo search. Repeated `lang:`, `root:` and `origin:` match any of their
+/// values; every `tag:` is required.
+pub fn parse_repo(input: &str) -> Result<RepoQuery> {
+    let mut q = RepoQuery::default();
+    let mut cols = Vec::new();
+    let text = parse(input, REPO_KEYS, |key, value, negate| {
+        match key {
+            "tag" => {
+                let tag = db::normalize_tag(value)?;
+                if negate {
+                    q.filter.not.push(tag);
+                } else {
+                    q.filter.all.push(tag);
+                }
+            }
+            "lang" => add(&mut q.filter.languages, value, negate),
+            "root" => add(&mut q.filter.roots, &expand_root(value)?, negate),
+            "origin" => add(&mut q.filter.origins, value, negate),
+            "field" => {
+                no_negation(key, negate)?;
+                q.filter.fields.push(db::parse_field_filter(value)?);
+            }
+            "is" => match value {
+                "pinned" => q.filter.pinned = Some(!negate),
+                "archived" => q.filter.archived = Some(!negate),
+                _ => bail!("unknown value (use pinned or archived)"),
+            },
+            "in" => {
+                no_negation(key, negate)?;
+                columns(value, REPO_COLUMNS, &mut cols)?;
+            }
+            _ => unreachable!("key checked by parse"),
+        }
+        Ok(())
+    })?;
+    q.text = text;
+    q.columns = cols;
+    Ok(q)
+}
+
+/// Parses a commit search. Repeated `author:`, `email:`, `branch:` and `repo:` match any
+/// of their values. `after:`/`before:` take what `--since` does (`2025-01-01`, `7d`, ...);
+/// a bare `before:` date includes that whole day.
+/// `is:mine` matches commits by any of `me` (`my_identities` in config.toml).
+pub fn parse_commit(input: &str, now: i64, me: &[String]) -> Result<CommitQuery> {
+    let mut q = CommitQuery::default();
+    let mut cols = Vec::new();
+    let text = parse(input, COMMIT_KEYS, |key, value, negate| {
+        match key {
+            "author" => add(&mut q.filter.authors, value, negate),
+            "email" => add(&mut q.filter.emails, value, negate),
+            "branch" => add(&mut q.filter.branches, value, negate),
+            "repo" => add(&mut q.filter.repos, value, negate),
+            "after" => {
+                no_negation(key, negate)?;
+                q.filter.after = Some(dates::parse_time(value, now)?);
+            }
+            "before" => {
+                no_negation(key, negate)?;
+                q.filter.before = Some(dates::parse_time_end(value, now)?);
+            }
+            "is" => match value {
+                "merge" => q.filter.merge = Some(!negate),
+                "mine" => add_mine(&mut q.filter, me, negate)?,
+                _ => bail!("unknown value (use merge or mine)"),
+            },
+            "in" => {
+                no_negation(key, negate)?;
+                columns(value, COMMIT_COLUMNS, &mut cols)?;
+            }
+            _ => unreachable!("key checked by parse"),
+        }
+        Ok(())
+    })?;
+    q.text = text;
+    q.columns = cols;
+    Ok(q)
+}
+
+/// Limits commits to those by `me` (or, negated, to everyone else's).
+pub fn add_mine(filter: &mut db::CommitFilter, me: &[String], negate: bool) -> Result<()> {
+    let me: Vec<&str> = me.iter().map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
+    if me.is_empty() {
+        bail!("no identities configured; add my_identities = [\"you@example.com\", \"Your Name\"] to config.toml");
+    }
+    for identity in me {
+        add(&mut filter.identities, identity, negate);
+    }
+    Ok(())
+}
+
+/// Columns of the scope checkboxes that are on; none or all on means every column.
+pub fn scope_columns(scopes: &[(&'static str, bool)]) -> Vec<&'static str> {
+    if scopes.iter().all(|(_, on)| *on) {
+        return Vec::new();
+    }
+    scopes.iter().filter(|(_, on)| *on).map(|(c, _)| *c).collect()
+}
+
+/// Absolute form of a `root:` value without trailing separators (a filesystem root such
+/// as `/` or `C:\` stays as is).
+fn expand_root(value: &str) -> Result<String> {
+    let is_sep = |c: char| c == '/' || c == MAIN_SEPARATOR;
+    let path = match value.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(is_sep) => {
+            let home = dirs::home_dir().context("cannot resolve home dir")?;
+            format!("{}{rest}", home.to_string_lossy())
+        }
+        _ => value.to_string(),
+    };
+    if !Path::new(&path).is_absolute() {
+        bail!("needs an absolute path or one starting with ~/");
+    }
+    let trimmed = path.trim_end_matches(is_sep);
+    Ok(if Path::new(trimmed).is_absolute() { trimmed } else { path.as_str() }.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_filters_terms_and_errors() -> Result<()> {
+        let q = parse_repo(r#"tag:backend lang:rust root:/work/ origin:github.com/acme "retry policy" -tag:archived api"#)?;
+        assert_eq!(
+            q.text.include,
+            vec![Term::Phrase("retry policy".into()), Term::Word("api".into())]
+        );
+        assert_eq!(q.filter.all, vec!["backend"]);
+        assert_eq!(q.filter.not, vec!["archived"]);
+        assert_eq!(q.filter.languages.any, vec!["rust"]);
+        assert_eq!(q.filter.roots.any, vec!["/work"]);
+        assert_eq!(parse_repo("root:/")?.filter.roots.any, vec!["/"]);
+        assert_eq!(q.filter.origins.any, vec!["github.com/acme"]);
+        let q = parse_repo("http://x C++: in:name,tag -is:pinned field:owner=alice")?;
+        assert_eq!(q.text.include, vec![Term::Word("http://x".into()), Term::Word("C++:".into())]);
+        assert_eq!(q.columns, vec!["name", "tags"]);
+        assert_eq!(q.filter.pinned, Some(false));
+
+        let now = 1_700_000_000;
+        let me = vec!["alice@example.com".to_string(), " Alice ".to_string()];
+        let q = parse_commit(
+            r#"author:alice after:2023-01-01 before:7d branch:main repo:"pay ments" is:merge -author:bot fix -wip"#,
+            now,
+            &me,
+        )?;
+        assert_eq!(q.text.include, vec![Term::Word("fix".into())]);
+        assert_eq!(q.text.exclude, vec![Term::Word("wip".into())]);
+        assert_eq!(q.filter.authors.any, vec!["alice"]);
+        assert_eq!(q.filter.authors.not, vec!["bot"]);
+        assert_eq!(q.filter.before, Some(now - 7 * 86_400));
+        assert!(q.filter.after.is_some());
+        assert_eq!(q.filter.repos.any, vec!["pay ments"]);
+        assert_eq!(q.filter.merge, Some(true));
+        let q = parse_commit("is:mine email:@acme.com", now, &me)?;
+        assert_eq!(q.filter.identities.any, vec!["alice@example.com", "Alice"]);
+        assert_eq!(q.filter.emails.any, vec!["@acme.com"]);
+        assert!(q.text.include.is_empty());
+
+        let err = |r: Result<RepoQuery>| r.err().map(|e| format!("{e:#}")).unwrap_or_default();
+        assert!(err(parse_repo("author:alice")).starts_with("unknown filter author:"));
+        assert_eq!(err(parse_repo("tag:")), "missing value after tag:");
+        assert_eq!(err(parse_repo("\"open")), "unclosed quote in search query");
+        assert_eq!(err(parse_repo("-api")), "excluding words needs at least one word to search for");
+        assert_eq!(err(parse_repo("is:old")), "in is:old: unknown value (use pinned or archived)");
+        assert_eq!(err(parse_repo("root:work")), "in root:work: needs an absolute path or one starting with ~/");
+        assert!(parse_commit("after:someday", now, &me).is_err());
+        // A date-only `before:` (the UI's "To" day) keeps commits from that day.
+        let q = parse_commit("before:2023-01-01", now, &me)?;
+        assert_eq!(q.filter.before, Some(dates::parse_time("2023-01-02", now)?));
+        let err = parse_commit("is:mine", now, &[]).err().map(|e| format!("{e:#}")).unwrap_or_default();
+        assert!(err.starts_with("in is:mine: no identities configured"));
+        assert_eq!(scope_columns(&[("name", true), ("path", false)]), vec!["name"]);
+        assert!(scope_columns(&[("name", true), ("path", true)]).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+//! Fuzzy matching and frecency scoring used to resolve a typed query (`coderoom open api`)
+//! to the repo the user most likely means.
+
+/// How well `query` matches `candidate`, in `(0, 1]`, or `None` when it does not match.
+/// Case-insensitive. Exact > prefix > substring (bonus at a word boundary) > in-order
+/// subsequence, where a subsequence scores higher the more compact it is.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<f64> {
+    let q: Vec<char> = query.to_lowercase().chars().collect();
+    let c: Vec<char> = candidate.to_lowercase().chars().collect();
+    if q.is_empty() || q.len() > c.len() {
+        return None;
+    }
+    if q == c {
+        return Some(1.0);
+    }
+    if c.starts_with(&q) {
+        return Some(0.9);
+    }
+    if let Some(start) = c.windows(q.len()).position(|w| w == q.as_slice()) {
+        return Some(if at_boundary(&c, start) { 0.85 } else { 0.75 });
+    }
+
+    // Shortest window containing the whole query in order: try every start position of
+    // the first query char and match the rest greedily.
+    let mut best: Option<(usize, usize)> = None;
+    for start in (0..c.len()).filter(|&i| c[i] == q[0]) {
+        let mut qi = 1;
+        let mut end = start;
+        for (i, &ch) in c.iter().enumerate().skip(start + 1) {
+            if qi == q.len() {
+                break;
+            }
+            if ch == q[qi] {
+                qi += 1;
+                end = i;
+            }
+        }
+        if qi < q.len() {
+            break;
+        }
+        let span = end - start + 1;
+        if best.is_none_or(|(_, s)| span < s) {
+            best = Some((start, span));
+        }
+    }
+    let (start, span) = best?;
+    let compact = q.len() as f64 / span as f64;
+    let bonus = if at_boundary(&c, start) { 0.1 } else { 0.0 };
+    Some(0.2 + 0.4 * compact + bonus)
+}
+
+fn at_boundary(c: &[char], i: usize) -> bool {
+    i == 0 || !c[i - 1].is_alphanumeric()
+}
+
+/// Recency weight of the last access, zoxide style.
+fn recency_factor(age_secs: i64) -> f64 {
+    match age_secs {
+        a if a < 3600 => 4.0,
+        a if a < 86_400 => 2.0,
+        a if a < 7 * 86_400 => 0.5,
+        _ => 0.25,
+    }
+}
+
+/// Frecency of a repo opened `count` times, last at `last_ts`: frequency × recency decay.
+/// Zero for repos that were never opened.
+pub fn frecency(count: i64, last_ts: Option<i64>, now: i64) -> f64 {
+    match last_ts {
+        Some(ts) if count > 0 => count as f64 * recency_factor(now - ts),
+        _ => 0.0,
+    }
+}
+
+/// Combines match quality and frecency into the final rank. A never-opened exact match
+/// still scores above zero, but a repo used all day outranks it on a looser match.
+pub fn rank(match_score: f64, frecency: f64) -> f64 {
+    match_score * (1.0 + frecency)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_and_frecency_ordering() {
+        let exact = fuzzy_score("api", "api").unwrap();
+        let prefix = fuzzy_score("api", "api-gateway").unwrap();
+        let word = fuzzy_score("api", "payments-api").unwrap();
+        let inner = fuzzy_score("api", "rapid").unwrap();
+        let subseq = fuzzy_score("pgw", "payments-gateway").unwrap();
+        assert!(exact > prefix && prefix > word && word > inner && inner > subseq);
+        assert!(fuzzy_score("API", "my-api").is_some());
+        assert!(fuzzy_score("xyz", "payments-gateway").is_none());
+        assert!(fuzzy_score("wg", "gw").is_none());
+        // A tighter subsequence beats a scattered one.
+        assert!(fuzzy_score("cr", "coderoom").unwrap() < fuzzy_score("cr", "crate").unwrap());
+
+        let now = 1_700_000_000;
+        assert_eq!(frecency(0, None, now), 0.0);
+        assert!(frecency(3, Some(now - 60), now) > frecency(3, Some(now - 2 * 86_400), now));
+        assert!(frecency(10, Some(now - 30 * 86_400), now) > frecency(1, Some(now - 30 * 86_400), now));
+        assert!(rank(word, frecency(20, Some(now), now)) > rank(exact, 0.0));
+    }
+}
@@ -1,18 +1,500 @@
-use crate::db::RepoMeta;
+use crate::db::{Db, RepoChange, RepoMeta, ScanError, ScanRun};
+use crate::tag_rules::{self, TagRules};
+use crate::text;
 use anyhow::{Context, Result};
 use chrono::Utc;
 use git2::Repository;
-use std::collections::HashSet;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use walkdir::WalkDir;
 
+/// Bounded queue length between discovery and the metadata readers.
+const QUEUE_CAPACITY: usize = 256;
+/// Number of repos written per `upsert_repos` transaction.
+const UPSERT_BATCH: usize = 200;
+/// Bump when `read_repo_metadata` starts storing something new, so the next scan
+/// re-reads every repo instead of skipping it as unchanged.
+const FINGERPRINT_VERSION: u32 = 4;
+
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    pub max_depth: Option<usize>,
+    pub prune: bool,
+    pub concurrency: usize,
+    /// Re-read every repo even when its fingerprint is unchanged.
+    pub full: bool,
+    /// With `prune`, only report what would go to the trash.
+    pub dry_run: bool,
+    /// Tag rules and root auto-tags, recomputed for every repo found.
+    pub tag_rules: TagRules,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScanOutcome {
+    pub indexed: usize,
+    pub skipped: usize,
+    pub pruned: usize,
+    /// Paths that could not be read; also stored in the `scan_errors` table.
+    pub errors: Vec<ScanError>,
+    /// What changed compared to the previous scan; also stored with the run in `scan_runs`.
+    pub changes: Vec<RepoChange>,
+    /// Repos a dry-run prune would have moved to the trash.
+    pub would_prune: Vec<RepoChange>,
+}
+
+/// Exclusion rules from `config.toml`: exact directory names plus gitignore-style patterns.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    pub dir_names: HashSet<String>,
+    pub patterns: Vec<String>,
+    /// Repos hidden by path (`excluded_repos`). Only the repo itself is skipped; worktrees
+    /// and submodules inside it are still found.
+    pub excluded_paths: HashSet<PathBuf>,
+}
+
+impl IgnoreRules {
+    pub fn new(dir_names: &[String], patterns: &[String]) -> Self {
+        Self {
+            dir_names: dir_names.iter().cloned().collect(),
+            patterns: patterns.to_vec(),
+            excluded_paths: HashSet::new(),
+        }
+    }
+}
+
+/// `IgnoreRules` compiled against one scan root.
+pub struct ScanFilter {
+    dir_names: HashSet<String>,
+    patterns: Gitignore,
+    excluded_paths: HashSet<PathBuf>,
+}
+
+impl ScanFilter {
+    /// Patterns follow gitignore semantics relative to `root`. `~/` patterns and absolute
+    /// paths under `root` are anchored to their location; a `~/` pattern outside `root` is
+    /// dropped, while any other leading `/` anchors the pattern to `root` as in gitignore.
+    pub fn new(root: &Path, rules: &IgnoreRules) -> Result<Self> {
+        let mut builder = GitignoreBuilder::new(root);
+        for raw in &rules.patterns {
+            if let Some(line) = pattern_for_root(root, raw) {
+                builder
+                    .add_line(None, &line)
+                    .with_context(|| format!("invalid ignore pattern {raw}"))?;
+            }
+        }
+        Ok(Self {
+            dir_names: rules.dir_names.clone(),
+            patterns: builder.build()?,
+            excluded_paths: rules.excluded_paths.clone(),
+        })
+    }
+
+    fn is_ignored(&self, path: &Path, name: &str) -> bool {
+        self.dir_names.contains(name) || self.patterns.matched(path, true).is_ignore()
+    }
+
+    fn is_hidden_repo(&self, repo_root: &Path) -> bool {
+        self.excluded_paths.contains(repo_root)
+    }
+
+    /// Whether `path` is a hidden repo, or it or any directory between `root` and it is
+    /// ignored. Paths outside `root` are always excluded.
+    pub fn excludes(&self, root: &Path, path: &Path) -> bool {
+        let Ok(rel) = path.strip_prefix(root) else {
+            return true;
+        };
+        if self.is_hidden_repo(path) {
+            return true;
+        }
+        let mut cur = root.to_path_buf();
+        for component in rel.components() {
+            cur.push(component);
+            if self.is_ignored(&cur, &component.as_os_str().to_string_lossy()) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Checks that `pattern` parses as a gitignore line.
+pub fn validate_ignore_pattern(pattern: &str) -> Result<()> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() || pattern.starts_with('#') {
+        anyhow::bail!("empty ignore pattern");
+    }
+    let root = Path::new("/");
+    let line = pattern_for_root(root, pattern).unwrap_or_else(|| pattern.to_string());
+    GitignoreBuilder::new(root)
+        .add_line(None, &line)
+        .with_context(|| format!("invalid ignore pattern {pattern}"))?;
+    Ok(())
+}
+
+fn pattern_for_root(root: &Path, raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if raw.is_empty() || raw.starts_with('#') {
+        return None;
+    }
+    let (neg, body) = match raw.strip_prefix('!') {
+        Some(rest) => ("!", rest),
+        None => ("", raw),
+    };
+    let expanded = match body.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()?.join(rest).to_string_lossy().to_string(),
+        None => body.to_string(),
+    };
+    let root_str = root.to_string_lossy();
+    let root_prefix = root_str.trim_end_matches('/');
+    if body.starts_with("~/") || (expanded.starts_with('/') && !root_prefix.is_empty()) {
+        match expanded.strip_prefix(root_prefix) {
+            Some(rest) if rest.starts_with('/') => return Some(format!("{neg}{rest}")),
+            // Not an absolute path under this root: only `~/` patterns are known to be
+            // absolute, a bare `/x` stays anchored to the root as in gitignore.
+            _ if body.starts_with("~/") => return None,
+            _ => {}
+        }
+    }
+    Some(format!("{neg}{expanded}"))
+}
+
+/// What a metadata reader produced for one discovered repo.
+enum RepoResult {
+    Read(Box<RepoMeta>),
+    Unchanged(String),
+    Failed(String),
+}
+
+fn scan_error(path: &Path, kind: &str, message: impl std::fmt::Display) -> ScanError {
+    ScanError {
+        path: path.to_string_lossy().to_string(),
+        kind: kind.to_string(),
+        message: message.to_string(),
+    }
+}
+
+pub const README_CANDIDATES: [&str; 4] = ["README.md", "Readme.md", "README.MD", "README"];
+
+/// Progress callback: (discovered, processed, current path).
+pub type ProgressFn<'a> = dyn Fn(usize, usize, &str) + Sync + 'a;
+
+/// Progress reporting and cancellation for a running scan (used by Web jobs).
+#[derive(Default)]
+pub struct ScanHooks<'a> {
+    /// Checked between repos; a cancelled scan keeps what it wrote and skips pruning.
+    pub cancel: Option<&'a AtomicBool>,
+    /// Called after each repo is processed.
+    pub on_progress: Option<&'a ProgressFn<'a>>,
+}
+
+impl ScanHooks<'_> {
+    fn cancelled(&self) -> bool {
+        self.cancel.is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+}
+
+/// Scans one root: discovery streams repo roots into a bounded queue that a pool of
+/// metadata readers drains, while the calling thread writes results in batches.
+pub fn scan_root(
+    db: &Db,
+    root: &Path,
+    opts: &ScanOptions,
+    ignores: &IgnoreRules,
+) -> Result<ScanOutcome> {
+    scan_root_with(db, root, opts, ignores, &ScanHooks::default())
+}
+
+pub fn scan_root_with(
+    db: &Db,
+    root: &Path,
+    opts: &ScanOptions,
+    ignores: &IgnoreRules,
+    hooks: &ScanHooks<'_>,
+) -> Result<ScanOutcome> {
+    let started = std::time::Instant::now();
+    let started_ts = Utc::now().timestamp();
+    let root = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let filter = ScanFilter::new(&root, ignores)?;
+    let workers = opts.concurrency.clamp(1, 64);
+    let known: HashMap<String, String> = if opts.full {
+        HashMap::new()
+    } else {
+        db.repo_fingerprints()?
+    };
+    let heads = db.repo_heads()?;
+    let mut keep = HashSet::<String>::new();
+    let mut skipped = Vec::<String>::new();
+    let mut errors = Vec::<ScanError>::new();
+    let mut changes = Vec::<RepoChange>::new();
+    let discovered = AtomicUsize::new(0);
+
+    std::thread::scope(|s| -> Result<()> {
+        let (path_tx, path_rx) = mpsc::sync_channel::<PathBuf>(QUEUE_CAPACITY);
+        let (meta_tx, meta_rx) =
+            mpsc::sync_channel::<(RepoResult, Vec<ScanError>)>(QUEUE_CAPACITY);
+        // Workers own the receiver, so discovery stops as soon as they are gone.
+        let path_rx = Arc::new(Mutex::new(path_rx));
+
+        let walk_root = root.as_path();
+        let filter = &filter;
+        let discovered = &discovered;
+        let discovery = s.spawn(move || {
+            let mut walk_errors = Vec::new();
+            discover_git_repos(walk_root, opts.max_depth, filter, &mut walk_errors, |repo_root| {
+                if hooks.cancelled() {
+                    return false;
+                }
+                discovered.fetch_add(1, Ordering::Relaxed);
+                path_tx.send(repo_root).is_ok()
+            })
+            .map(|_| walk_errors)
+        });
+
+        for _ in 0..workers {
+            let path_rx = Arc::clone(&path_rx);
+            let meta_tx = meta_tx.clone();
+            let known = &known;
+            let heads = &heads;
+            s.spawn(move || loop {
+                if hooks.cancelled() {
+                    break;
+                }
+                let next = path_rx.lock().map_err(|_| ()).and_then(|rx| rx.recv().map_err(|_| ()));
+                let Ok(repo_root) = next else { break };
+                let repo_root = std::fs::canonicalize(&repo_root).unwrap_or(repo_root);
+                let path = repo_root.to_string_lossy().to_string();
+                let mut issues = Vec::new();
+                let result = match known.get(&path) {
+                    Some(fp) if *fp == repo_fingerprint(&repo_root) => RepoResult::Unchanged(path),
+                    _ => match read_repo_metadata(&repo_root, heads.get(&path), &mut issues) {
+                        Ok(meta) => RepoResult::Read(Box::new(meta)),
+                        Err(e) => {
+                            issues.push(scan_error(&repo_root, "metadata", format!("{e:#}")));
+                            RepoResult::Failed(path)
+                        }
+                    },
+                };
+                if meta_tx.send((result, issues)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(path_rx);
+        drop(meta_tx);
+
+        let mut batch = Vec::with_capacity(UPSERT_BATCH);
+        let mut processed = 0usize;
+        for (result, issues) in meta_rx {
+            errors.extend(issues);
+            processed += 1;
+            if let Some(report) = hooks.on_progress {
+                let current = match &result {
+                    RepoResult::Read(meta) => meta.path.as_str(),
+                    RepoResult::Unchanged(path) | RepoResult::Failed(path) => path.as_str(),
+                };
+                report(discovered.load(Ordering::Relaxed), processed, current);
+            }
+            match result {
+                RepoResult::Read(meta) => {
+                    keep.insert(meta.path.clone());
+                    batch.push(*meta);
+                    if batch.len() >= UPSERT_BATCH {
+                        changes.extend(db.upsert_repos(&batch)?);
+                        batch.clear();
+                    }
+                }
+                RepoResult::Unchanged(path) => {
+                    keep.insert(path.clone());
+                    skipped.push(path);
+                }
+                // Keep the existing row so a transient failure does not get it pruned.
+                RepoResult::Failed(path) => {
+                    keep.insert(path);
+                }
+            }
+            if hooks.cancelled() {
+                break;
+            }
+        }
+        changes.extend(db.upsert_repos(&batch)?);
+        db.touch_repos(&skipped, Utc::now().timestamp())?;
+
+        let walk_errors = discovery
+            .join()
+            .map_err(|_| anyhow::anyhow!("discovery thread panicked"))?
+            .with_context(|| format!("scan root {}", root.display()))?;
+        errors.extend(walk_errors);
+        Ok(())
+    })?;
+    if hooks.cancelled() {
+        anyhow::bail!("scan of {} cancelled", root.display());
+    }
+
+    errors.sort_by(|a, b| a.path.cmp(&b.path));
+    db.replace_scan_errors(&root.to_string_lossy(), &errors, Utc::now().timestamp())?;
+
+    for change in changes.iter_mut().filter(|c| c.kind == "head_moved") {
+        describe_head_move(change);
+    }
+    tag_rules::apply(db, &keep, &opts.tag_rules)?;
+    let mut would_prune = Vec::new();
+    let mut pruned_count = 0;
+    if opts.prune {
+        let pruned = db.prune_under_root(&root.to_string_lossy(), &keep, opts.dry_run)?;
+        if opts.dry_run {
+            would_prune = pruned;
+        } else {
+            pruned_count = pruned.len();
+            changes.extend(pruned);
+        }
+    }
+
+    db.record_scan_run(
+        &ScanRun {
+            root: root.to_string_lossy().to_string(),
+            started_ts,
+            finished_ts: Utc::now().timestamp(),
+            duration_ms: started.elapsed().as_millis() as i64,
+            full: opts.full,
+            indexed: keep.len(),
+            skipped: skipped.len(),
+            pruned: pruned_count,
+            errors: errors.len(),
+        },
+        &changes,
+    )?;
+    Ok(ScanOutcome {
+        indexed: keep.len(),
+        skipped: skipped.len(),
+        pruned: pruned_count,
+        errors,
+        changes,
+        would_prune,
+    })
+}
+
+/// Re-reads only `targets` under `root` (repo roots, or directories that appeared or
+/// vanished) instead of walking the whole root; used by the watcher. Repos found under a
+/// target are upserted, indexed repos at or under a target that were not found go to
+/// the trash. A scan run is recorded only when something changed.
+pub fn refresh_paths(
+    db: &Db,
+    root: &Path,
+    targets: &[PathBuf],
+    ignores: &IgnoreRules,
+    rules: &TagRules,
+) -> Result<ScanOutcome> {
+    let started = std::time::Instant::now();
+    let started_ts = Utc::now().timestamp();
+    let filter = ScanFilter::new(root, ignores)?;
+    let targets = targets
+        .iter()
+        .filter(|t| !filter.excludes(root, t))
+        .collect::<Vec<_>>();
+    let heads = db.repo_heads()?;
+    let mut keep = HashSet::<String>::new();
+    let mut errors = Vec::<ScanError>::new();
+    let mut metas = Vec::new();
+
+    for target in &targets {
+        let mut found = Vec::new();
+        if target.is_dir() {
+            discover_git_repos(target, None, &filter, &mut errors, |repo_root| {
+                found.push(repo_root);
+                true
+            })?;
+        }
+        for repo_root in found {
+            let repo_root = std::fs::canonicalize(&repo_root).unwrap_or(repo_root);
+            let path = repo_root.to_string_lossy().to_string();
+            if !keep.insert(path.clone()) {
+                continue;
+            }
+            match read_repo_metadata(&repo_root, heads.get(&path), &mut errors) {
+                Ok(meta) => metas.push(meta),
+                Err(e) => errors.push(scan_error(&repo_root, "metadata", format!("{e:#}"))),
+            }
+        }
+    }
+
+    let mut changes = db.upsert_repos(&metas)?;
+    for change in changes.iter_mut().filter(|c| c.kind == "head_moved") {
+        describe_head_move(change);
+    }
+    tag_rules::apply(db, &keep, rules)?;
+    let mut pruned = 0;
+    for target in &targets {
+        let removed = db.prune_under_root(&target.to_string_lossy(), &keep, false)?;
+        pruned += removed.len();
+        changes.extend(removed);
+    }
+
+    if !changes.is_empty() {
+        db.record_scan_run(
+            &ScanRun {
+                root: root.to_string_lossy().to_string(),
+                started_ts,
+                finished_ts: Utc::now().timestamp(),
+                duration_ms: started.elapsed().as_millis() as i64,
+                full: false,
+                indexed: metas.len(),
+                skipped: 0,
+                pruned,
+                errors: errors.len(),
+            },
+            &changes,
+        )?;
+    }
+    Ok(ScanOutcome {
+        indexed: metas.len(),
+        skipped: 0,
+        pruned,
+        errors,
+        changes,
+        would_prune: Vec::new(),
+    })
+}
+
+/// Turns a `head_moved` change into `new_commits` when the new HEAD descends from the
+/// old one (a pull or local commits), recording how many commits arrived.
+fn describe_head_move(change: &mut RepoChange) {
+    let (Some(old), Some(new)) = (&change.old_value, &change.new_value) else {
+        return;
+    };
+    let Ok(repo) = Repository::open(&change.path) else {
+        return;
+    };
+    let (Ok(old), Ok(new)) = (git2::Oid::from_str(old), git2::Oid::from_str(new)) else {
+        return;
+    };
+    if !repo.graph_descendant_of(new, old).unwrap_or(false) {
+        return;
+    }
+    let Ok(mut walk) = repo.revwalk() else {
+        return;
+    };
+    if walk.push(new).is_err() || walk.hide(old).is_err() {
+        return;
+    }
+    let count = walk.count();
+    change.kind = "new_commits".to_string();
+    change.detail = Some(format!("{count} commits"));
+}
+
+/// Walks `root` and calls `on_repo` for every repository found: regular checkouts,
+/// linked worktrees and submodules (whose `.git` is a `gitdir:` file) and bare repos.
+/// Unreadable directories are recorded in `errors` and skipped. Returning `false`
+/// from the callback stops the walk early.
 pub fn discover_git_repos(
     root: &Path,
     max_depth: Option<usize>,
-    ignore_dir_names: &HashSet<String>,
-) -> Result<Vec<PathBuf>> {
-    let mut repos = HashSet::<PathBuf>::new();
-
+    filter: &ScanFilter,
+    errors: &mut Vec<ScanError>,
+    mut on_repo: impl FnMut(PathBuf) -> bool,
+) -> Result<()> {
     let mut walker = WalkDir::new(root).follow_links(false);
     if let Some(d) = max_depth {
         walker = walker.max_depth(d);
@@ -22,44 +504,145 @@ pub fn discover_git_repos(
     while let Some(entry) = it.next() {
         let entry = match entry {
             Ok(e) => e,
-            Err(_) => continue,
+            Err(e) => {
+                let path = e.path().unwrap_or(root).to_path_buf();
+                errors.push(scan_error(&path, "walk", e));
+                continue;
+            }
         };
+        let name = entry.file_name().to_string_lossy();
+
+        if entry.file_type().is_file() {
+            if name == ".git" && is_gitdir_file(entry.path()) {
+                if let Some(repo_root) = entry.path().parent() {
+                    if !filter.is_hidden_repo(repo_root) && !on_repo(repo_root.to_path_buf()) {
+                        return Ok(());
+                    }
+                }
+            }
+            continue;
+        }
         if !entry.file_type().is_dir() {
             continue;
         }
 
-        let name = entry.file_name().to_string_lossy();
         if name == ".git" {
             if let Some(repo_root) = entry.path().parent() {
-                repos.insert(repo_root.to_path_buf());
+                if !filter.is_hidden_repo(repo_root) && !on_repo(repo_root.to_path_buf()) {
+                    return Ok(());
+                }
             }
             it.skip_current_dir();
             continue;
         }
 
-        if ignore_dir_names.contains(name.as_ref()) {
+        if entry.depth() > 0 && filter.is_ignored(entry.path(), name.as_ref()) {
             it.skip_current_dir();
             continue;
         }
+
+        if name.ends_with(".git") && is_bare_repo_dir(entry.path()) {
+            if !filter.is_hidden_repo(entry.path()) && !on_repo(entry.path().to_path_buf()) {
+                return Ok(());
+            }
+            it.skip_current_dir();
+        }
     }
 
-    let mut repos: Vec<_> = repos.into_iter().collect();
-    repos.sort();
-    Ok(repos)
+    Ok(())
 }
 
-pub fn read_repo_metadata(repo_root: &Path) -> Result<RepoMeta> {
+/// A `.git` file pointing elsewhere, as written for worktrees and submodules.
+fn is_gitdir_file(path: &Path) -> bool {
+    std::fs::read_to_string(path)
+        .map(|s| s.trim_start().starts_with("gitdir:"))
+        .unwrap_or(false)
+}
+
+pub fn is_bare_repo_dir(dir: &Path) -> bool {
+    dir.join("HEAD").is_file() && dir.join("objects").is_dir() && dir.join("refs").is_dir()
+}
+
+/// Git directory of a checkout, following `gitdir:` files; bare repos are their own git dir.
+fn resolve_git_dir(repo_root: &Path) -> PathBuf {
+    let dot_git = repo_root.join(".git");
+    if dot_git.is_dir() {
+        return dot_git;
+    }
+    if let Ok(s) = std::fs::read_to_string(&dot_git) {
+        if let Some(target) = s.trim().strip_prefix("gitdir:") {
+            return repo_root.join(target.trim());
+        }
+    }
+    repo_root.to_path_buf()
+}
+
+/// Shared git dir of a linked worktree (or the git dir itself for everything else).
+fn resolve_common_dir(git_dir: &Path) -> PathBuf {
+    match std::fs::read_to_string(git_dir.join("commondir")) {
+        Ok(s) => git_dir.join(s.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+/// Reads what can be read about a repo; partial failures (unopenable repo, unreadable
+/// README) are pushed to `errors` and leave the fingerprint unset so the next scan retries.
+/// `known` is the `(head_oid, identity)` stored by an earlier scan: while HEAD stays put
+/// its root commit is reused instead of walking the history again.
+pub fn read_repo_metadata(
+    repo_root: &Path,
+    known: Option<&(String, String)>,
+    errors: &mut Vec<ScanError>,
+) -> Result<RepoMeta> {
     let repo_root = std::fs::canonicalize(repo_root).unwrap_or_else(|_| repo_root.to_path_buf());
-    let name = repo_root
+    let mut name = repo_root
         .file_name()
         .map(|s| s.to_string_lossy().to_string())
         .unwrap_or_else(|| repo_root.to_string_lossy().to_string());
 
     let mut default_branch: Option<String> = None;
     let mut last_commit_ts: Option<i64> = None;
+    let mut head_oid: Option<String> = None;
+    let mut identity: Option<String> = None;
     let mut origin_url: Option<String> = None;
+    let mut kind = "normal";
+    let mut parent_path: Option<String> = None;
+    let errors_before = errors.len();
+
+    let repo = match Repository::open(&repo_root) {
+        Ok(repo) => Some(repo),
+        Err(e) => {
+            errors.push(scan_error(&repo_root, "open", e.message()));
+            None
+        }
+    };
+    if let Some(repo) = repo {
+        if repo.is_bare() {
+            kind = "bare";
+            if let Some(stripped) = name.strip_suffix(".git").filter(|s| !s.is_empty()) {
+                name = stripped.to_string();
+            }
+        } else if repo.is_worktree() {
+            kind = "worktree";
+            let common = resolve_common_dir(repo.path());
+            let common = std::fs::canonicalize(&common).unwrap_or(common);
+            // A worktree of a bare repo points straight at it; otherwise the main
+            // checkout is the directory holding `.git`.
+            let main = if common.file_name().is_some_and(|n| n == ".git") {
+                common.parent().map(Path::to_path_buf)
+            } else {
+                Some(common)
+            };
+            parent_path = main.map(|p| p.to_string_lossy().to_string());
+        } else if repo_root.join(".git").is_file() {
+            kind = "submodule";
+            parent_path = repo_root
+                .ancestors()
+                .skip(1)
+                .find(|p| p.join(".git").exists())
+                .map(|p| p.to_string_lossy().to_string());
+        }
 
-    if let Ok(repo) = Repository::open(&repo_root) {
         if let Ok(remote) = repo.find_remote("origin") {
             origin_url = remote.url().map(|s| s.to_string());
         } else if let Ok(remotes) = repo.remotes() {
@@ -80,11 +663,27 @@ pub fn read_repo_metadata(repo_root: &Path) -> Result<RepoMeta> {
             }
             if let Ok(commit) = head.peel_to_commit() {
                 last_commit_ts = Some(commit.time().seconds());
+                let oid = commit.id().to_string();
+                identity = match known {
+                    Some((known_head, known_identity)) if *known_head == oid => {
+                        let root = known_identity.split_once('@').map_or(known_identity.as_str(), |(root, _)| root);
+                        Some(identity_for(root, origin_url.as_deref()))
+                    }
+                    _ => repo_identity(&repo, commit.id(), origin_url.as_deref()),
+                };
+                head_oid = Some(oid);
             }
         }
     }
 
-    let readme_excerpt = read_readme_excerpt(&repo_root).ok();
+    let readme_excerpt = match read_readme_excerpt(&repo_root) {
+        Ok(excerpt) => excerpt,
+        Err(e) => {
+            errors.push(scan_error(&repo_root, "readme", format!("{e:#}")));
+            None
+        }
+    };
+    let fingerprint = (errors.len() == errors_before).then(|| repo_fingerprint(&repo_root));
     let now = Utc::now().timestamp();
 
     Ok(RepoMeta {
@@ -95,16 +694,122 @@ pub fn read_repo_metadata(repo_root: &Path) -> Result<RepoMeta> {
         last_scan_ts: now,
         readme_excerpt,
         origin_url,
+        fingerprint,
+        kind: kind.to_string(),
+        parent_path,
+        head_oid,
+        identity,
+        language: detect_language(&repo_root),
     })
 }
 
-fn read_readme_excerpt(repo_root: &Path) -> Result<String> {
-    let candidates = ["README.md", "Readme.md", "README.MD", "README"];
-    let readme = candidates
+/// Identity that survives moving a checkout: the root commit reached by following first
+/// parents from HEAD, plus the normalized origin so forks of one project stay distinct.
+pub fn repo_identity(repo: &Repository, head: git2::Oid, origin: Option<&str>) -> Option<String> {
+    let mut walk = repo.revwalk().ok()?;
+    walk.push(head).ok()?;
+    walk.simplify_first_parent().ok()?;
+    let root = walk.filter_map(|oid| oid.ok()).last()?;
+    Some(identity_for(&root.to_string(), origin))
+}
+
+fn identity_for(root: &str, origin: Option<&str>) -> String {
+    match origin.map(normalize_origin) {
+        Some(origin) if !origin.is_empty() => format!("{root}@{origin}"),
+        _ => root.to_string(),
+    }
+}
+
+/// `git@github.com:Org/Repo.git`, `https://user@github.com/Org/Repo/` and
+/// `ssh://git@github.com/Org/Repo` all become `github.com/org/repo`.
+pub fn normalize_origin(url: &str) -> String {
+    let mut s = url.trim();
+    if let Some((_, rest)) = s.split_once("://") {
+        s = rest;
+    }
+    if let Some((user, rest)) = s.split_once('@') {
+        if !user.contains('/') {
+            s = rest;
+        }
+    }
+    let s = s.trim_end_matches('/');
+    let s = s.strip_suffix(".git").unwrap_or(s);
+    // scp-like `host:path`; a port in `host:22/path` is dropped as well.
+    let s = match s.split_once(':') {
+        Some((host, path)) if !host.contains('/') => {
+            let path = path.trim_start_matches(|c: char| c.is_ascii_digit());
+            format!("{host}/{}", path.trim_start_matches('/'))
+        }
+        _ => s.to_string(),
+    };
+    s.to_lowercase()
+}
+
+/// Cheap change detector for a repo: stats the git files that move when HEAD, refs,
+/// remotes or the README change, without opening the repository. The work-tree root
+/// and `src/` directories are stated too, since adding or removing a file there can
+/// change the detected language; edits to existing files do not move them.
+pub fn repo_fingerprint(repo_root: &Path) -> String {
+    let git_dir = resolve_git_dir(repo_root);
+    let common_dir = resolve_common_dir(&git_dir);
+    let mut parts = vec![format!("v{FINGERPRINT_VERSION}")];
+
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).unwrap_or_default();
+    parts.push(format!("HEAD={}", head.trim()));
+    if let Some(target) = head.trim().strip_prefix("ref:") {
+        parts.push(stat_part("ref", &common_dir.join(target.trim())));
+    }
+    for name in ["packed-refs", "config"] {
+        parts.push(stat_part(name, &common_dir.join(name)));
+    }
+    parts.push(stat_part("logs/HEAD", &git_dir.join("logs/HEAD")));
+    for name in README_CANDIDATES {
+        let p = repo_root.join(name);
+        if p.exists() {
+            parts.push(stat_part(name, &p));
+            break;
+        }
+    }
+    if git_dir != repo_root {
+        parts.push(stat_part("worktree", repo_root));
+        parts.push(stat_part("src", &repo_root.join("src")));
+    }
+
+    format!("{:016x}", fnv1a64(parts.join("\n").as_bytes()))
+}
+
+fn stat_part(label: &str, path: &Path) -> String {
+    match std::fs::metadata(path) {
+        Ok(m) => {
+            let mtime = m
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_nanos())
+                .unwrap_or(0);
+            format!("{label}={mtime}:{}", m.len())
+        }
+        Err(_) => format!("{label}=-"),
+    }
+}
+
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in bytes {
+        h ^= u64::from(*b);
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    h
+}
+
+fn read_readme_excerpt(repo_root: &Path) -> Result<Option<String>> {
+    let Some(readme) = README_CANDIDATES
         .iter()
         .map(|n| repo_root.join(n))
         .find(|p| p.exists())
-        .context("no readme")?;
+    else {
+        return Ok(None);
+    };
 
     let s = std::fs::read_to_string(&readme).with_context(|| format!("read {}", readme.display()))?;
     let excerpt = s
@@ -113,5 +818,491 @@ fn read_readme_excerpt(repo_root: &Path) -> Result<String> {
         .take(10)
         .collect::<Vec<_>>()
         .join(" ");
-    Ok(excerpt.chars().take(280).collect())
+    Ok(Some(text::take_graphemes(&excerpt, 280).to_string()))
+}
+
+/// Build files that settle a repo's language; checked in order, so a TypeScript project
+/// with a `package.json` is not reported as JavaScript.
+const LANGUAGE_MARKERS: [(&str, &str); 18] = [
+    ("Cargo.toml", "Rust"),
+    ("go.mod", "Go"),
+    ("tsconfig.json", "TypeScript"),
+    ("package.json", "JavaScript"),
+    ("pyproject.toml", "Python"),
+    ("setup.py", "Python"),
+    ("requirements.txt", "Python"),
+    ("Pipfile", "Python"),
+    ("build.gradle.kts", "Kotlin"),
+    ("pom.xml", "Java"),
+    ("build.gradle", "Java"),
+    ("Gemfile", "Ruby"),
+    ("composer.json", "PHP"),
+    ("mix.exs", "Elixir"),
+    ("Package.swift", "Swift"),
+    ("pubspec.yaml", "Dart"),
+    ("stack.yaml", "Haskell"),
+    ("CMakeLists.txt", "C++"),
+];
+
+const LANGUAGE_EXTENSIONS: [(&str, &str); 22] = [
+    ("rs", "Rust"),
+    ("go", "Go"),
+    ("ts", "TypeScript"),
+    ("tsx", "TypeScript"),
+    ("js", "JavaScript"),
+    ("jsx", "JavaScript"),
+    ("mjs", "JavaScript"),
+    ("py", "Python"),
+    ("java", "Java"),
+    ("kt", "Kotlin"),
+    ("rb", "Ruby"),
+    ("php", "PHP"),
+    ("ex", "Elixir"),
+    ("swift", "Swift"),
+    ("dart", "Dart"),
+    ("hs", "Haskell"),
+    ("c", "C"),
+    ("cpp", "C++"),
+    ("cc", "C++"),
+    ("cs", "C#"),
+    ("scala", "Scala"),
+    ("sh", "Shell"),
+];
+
+/// Guesses the main language of a work tree from marker files, falling back to the most
+/// common source extension in the top directory and `src/`. Bare repos have no files to
+/// look at and get `None`.
+pub fn detect_language(repo_root: &Path) -> Option<String> {
+    if let Some((_, lang)) = LANGUAGE_MARKERS.iter().find(|(f, _)| repo_root.join(f).is_file()) {
+        return Some(lang.to_string());
+    }
+    let mut counts = HashMap::<&str, usize>::new();
+    for dir in [repo_root.to_path_buf(), repo_root.join("src")] {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten().take(500) {
+            let path = entry.path();
+            let Some(ext) = path.extension().and_then(|e| e.to_str()) else { continue };
+            if let Some((_, lang)) = LANGUAGE_EXTENSIONS.iter().find(|(e, _)| *e == ext) {
+                *counts.entry(lang).or_default() += 1;
+            }
+        }
+    }
+    // Ties go to the language listed first so the answer does not depend on hash order.
+    LANGUAGE_EXTENSIONS
+        .iter()
+        .map(|(_, lang)| *lang)
+        .filter_map(|lang| counts.get(lang).map(|&n| (lang, n)))
+        .fold(None, |best: Option<(&str, usize)>, (lang, n)| match best {
+            Some((_, m)) if m >= n => best,
+            _ => Some((lang, n)),
+        })
+        .map(|(lang, _)| lang.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::RepoFilter;
+    use tempfile::tempdir;
+
+    #[test]
+    fn scan_root_indexes_nested_repos() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path().join("root");
+        for name in ["a", "b", "nested/c", "node_modules/dep"] {
+            Repository::init(root.join(name))?;
+        }
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+
+        let ignore = IgnoreRules::new(&["node_modules".to_string()], &[]);
+        let opts = ScanOptions {
+            max_depth: None,
+            prune: false,
+            concurrency: 2,
+            full: false,
+            dry_run: false,
+            tag_rules: TagRules::default(),
+        };
+        let out = scan_root(&db, &root, &opts, &ignore)?;
+        assert_eq!(out.indexed, 3);
+        assert_eq!(out.skipped, 0);
+
+        let again = scan_root(&db, &root, &opts, &ignore)?;
+        assert_eq!(again.indexed, 3);
+        assert_eq!(again.skipped, 3);
+
+        std::fs::write(root.join("a/README.md"), "# a\n")?;
+        let after_edit = scan_root(&db, &root, &opts, &ignore)?;
+        assert_eq!(after_edit.skipped, 2);
+
+        std::fs::write(root.join("b/Cargo.toml"), "[package]\n")?;
+        let after_marker = scan_root(&db, &root, &opts, &ignore)?;
+        assert_eq!(after_marker.skipped, 2);
+        let b = db.list_repos(&RepoFilter::default(), false)?.into_iter().find(|r| r.name == "b");
+        assert_eq!(b.and_then(|r| r.language).as_deref(), Some("Rust"));
+
+        let mut names = db
+            .list_repos(&RepoFilter::default(), false)?
+            .into_iter()
+            .map(|r| r.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["a", "b", "c"]);
+        Ok(())
+    }
+
+    #[test]
+    fn ignore_patterns_follow_gitignore_rules() -> Result<()> {
+        let dir = tempdir()?;
+        let root = std::fs::canonicalize(dir.path())?;
+        for name in ["vendor/x", "app/vendor/y", "old-backup/z", "keep-backup/w", "app/src"] {
+            Repository::init(root.join(name))?;
+        }
+        let anchored = format!("{}/vendor/**", root.display());
+        let rules = IgnoreRules::new(
+            &[],
+            &[anchored, "*-backup".to_string(), "!keep-backup".to_string()],
+        );
+        let filter = ScanFilter::new(&root, &rules)?;
+        let mut found = Vec::new();
+        discover_git_repos(&root, None, &filter, &mut Vec::new(), |p| {
+            found.push(p.strip_prefix(&root).unwrap().to_string_lossy().to_string());
+            true
+        })?;
+        found.sort();
+        assert_eq!(found, vec!["app/src", "app/vendor/y", "keep-backup/w"]);
+
+        // Hiding a repo skips just that repo: a plain directory is not a hidden repo, and
+        // repos nested inside a hidden one are still found.
+        Repository::init(root.join("app/src/nested"))?;
+        let mut rules = IgnoreRules::default();
+        rules.excluded_paths.insert(root.join("app/src"));
+        rules.excluded_paths.insert(root.join("vendor/x"));
+        rules.excluded_paths.insert(root.join("old-backup"));
+        let filter = ScanFilter::new(&root, &rules)?;
+        let mut found = Vec::new();
+        discover_git_repos(&root, None, &filter, &mut Vec::new(), |p| {
+            found.push(p.strip_prefix(&root).unwrap().to_string_lossy().to_string());
+            true
+        })?;
+        found.sort();
+        assert_eq!(found, vec!["app/src/nested", "app/vendor/y", "keep-backup/w", "old-backup/z"]);
+        assert!(filter.excludes(&root, &root.join("app/src")));
+        assert!(!filter.excludes(&root, &root.join("app/src/nested")));
+        Ok(())
+    }
+
+    #[test]
+    fn absolute_patterns_outside_the_root_stay_root_anchored() {
+        let root = Path::new("/work");
+        assert_eq!(pattern_for_root(root, "/work/vendor/**").as_deref(), Some("/vendor/**"));
+        assert_eq!(pattern_for_root(root, "!/work/keep").as_deref(), Some("!/keep"));
+        assert_eq!(pattern_for_root(root, "/elsewhere/vendor").as_deref(), Some("/elsewhere/vendor"));
+        assert_eq!(pattern_for_root(root, "/workshop").as_deref(), Some("/workshop"));
+        if dirs::home_dir().is_some_and(|h| !h.starts_with(root)) {
+            assert_eq!(pattern_for_root(root, "~/vendor"), None);
+        }
+    }
+
+    #[test]
+    fn discovers_worktrees_submodules_and_bare_repos() -> Result<()> {
+        let dir = tempdir()?;
+        let root = std::fs::canonicalize(dir.path())?;
+        let main = Repository::init(root.join("main"))?;
+        let sig = git2::Signature::now("t", "t@example.com")?;
+        let tree = main.find_tree(main.index()?.write_tree()?)?;
+        main.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])?;
+        main.worktree("wt", &root.join("main-wt"), None)?;
+        let mut opts = git2::RepositoryInitOptions::new();
+        opts.workdir_path(&root.join("main/sub"));
+        Repository::init_opts(root.join("main/.git/modules/sub"), &opts)?;
+        Repository::init_bare(root.join("mirror.git"))?;
+
+        let db = Db::open(&root.join("t.db"))?;
+        db.init_schema()?;
+        let scan_opts = ScanOptions {
+            max_depth: None,
+            prune: false,
+            concurrency: 2,
+            full: false,
+            dry_run: false,
+            tag_rules: TagRules::default(),
+        };
+        let out = scan_root(&db, &root, &scan_opts, &IgnoreRules::default())?;
+        assert_eq!(out.indexed, 4);
+
+        let main_path = root.join("main").to_string_lossy().to_string();
+        let mut kinds = db
+            .list_repos(&RepoFilter::default(), false)?
+            .into_iter()
+            .map(|r| (r.name, r.kind, r.parent_path))
+            .collect::<Vec<_>>();
+        kinds.sort();
+        assert_eq!(
+            kinds,
+            vec![
+                ("main".to_string(), "normal".to_string(), None),
+                ("main-wt".to_string(), "worktree".to_string(), Some(main_path.clone())),
+                ("mirror".to_string(), "bare".to_string(), None),
+                ("sub".to_string(), "submodule".to_string(), Some(main_path.clone())),
+            ]
+        );
+
+        let listed = db.list_repos_with_tags_paged(&RepoFilter::default(), false, 1, 50)?;
+        assert_eq!(listed.total, 3);
+        let grouped = db.worktrees_by_parent(std::slice::from_ref(&main_path))?;
+        assert_eq!(grouped[&main_path].len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn scan_records_errors_and_keeps_going() -> Result<()> {
+        let dir = tempdir()?;
+        let root = std::fs::canonicalize(dir.path())?.join("root");
+        Repository::init(root.join("ok"))?;
+        std::fs::create_dir_all(root.join("broken/.git"))?;
+
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        let opts = ScanOptions {
+            max_depth: None,
+            prune: false,
+            concurrency: 2,
+            full: false,
+            dry_run: false,
+            tag_rules: TagRules::default(),
+        };
+        let out = scan_root(&db, &root, &opts, &IgnoreRules::default())?;
+        assert_eq!(out.indexed, 2);
+        assert_eq!(out.errors.len(), 1);
+        assert_eq!(out.errors[0].kind, "open");
+
+        let stored = db.list_scan_errors(10)?;
+        assert_eq!(stored.len(), 1);
+        assert!(stored[0].path.ends_with("broken"));
+
+        // A failed read is retried next time rather than skipped as unchanged.
+        let again = scan_root(&db, &root, &opts, &IgnoreRules::default())?;
+        assert_eq!(again.skipped, 1);
+        assert_eq!(db.list_scan_errors(10)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn scan_runs_record_repo_changes() -> Result<()> {
+        let dir = tempdir()?;
+        let root = std::fs::canonicalize(dir.path())?.join("root");
+        let a = Repository::init(root.join("a"))?;
+        Repository::init(root.join("b"))?;
+        let sig = git2::Signature::now("t", "t@example.com")?;
+        let commit = |repo: &Repository, msg: &str| -> Result<()> {
+            let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+            let parents = match repo.head() {
+                Ok(h) => vec![h.peel_to_commit()?],
+                Err(_) => Vec::new(),
+            };
+            repo.commit(Some("HEAD"), &sig, &sig, msg, &tree, &parents.iter().collect::<Vec<_>>())?;
+            Ok(())
+        };
+        commit(&a, "one")?;
+
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        let opts = ScanOptions {
+            max_depth: None,
+            prune: true,
+            concurrency: 2,
+            full: false,
+            dry_run: false,
+            tag_rules: TagRules::default(),
+        };
+        let first = scan_root(&db, &root, &opts, &IgnoreRules::default())?;
+        assert_eq!(first.changes.iter().filter(|c| c.kind == "new").count(), 2);
+
+        commit(&a, "two")?;
+        commit(&a, "three")?;
+        a.remote("origin", "https://example.com/a.git")?;
+        std::fs::remove_dir_all(root.join("b"))?;
+        let second = scan_root(&db, &root, &opts, &IgnoreRules::default())?;
+        let mut kinds = second
+            .changes
+            .iter()
+            .map(|c| (c.kind.as_str(), c.name.as_str(), c.detail.as_deref()))
+            .collect::<Vec<_>>();
+        kinds.sort();
+        let reason = format!("not found by scan of {}", root.display());
+        assert_eq!(
+            kinds,
+            vec![
+                ("new_commits", "a", Some("2 commits")),
+                ("origin_changed", "a", None),
+                ("removed", "b", Some(reason.as_str())),
+            ]
+        );
+
+        let runs = db.list_scan_runs(0, 10)?;
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].changes.len(), 3);
+        assert_eq!(runs[0].run.pruned, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn moved_repo_keeps_its_row_and_tags() -> Result<()> {
+        let dir = tempdir()?;
+        let root = std::fs::canonicalize(dir.path())?.join("root");
+        {
+            let repo = Repository::init(root.join("old/app"))?;
+            let sig = git2::Signature::now("t", "t@example.com")?;
+            let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+            repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])?;
+            repo.remote("origin", "git@github.com:Org/App.git")?;
+        }
+
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        let opts = ScanOptions {
+            max_depth: None,
+            prune: true,
+            concurrency: 2,
+            full: false,
+            dry_run: false,
+            tag_rules: TagRules::default(),
+        };
+        scan_root(&db, &root, &opts, &IgnoreRules::default())?;
+        let old_path = root.join("old/app").to_string_lossy().to_string();
+        db.add_tag_to_repo(&old_path, "keep")?;
+        let id = db.list_repos(&RepoFilter::default(), false)?[0].id;
+
+        std::fs::create_dir_all(root.join("new"))?;
+        std::fs::rename(root.join("old/app"), root.join("new/app"))?;
+        let out = scan_root(&db, &root, &opts, &IgnoreRules::default())?;
+        assert_eq!(out.pruned, 0);
+        assert!(out.changes.iter().any(|c| c.kind == "moved"));
+
+        let new_path = root.join("new/app").to_string_lossy().to_string();
+        let rows = db.list_repos(&RepoFilter::default(), false)?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!((rows[0].id, rows[0].path.as_str()), (id, new_path.as_str()));
+        assert_eq!(db.list_repo_tags(&new_path)?, vec!["keep"]);
+        Ok(())
+    }
+
+    #[test]
+    fn identity_walks_history_only_when_head_moves() -> Result<()> {
+        let dir = tempdir()?;
+        let repo = Repository::init(dir.path().join("app"))?;
+        let sig = git2::Signature::now("t", "t@example.com")?;
+        let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+        let root = repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])?;
+        repo.remote("origin", "git@github.com:Org/App.git")?;
+
+        let read = |known: Option<&(String, String)>| -> Result<RepoMeta> {
+            read_repo_metadata(&dir.path().join("app"), known, &mut Vec::new())
+        };
+        let first = read(None)?;
+        assert_eq!(first.identity, Some(format!("{root}@github.com/org/app")));
+
+        // Same HEAD: the stored root commit is trusted, only the origin is re-read.
+        let stored = (first.head_oid.clone().unwrap_or_default(), "cached@gitlab.com/old/app".to_string());
+        assert_eq!(read(Some(&stored))?.identity.as_deref(), Some("cached@github.com/org/app"));
+
+        let parent = repo.find_commit(root)?;
+        repo.commit(Some("HEAD"), &sig, &sig, "two", &tree, &[&parent])?;
+        assert_eq!(read(Some(&stored))?.identity, first.identity);
+        Ok(())
+    }
+
+    #[test]
+    fn pruned_repos_go_to_trash_and_come_back() -> Result<()> {
+        let dir = tempdir()?;
+        let root = std::fs::canonicalize(dir.path())?.join("root");
+        Repository::init(root.join("a"))?;
+        Repository::init(root.join("b"))?;
+
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        let mut opts = ScanOptions {
+            max_depth: None,
+            prune: true,
+            concurrency: 2,
+            full: false,
+            dry_run: true,
+            tag_rules: TagRules::default(),
+        };
+        scan_root(&db, &root, &opts, &IgnoreRules::default())?;
+        let b_path = root.join("b").to_string_lossy().to_string();
+        db.add_tag_to_repo(&b_path, "keep")?;
+
+        // An unmounted disk looks like this: the directory is simply gone.
+        let hidden = dir.path().join("hidden-b");
+        std::fs::rename(root.join("b"), &hidden)?;
+        let out = scan_root(&db, &root, &opts, &IgnoreRules::default())?;
+        assert_eq!(out.would_prune.len(), 1);
+        assert_eq!(db.list_repos(&RepoFilter::default(), false)?.len(), 2);
+
+        opts.dry_run = false;
+        let out = scan_root(&db, &root, &opts, &IgnoreRules::default())?;
+        assert_eq!(out.pruned, 1);
+        assert_eq!(db.list_repos(&RepoFilter::default(), false)?.len(), 1);
+        assert!(db.list_tags_with_count()?.is_empty());
+        let trash = db.list_trash()?;
+        assert_eq!(trash.len(), 1);
+        assert!(trash[0].removed_reason.is_some());
+
+        // Coming back at the same path restores the row with its tags.
+        std::fs::rename(&hidden, root.join("b"))?;
+        let out = scan_root(&db, &root, &opts, &IgnoreRules::default())?;
+        assert!(out.changes.iter().any(|c| c.kind == "restored"));
+        assert!(db.list_trash()?.is_empty());
+        assert_eq!(db.list_repo_tags(&b_path)?, vec!["keep"]);
+
+        std::fs::remove_dir_all(root.join("b"))?;
+        assert_eq!(db.prune_missing_paths(false)?.len(), 1);
+        assert_eq!(db.restore_repo("b")?.as_deref(), Some(b_path.as_str()));
+        db.prune_missing_paths(false)?;
+        assert_eq!(db.purge_trash(None, None)?, 1);
+        assert!(db.list_trash()?.is_empty());
+        assert!(db.list_tags()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn refresh_paths_only_touches_the_given_targets() -> Result<()> {
+        let dir = tempdir()?;
+        let root = std::fs::canonicalize(dir.path())?.join("root");
+        Repository::init(root.join("a"))?;
+        Repository::init(root.join("group/b"))?;
+
+        let db = Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        let ignores = IgnoreRules::new(&["node_modules".to_string()], &[]);
+        let out = refresh_paths(&db, &root, &[root.join("group")], &ignores, &TagRules::default())?;
+        assert_eq!(out.indexed, 1);
+        assert_eq!(db.list_repos(&RepoFilter::default(), false)?[0].name, "b");
+
+        Repository::init(root.join("group/c"))?;
+        Repository::init(root.join("node_modules/dep"))?;
+        std::fs::remove_dir_all(root.join("group/b"))?;
+        let targets = [root.join("group/b"), root.join("group/c"), root.join("node_modules/dep")];
+        let out = refresh_paths(&db, &root, &targets, &ignores, &TagRules::default())?;
+        let mut kinds = out.changes.iter().map(|c| (c.kind.as_str(), c.name.as_str())).collect::<Vec<_>>();
+        kinds.sort();
+        assert_eq!(kinds, vec![("new", "c"), ("removed", "b")]);
+        assert_eq!(db.list_scan_runs(0, 10)?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn origins_normalize_across_url_styles() {
+        for url in [
+            "git@github.com:Org/App.git",
+            "https://user@github.com/Org/App/",
+            "ssh://git@github.com/Org/App",
+            "ssh://git@github.com:22/Org/App.git",
+        ] {
+            assert_eq!(normalize_origin(url), "github.com/org/app", "{url}");
+        }
+    }
 }
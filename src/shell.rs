@@ -0,0 +1,212 @@
+//! Shell integration: the `cr`-style jump function, static completions generated from the
+//! clap definitions, and a hook that completes repo names, aliases, tags and roots from
+//! the DB (`coderoom complete -- <words before the cursor>`).
+
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InitShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl InitShell {
+    pub fn generator(self) -> clap_complete::Shell {
+        match self {
+            InitShell::Bash => clap_complete::Shell::Bash,
+            InitShell::Zsh => clap_complete::Shell::Zsh,
+            InitShell::Fish => clap_complete::Shell::Fish,
+        }
+    }
+}
+
+/// What to offer for the word being completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    /// Indexed repo names and aliases.
+    Repos,
+    Tags,
+    Roots,
+    Aliases,
+    /// Paths in `excluded_repos`.
+    Hidden,
+}
+
+/// Works out which [`ValueKind`] the next word takes. `words` is the command line up to
+/// (not including) the word under the cursor, starting with the program name.
+pub fn value_kind(cmd: &clap::Command, words: &[String]) -> Option<ValueKind> {
+    let mut cmd = cmd;
+    let mut path: Vec<&str> = Vec::new();
+    let mut positionals = 0;
+    let mut i = 1;
+    while i < words.len() {
+        let w = words[i].as_str();
+        if let Some(long) = w.strip_prefix("--").filter(|l| !l.is_empty() && !l.contains('=')) {
+            let arg = cmd.get_arguments().find(|a| a.get_long() == Some(long));
+            if let Some(arg) = arg.filter(|a| a.get_action().takes_values()) {
+                if i + 1 == words.len() {
+                    return kind_for(&path, arg.get_id().as_str());
+                }
+                i += 1;
+            }
+        } else if w.starts_with('-') {
+            // Short flags and `--opt=value` carry no separate value word.
+        } else if let Some(sub) = cmd.find_subcommand(w).filter(|_| positionals == 0) {
+            cmd = sub;
+            path.push(sub.get_name());
+        } else {
+            positionals += 1;
+        }
+        i += 1;
+    }
+    let positional: Vec<&clap::Arg> = cmd.get_positionals().collect();
+    let arg = positional.get(positionals).or_else(|| {
+        // A trailing multi-value positional (`open <query>...`) keeps taking words.
+        positional
+            .last()
+            .filter(|a| a.get_num_args().is_some_and(|n| n.max_values() > 1))
+    })?;
+    kind_for(&path, arg.get_id().as_str())
+}
+
+fn kind_for(path: &[&str], id: &str) -> Option<ValueKind> {
+    match (path, id) {
+        // Trashed repos are not in the live index.
+        (["trash", ..], _) => None,
+        // A commit search query, not a repo.
+        (["commits"], _) => None,
+        (_, "repo" | "query") => Some(ValueKind::Repos),
+        (_, "tag" | "any" | "not") => Some(ValueKind::Tags),
+        (["tag", ..], "from" | "into" | "name") => Some(ValueKind::Tags),
+        (_, "root") => Some(ValueKind::Roots),
+        (["alias", "remove"], "alias") => Some(ValueKind::Aliases),
+        (["unhide"], "path") => Some(ValueKind::Hidden),
+        _ => None,
+    }
+}
+
+/// The script `eval`ed from the shell rc file: static completions for `coderoom`, the
+/// DB-backed completion hook, and a `cmd` function that cd's into `coderoom open`'s hit
+/// (without arguments it lists the top candidates).
+pub fn init_script(shell: InitShell, cmd: &str, completions: &str) -> String {
+    let tail = match shell {
+        InitShell::Bash => format!(
+            r#"
+_coderoom_dynamic() {{
+  local vals
+  vals="$(command coderoom complete -- "${{COMP_WORDS[@]:0:COMP_CWORD}}" 2>/dev/null)"
+  if [ -z "$vals" ]; then
+    _coderoom "$@"
+    return
+  fi
+  local IFS=$'\n'
+  COMPREPLY=($(compgen -W "$vals" -- "${{COMP_WORDS[COMP_CWORD]}}"))
+}}
+complete -F _coderoom_dynamic -o bashdefault -o default coderoom
+
+{cmd}() {{
+  if [ "$#" -eq 0 ]; then
+    command coderoom open --list
+    return
+  fi
+  local dir
+  dir="$(command coderoom open -- "$@")" && builtin cd -- "$dir"
+}}
+
+_coderoom_cd_complete() {{
+  local IFS=$'\n'
+  COMPREPLY=($(compgen -W "$(command coderoom complete -- coderoom open 2>/dev/null)" -- "${{COMP_WORDS[COMP_CWORD]}}"))
+}}
+complete -F _coderoom_cd_complete {cmd}
+"#
+        ),
+        InitShell::Zsh => format!(
+            r#"
+_coderoom_dynamic() {{
+  local -a vals
+  vals=("${{(@f)$(command coderoom complete -- "${{(@)words[1,CURRENT-1]}}" 2>/dev/null)}}")
+  if [[ -n "${{vals[1]}}" ]]; then
+    compadd -a vals
+  else
+    _coderoom "$@"
+  fi
+}}
+compdef _coderoom_dynamic coderoom
+
+{cmd}() {{
+  if [[ $# -eq 0 ]]; then
+    command coderoom open --list
+    return
+  fi
+  local dir
+  dir="$(command coderoom open -- "$@")" && builtin cd -- "$dir"
+}}
+
+_coderoom_cd_complete() {{
+  local -a vals
+  vals=("${{(@f)$(command coderoom complete -- coderoom open 2>/dev/null)}}")
+  compadd -a vals
+}}
+compdef _coderoom_cd_complete {cmd}
+"#
+        ),
+        InitShell::Fish => format!(
+            r#"
+complete -c coderoom -a '(command coderoom complete -- (commandline -opc) 2>/dev/null)'
+
+function {cmd} --description 'cd into a coderoom repo'
+    if test (count $argv) -eq 0
+        command coderoom open --list
+        return
+    end
+    set -l dir (command coderoom open -- $argv); and builtin cd -- $dir
+end
+
+complete -c {cmd} -f -a '(command coderoom complete -- coderoom open 2>/dev/null)'
+"#
+        ),
+    };
+    format!("{completions}{tail}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Arg, ArgAction, Command};
+
+    #[test]
+    fn value_kind_follows_subcommands_and_options() {
+        let cli = Command::new("coderoom")
+            .subcommand(
+                Command::new("list")
+                    .arg(Arg::new("tag").long("tag").action(ArgAction::Append))
+                    .arg(Arg::new("recent").long("recent").action(ArgAction::SetTrue)),
+            )
+            .subcommand(Command::new("open").arg(Arg::new("query").num_args(1..)))
+            .subcommand(
+                Command::new("field").subcommand(
+                    Command::new("set").arg(Arg::new("repo")).arg(Arg::new("key")).arg(Arg::new("value")),
+                ),
+            )
+            .subcommand(Command::new("trash").subcommand(Command::new("restore").arg(Arg::new("repo"))));
+        let kind = |line: &str| {
+            let words: Vec<String> = line.split_whitespace().map(String::from).collect();
+            value_kind(&cli, &words)
+        };
+        assert_eq!(kind("coderoom list --tag"), Some(ValueKind::Tags));
+        assert_eq!(kind("coderoom list --recent --tag"), Some(ValueKind::Tags));
+        assert_eq!(kind("coderoom list --tag x"), None);
+        assert_eq!(kind("coderoom open"), Some(ValueKind::Repos));
+        assert_eq!(kind("coderoom open pay"), Some(ValueKind::Repos));
+        assert_eq!(kind("coderoom field set"), Some(ValueKind::Repos));
+        assert_eq!(kind("coderoom field set api"), None);
+        assert_eq!(kind("coderoom trash restore"), None);
+        assert_eq!(kind("coderoom"), None);
+
+        let script = init_script(InitShell::Bash, "j", "# generated\n");
+        assert!(script.starts_with("# generated\n"));
+        assert!(script.contains("j() {"));
+        assert!(script.contains("complete -F _coderoom_cd_complete j"));
+    }
+}
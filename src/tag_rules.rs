@@ -0,0 +1,219 @@
+use crate::config::{Config, TagRule};
+use crate::db::{Db, RepoRow};
+use crate::scan;
+use anyhow::{bail, Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// `[[tag_rules]]` from `config.toml` compiled for matching, plus the auto-tags of the
+/// root being scanned (which apply to every repo found under it).
+#[derive(Debug, Clone, Default)]
+pub struct TagRules {
+    pub auto_tags: Vec<String>,
+    rules: Vec<CompiledRule>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    rule: TagRule,
+    path_glob: Option<GlobMatcher>,
+    root: Option<PathBuf>,
+}
+
+impl TagRules {
+    pub fn compile(rules: &[TagRule], auto_tags: Vec<String>) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| compile_rule(rule).with_context(|| format!("tag rule #{}", i + 1)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { auto_tags, rules })
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &TagRule> {
+        self.rules.iter().map(|r| &r.rule)
+    }
+
+    /// Whether the rule at `index` matches `repo`.
+    pub fn matches(&self, index: usize, repo: &RepoRow) -> bool {
+        let r = &self.rules[index];
+        let path = Path::new(&repo.path);
+        if r.path_glob.as_ref().is_some_and(|g| !g.is_match(path)) {
+            return false;
+        }
+        if r.root.as_ref().is_some_and(|root| !path.starts_with(root)) {
+            return false;
+        }
+        if r.rule.origin_host.is_some() || r.rule.origin_owner.is_some() {
+            let origin = repo.origin_url.as_deref().map(scan::normalize_origin).unwrap_or_default();
+            let mut parts = origin.split('/');
+            let (host, owner) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+            if r.rule.origin_host.as_ref().is_some_and(|h| !h.eq_ignore_ascii_case(host)) {
+                return false;
+            }
+            if r.rule.origin_owner.as_ref().is_some_and(|o| !o.eq_ignore_ascii_case(owner)) {
+                return false;
+            }
+        }
+        if let Some(lang) = &r.rule.language {
+            if !repo.language.as_deref().is_some_and(|l| l.eq_ignore_ascii_case(lang)) {
+                return false;
+            }
+        }
+        if let Some(branch) = &r.rule.default_branch {
+            if !repo.default_branch.as_deref().is_some_and(|b| b.eq_ignore_ascii_case(branch)) {
+                return false;
+            }
+        }
+        r.rule.has_files.iter().all(|f| path.join(f).exists())
+    }
+
+    /// Auto-tags plus the tags of every matching rule, sorted and deduplicated.
+    pub fn tags_for(&self, repo: &RepoRow) -> Vec<String> {
+        let mut tags = self.auto_tags.clone();
+        for (i, r) in self.rules.iter().enumerate() {
+            if self.matches(i, repo) {
+                tags.extend(r.rule.tags.iter().cloned());
+            }
+        }
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+}
+
+fn compile_rule(rule: &TagRule) -> Result<CompiledRule> {
+    let mut rule = rule.clone();
+    rule.tags = rule
+        .tags
+        .iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    if rule.tags.is_empty() {
+        bail!("no tags to apply");
+    }
+    let unconditional = TagRule {
+        tags: rule.tags.clone(),
+        ..TagRule::default()
+    };
+    if rule == unconditional {
+        bail!("no conditions; use a root's auto_tags to tag everything under it");
+    }
+    let path_glob = match &rule.path_glob {
+        Some(pattern) => Some(
+            GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("invalid path_glob '{pattern}'"))?
+                .compile_matcher(),
+        ),
+        None => None,
+    };
+    let root = rule
+        .root
+        .as_ref()
+        .map(|r| std::fs::canonicalize(r).unwrap_or_else(|_| PathBuf::from(r)));
+    Ok(CompiledRule { rule, path_glob, root })
+}
+
+/// Recomputes the rule-applied tags of the indexed repos at `paths`.
+pub fn apply(db: &Db, paths: &HashSet<String>, rules: &TagRules) -> Result<()> {
+    let tags = db
+        .repos_by_paths(paths)?
+        .into_iter()
+        .map(|repo| {
+            let tags = rules.tags_for(&repo);
+            (repo.path, tags)
+        })
+        .collect::<Vec<_>>();
+    db.replace_rule_tags(&tags)
+}
+
+/// Rule tags every live repo should carry under the current config, each repo getting
+/// the auto-tags of the root it lives under.
+pub fn evaluate_all(db: &Db, cfg: &Config) -> Result<Vec<(RepoRow, Vec<String>)>> {
+    let rules = TagRules::compile(&cfg.tag_rules, Vec::new())?;
+    let mut out = Vec::new();
+    for repo in db.repos_by_paths(&db.list_repo_paths()?)? {
+        let mut tags = rules.tags_for(&repo);
+        if let Some(root) = cfg.root_of(Path::new(&repo.path)) {
+            tags.extend(cfg.root_settings(Path::new(root)).auto_tags);
+            tags.sort();
+            tags.dedup();
+        }
+        out.push((repo, tags));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn rules_match_on_origin_language_files_and_path() -> Result<()> {
+        let dir = tempdir()?;
+        let root = std::fs::canonicalize(dir.path())?;
+        let app = root.join("work/app");
+        std::fs::create_dir_all(&app)?;
+        std::fs::write(app.join("Dockerfile"), "FROM scratch\n")?;
+
+        let rules = TagRules::compile(
+            &[
+                TagRule {
+                    tags: vec!["acme".into()],
+                    origin_host: Some("GitHub.com".into()),
+                    origin_owner: Some("acme".into()),
+                    ..TagRule::default()
+                },
+                TagRule {
+                    tags: vec!["docker".into(), "rust".into()],
+                    language: Some("rust".into()),
+                    has_files: vec!["Dockerfile".into()],
+                    default_branch: Some("Main".into()),
+                    ..TagRule::default()
+                },
+                TagRule {
+                    tags: vec!["work".into()],
+                    path_glob: Some(format!("{}/work/*", root.display())),
+                    ..TagRule::default()
+                },
+            ],
+            vec!["mine".into()],
+        )?;
+        let mut repo = RepoRow {
+            id: 1,
+            path: app.to_string_lossy().to_string(),
+            name: "app".into(),
+            default_branch: Some("main".into()),
+            last_commit_ts: None,
+            last_scan_ts: 0,
+            readme_excerpt: None,
+            origin_url: Some("git@github.com:Acme/app.git".into()),
+            last_access_ts: None,
+            kind: "normal".into(),
+            parent_path: None,
+            removed_ts: None,
+            removed_reason: None,
+            language: Some("Rust".into()),
+            pinned: false,
+            archived: false,
+        };
+        assert_eq!(rules.tags_for(&repo), vec!["acme", "docker", "mine", "rust", "work"]);
+
+        repo.origin_url = Some("https://gitlab.com/acme/app".into());
+        repo.language = Some("Go".into());
+        repo.path = root.join("work/app/nested").to_string_lossy().to_string();
+        assert_eq!(rules.tags_for(&repo), vec!["mine"]);
+
+        let unconditional = TagRule {
+            tags: vec!["x".into()],
+            ..TagRule::default()
+        };
+        assert!(TagRules::compile(&[unconditional], Vec::new()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+//! Unicode helpers for search: lowercasing that goes beyond ASCII, splitting CJK text
+//! into one word per character for the full-text indexes, and cutting text on grapheme
+//! boundaries.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Put between CJK characters of text going into a full-text index. The `unicode61`
+/// tokenizer treats it as a separator, so every character becomes its own token and a
+/// query phrase of consecutive characters matches inside a longer sentence.
+pub const CJK_SEPARATOR: char = '\u{200B}';
+
+/// Lowercase form of `s` for case-insensitive comparisons. SQLite's `lower()` and `LIKE`
+/// only handle ASCII; this lowercases `É`, `Ä`, `Σ` and friends too. It is not full case
+/// folding, so `STRASSE` and `straße` stay different.
+pub fn fold(s: &str) -> String {
+    s.to_lowercase()
+}
+
+/// Han, kana and Hangul: scripts written without spaces between words.
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0x20000..=0x3134F
+    )
+}
+
+/// Separates every CJK grapheme from its neighbours with [`CJK_SEPARATOR`]. Works on
+/// grapheme clusters, so a character keeps its variation selector.
+pub fn split_cjk(s: &str) -> String {
+    if !s.chars().any(is_cjk) {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len() + s.len() / 2);
+    let mut prev_cjk = false;
+    for g in s.graphemes(true) {
+        let cjk = g.chars().next().is_some_and(is_cjk);
+        if (cjk || prev_cjk) && !out.is_empty() {
+            out.push(CJK_SEPARATOR);
+        }
+        out.push_str(g);
+        prev_cjk = cjk;
+    }
+    out
+}
+
+/// At most the first `max` grapheme clusters of `s`, so a cut never splits an emoji,
+/// an accent from its letter or a Hangul syllable.
+pub fn take_graphemes(s: &str, max: usize) -> &str {
+    match s.grapheme_indices(true).nth(max) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_splits_and_cuts_unicode() {
+        assert_eq!(fold("ÉCOLE Straße ΣΟΦΙΑ"), "école straße σοφια");
+        assert_eq!(split_cjk("修复bug 的问题"), "修\u{200B}复\u{200B}bug \u{200B}的\u{200B}问\u{200B}题");
+        assert_eq!(split_cjk("plain text"), "plain text");
+        // A variation selector stays with its character.
+        assert_eq!(split_cjk("葛\u{E0100}飾"), "葛\u{E0100}\u{200B}飾");
+        assert_eq!(take_graphemes("ne\u{301}e", 2), "ne\u{301}");
+        assert_eq!(take_graphemes("👩‍💻 dev", 1), "👩‍💻");
+        assert_eq!(take_graphemes("短", 5), "短");
+    }
+}
@@ -0,0 +1,709 @@
+//! `coderoom tui`: a full-screen browser over the index. The UI is drawn on stderr so the
+//! path of the repo picked with Enter can be printed on stdout (`cd "$(coderoom tui)"`).
+
+use crate::{commits, dates, db, text};
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+
+/// Commits loaded per page in the commit browser.
+const COMMIT_PAGE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Repos,
+    Tags,
+    Branches,
+    Commits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Input {
+    None,
+    Filter,
+    Tag,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    Continue,
+    Quit,
+    Open(String),
+    Copy(String),
+}
+
+struct App<'a> {
+    db: &'a db::Db,
+    /// Repos passing the tag filter, in listing order (pinned first, then by name).
+    repos: Vec<db::RepoWithTags>,
+    /// Indices into `repos` matching `query`, best first.
+    visible: Vec<usize>,
+    repo_state: ListState,
+    query: String,
+    tags: Vec<db::TagCount>,
+    tag_state: ListState,
+    selected_tags: BTreeSet<String>,
+    /// Selected tags combine with OR instead of AND.
+    any_tags: bool,
+    focus: Focus,
+    input: Input,
+    tag_input: String,
+    status: String,
+    /// Repo whose branches and commits are being browsed.
+    browsing: Option<db::RepoWithTags>,
+    branches: Vec<commits::Branch>,
+    branch_state: ListState,
+    commits: Vec<commits::CommitSummary>,
+    commit_state: ListState,
+    commits_ref: Option<String>,
+    commits_more: bool,
+}
+
+/// Runs the UI until the user quits (`None`) or picks a repo to open (its path).
+pub fn run(db: &db::Db) -> Result<Option<String>> {
+    let mut app = App::new(db)?;
+
+    enable_raw_mode()?;
+    let _guard = TerminalGuard;
+    let mut stderr = std::io::stderr();
+    execute!(stderr, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stderr()))?;
+
+    loop {
+        terminal.draw(|f| draw(f, &mut app))?;
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.on_key(key) {
+            Ok(Action::Continue) => {}
+            Ok(Action::Quit) => return Ok(None),
+            Ok(Action::Open(path)) => return Ok(Some(path)),
+            Ok(Action::Copy(text)) => {
+                copy_to_clipboard(&text)?;
+                app.status = format!("copied {text}");
+            }
+            Err(e) => app.status = format!("error: {e:#}"),
+        }
+    }
+}
+
+/// Leaves the alternate screen and raw mode however `run` exits.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(std::io::stderr(), LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+    }
+}
+
+/// Sets the system clipboard through the terminal (OSC 52), which also works over SSH.
+fn copy_to_clipboard(text: &str) -> Result<()> {
+    let mut stderr = std::io::stderr();
+    write!(stderr, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stderr.flush()?;
+    Ok(())
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Moves a list selection by `delta`, clamped to `len` items.
+fn step(state: &mut ListState, len: usize, delta: isize) {
+    if len == 0 {
+        state.select(None);
+        return;
+    }
+    let cur = state.selected().unwrap_or(0) as isize;
+    state.select(Some((cur + delta).clamp(0, len as isize - 1) as usize));
+}
+
+impl<'a> App<'a> {
+    fn new(db: &'a db::Db) -> Result<Self> {
+        let mut app = App {
+            db,
+            repos: Vec::new(),
+            visible: Vec::new(),
+            repo_state: ListState::default(),
+            query: String::new(),
+            tags: Vec::new(),
+            tag_state: ListState::default(),
+            selected_tags: BTreeSet::new(),
+            any_tags: false,
+            focus: Focus::Repos,
+            input: Input::None,
+            tag_input: String::new(),
+            status: String::new(),
+            browsing: None,
+            branches: Vec::new(),
+            branch_state: ListState::default(),
+            commits: Vec::new(),
+            commit_state: ListState::default(),
+            commits_ref: None,
+            commits_more: false,
+        };
+        app.reload()?;
+        Ok(app)
+    }
+
+    fn selected_repo(&self) -> Option<&db::RepoWithTags> {
+        let i = *self.visible.get(self.repo_state.selected()?)?;
+        self.repos.get(i)
+    }
+
+    /// Reloads tags and repos from the DB, keeping the selected repo when it is still listed.
+    fn reload(&mut self) -> Result<()> {
+        let keep = self.selected_repo().map(|r| r.repo.path.clone());
+        self.tags = self.db.list_tags_with_count()?;
+        self.selected_tags.retain(|t| self.tags.iter().any(|c| &c.name == t));
+        step(&mut self.tag_state, self.tags.len(), 0);
+
+        let tags: Vec<String> = self.selected_tags.iter().cloned().collect();
+        let filter = if self.any_tags {
+            db::RepoFilter { any: tags, ..db::RepoFilter::default() }
+        } else {
+            db::RepoFilter { all: tags, ..db::RepoFilter::default() }
+        };
+        self.repos.clear();
+        for page in 1.. {
+            let paged = self.db.list_repos_with_tags_paged(&filter, false, page, 200)?;
+            let done = paged.items.is_empty() || self.repos.len() + paged.items.len() >= paged.total;
+            self.repos.extend(paged.items);
+            if done {
+                break;
+            }
+        }
+        self.refilter(keep.as_deref())
+    }
+
+    /// Recomputes `visible` for the current query: fuzzy match plus frecency, the same
+    /// ranking as `coderoom open`.
+    fn refilter(&mut self, keep: Option<&str>) -> Result<()> {
+        if self.query.trim().is_empty() {
+            self.visible = (0..self.repos.len()).collect();
+        } else {
+            let by_path: HashMap<&str, usize> = self
+                .repos
+                .iter()
+                .enumerate()
+                .map(|(i, r)| (r.repo.path.as_str(), i))
+                .collect();
+            self.visible = self
+                .db
+                .rank_repos(&self.query, usize::MAX)?
+                .iter()
+                .filter_map(|r| by_path.get(r.path.as_str()).copied())
+                .collect();
+        }
+        let pos = keep.and_then(|p| self.visible.iter().position(|&i| self.repos[i].repo.path == p));
+        self.repo_state.select(if self.visible.is_empty() { None } else { Some(pos.unwrap_or(0)) });
+        Ok(())
+    }
+
+    fn on_key(&mut self, key: KeyEvent) -> Result<Action> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Ok(Action::Quit);
+        }
+        match self.input {
+            Input::Filter => return self.on_filter_key(key),
+            Input::Tag => return self.on_tag_key(key),
+            Input::None => {}
+        }
+        self.status.clear();
+        match self.focus {
+            Focus::Repos => self.on_repos_key(key),
+            Focus::Tags => self.on_tags_key(key),
+            Focus::Branches | Focus::Commits => self.on_commits_key(key),
+        }
+    }
+
+    fn on_filter_key(&mut self, key: KeyEvent) -> Result<Action> {
+        match key.code {
+            KeyCode::Enter | KeyCode::Down => self.input = Input::None,
+            KeyCode::Esc => {
+                self.input = Input::None;
+                self.query.clear();
+                self.refilter(None)?;
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.refilter(None)?;
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.query.clear();
+                self.refilter(None)?;
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.refilter(None)?;
+            }
+            _ => {}
+        }
+        Ok(Action::Continue)
+    }
+
+    /// `tag` adds a tag to the selected repo, `-tag` removes it.
+    fn on_tag_key(&mut self, key: KeyEvent) -> Result<Action> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input = Input::None;
+                self.tag_input.clear();
+            }
+            KeyCode::Backspace => {
+                self.tag_input.pop();
+            }
+            KeyCode::Char(c) => self.tag_input.push(c),
+            KeyCode::Enter => {
+                self.input = Input::None;
+                let input = std::mem::take(&mut self.tag_input);
+                let Some(path) = self.selected_repo().map(|r| r.repo.path.clone()) else {
+                    return Ok(Action::Continue);
+                };
+                match input.trim().strip_prefix('-') {
+                    Some(tag) => {
+                        self.db.remove_tag_from_repo(&path, tag.trim())?;
+                        self.status = format!("removed tag {}", tag.trim());
+                    }
+                    None if input.trim().is_empty() => return Ok(Action::Continue),
+                    None => {
+                        self.db.add_tag_to_repo(&path, &input)?;
+                        self.status = format!("added tag {}", input.trim());
+                    }
+                }
+                self.reload()?;
+            }
+            _ => {}
+        }
+        Ok(Action::Continue)
+    }
+
+    fn on_repos_key(&mut self, key: KeyEvent) -> Result<Action> {
+        let len = self.visible.len();
+        match key.code {
+            KeyCode::Char('q') => return Ok(Action::Quit),
+            KeyCode::Esc if self.query.is_empty() => return Ok(Action::Quit),
+            KeyCode::Esc => {
+                self.query.clear();
+                self.refilter(None)?;
+            }
+            KeyCode::Up | KeyCode::Char('k') => step(&mut self.repo_state, len, -1),
+            KeyCode::Down | KeyCode::Char('j') => step(&mut self.repo_state, len, 1),
+            KeyCode::PageUp => step(&mut self.repo_state, len, -10),
+            KeyCode::PageDown => step(&mut self.repo_state, len, 10),
+            KeyCode::Home | KeyCode::Char('g') => step(&mut self.repo_state, len, -(len as isize)),
+            KeyCode::End | KeyCode::Char('G') => step(&mut self.repo_state, len, len as isize),
+            KeyCode::Char('/') => self.input = Input::Filter,
+            KeyCode::Tab => self.focus = Focus::Tags,
+            KeyCode::Char('t') if self.selected_repo().is_some() => self.input = Input::Tag,
+            KeyCode::Enter | KeyCode::Char('o') => {
+                if let Some(r) = self.selected_repo() {
+                    return Ok(Action::Open(r.repo.path.clone()));
+                }
+            }
+            KeyCode::Char('y') => {
+                if let Some(r) = self.selected_repo() {
+                    return Ok(Action::Copy(r.repo.path.clone()));
+                }
+            }
+            KeyCode::Char('c') => {
+                if let Some(r) = self.selected_repo().cloned() {
+                    self.open_commits(r)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(Action::Continue)
+    }
+
+    fn on_tags_key(&mut self, key: KeyEvent) -> Result<Action> {
+        let len = self.tags.len();
+        match key.code {
+            KeyCode::Char('q') => return Ok(Action::Quit),
+            KeyCode::Tab | KeyCode::Esc => self.focus = Focus::Repos,
+            KeyCode::Up | KeyCode::Char('k') => step(&mut self.tag_state, len, -1),
+            KeyCode::Down | KeyCode::Char('j') => step(&mut self.tag_state, len, 1),
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                if let Some(t) = self.tag_state.selected().and_then(|i| self.tags.get(i)) {
+                    let name = t.name.clone();
+                    if !self.selected_tags.remove(&name) {
+                        self.selected_tags.insert(name);
+                    }
+                    self.reload()?;
+                }
+            }
+            KeyCode::Char('m') => {
+                self.any_tags = !self.any_tags;
+                self.reload()?;
+            }
+            KeyCode::Char('x') => {
+                self.selected_tags.clear();
+                self.reload()?;
+            }
+            _ => {}
+        }
+        Ok(Action::Continue)
+    }
+
+    /// Switches to the commit browser for `repo`, starting on its default branch.
+    fn open_commits(&mut self, repo: db::RepoWithTags) -> Result<()> {
+        self.branches = commits::list_branches(&repo.repo.path)?;
+        let start = repo
+            .repo
+            .default_branch
+            .as_deref()
+            .and_then(|d| self.branches.iter().position(|b| b.kind == "local" && b.name == d))
+            .unwrap_or(0);
+        self.browsing = Some(repo);
+        self.branch_state.select((!self.branches.is_empty()).then_some(start));
+        self.focus = Focus::Branches;
+        self.load_commits(false)
+    }
+
+    /// Loads the first page of the selected branch, or the next page with `more`.
+    fn load_commits(&mut self, more: bool) -> Result<()> {
+        let (Some(repo), Some(branch)) = (
+            self.browsing.as_ref(),
+            self.branch_state.selected().and_then(|i| self.branches.get(i)),
+        ) else {
+            self.commits.clear();
+            self.commits_ref = None;
+            return Ok(());
+        };
+        let offset = if more { self.commits.len() } else { 0 };
+        let (items, has_more) =
+            commits::list_commits(&repo.repo.path, &branch.refname, offset, COMMIT_PAGE)?;
+        self.commits_ref = Some(branch.refname.clone());
+        self.commits_more = has_more;
+        if !more {
+            self.commits.clear();
+            self.commit_state.select(None);
+        }
+        self.commits.extend(items);
+        if self.commit_state.selected().is_none() && !self.commits.is_empty() {
+            self.commit_state.select(Some(0));
+        }
+        Ok(())
+    }
+
+    fn on_commits_key(&mut self, key: KeyEvent) -> Result<Action> {
+        let (state, len) = match self.focus {
+            Focus::Branches => (&mut self.branch_state, self.branches.len()),
+            _ => (&mut self.commit_state, self.commits.len()),
+        };
+        let delta = match key.code {
+            KeyCode::Up | KeyCode::Char('k') => -1,
+            KeyCode::Down | KeyCode::Char('j') => 1,
+            KeyCode::PageUp => -10,
+            KeyCode::PageDown => 10,
+            _ => 0,
+        };
+        if delta != 0 {
+            step(state, len, delta);
+            if self.focus == Focus::Branches {
+                self.load_commits(false)?;
+            } else if self.commits_more && self.commit_state.selected() == Some(len.saturating_sub(1)) {
+                self.load_commits(true)?;
+            }
+            return Ok(Action::Continue);
+        }
+        match key.code {
+            KeyCode::Char('q') => return Ok(Action::Quit),
+            KeyCode::Esc | KeyCode::Char('c') => {
+                self.browsing = None;
+                self.focus = Focus::Repos;
+            }
+            KeyCode::Tab | KeyCode::Left | KeyCode::Right => {
+                self.focus = if self.focus == Focus::Branches { Focus::Commits } else { Focus::Branches };
+            }
+            KeyCode::Char('y') => {
+                if let Some(c) = self.commit_state.selected().and_then(|i| self.commits.get(i)) {
+                    return Ok(Action::Copy(c.oid.clone()));
+                }
+            }
+            KeyCode::Enter | KeyCode::Char('o') => {
+                if let Some(r) = &self.browsing {
+                    return Ok(Action::Open(r.repo.path.clone()));
+                }
+            }
+            _ => {}
+        }
+        Ok(Action::Continue)
+    }
+}
+
+fn pane(title: String, focused: bool) -> Block<'static> {
+    let block = Block::bordered().title(title);
+    if focused {
+        block.border_style(Style::new().fg(Color::Cyan))
+    } else {
+        block
+    }
+}
+
+fn draw(f: &mut Frame, app: &mut App<'_>) {
+    let [top, body, bottom] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(f.area());
+
+    let prompt = match app.input {
+        Input::Tag => Line::from(vec![
+            "tag (-tag removes): ".bold(),
+            Span::raw(app.tag_input.clone()),
+            "█".into(),
+        ]),
+        _ => {
+            let mut spans = vec!["/".bold(), Span::raw(app.query.clone())];
+            if app.input == Input::Filter {
+                spans.push("█".into());
+            }
+            spans.push(format!("  {} of {} repos", app.visible.len(), app.repos.len()).dark_gray());
+            if !app.selected_tags.is_empty() {
+                let join = if app.any_tags { " | " } else { " & " };
+                let tags: Vec<&str> = app.selected_tags.iter().map(String::as_str).collect();
+                spans.push(format!("  tags: {}", tags.join(join)).yellow());
+            }
+            Line::from(spans)
+        }
+    };
+    f.render_widget(Paragraph::new(prompt), top);
+
+    let help = match app.focus {
+        _ if !app.status.is_empty() => app.status.clone(),
+        Focus::Repos => {
+            "/ filter  ↑↓ move  enter open  t tag  y copy path  c commits  tab tags  q quit".into()
+        }
+        Focus::Tags => "↑↓ move  space toggle  m and/or  x clear  tab repos  q quit".into(),
+        Focus::Branches | Focus::Commits => {
+            "↑↓ move  tab branches/commits  y copy oid  enter open repo  esc back  q quit".into()
+        }
+    };
+    f.render_widget(Paragraph::new(help).dark_gray(), bottom);
+
+    if app.browsing.is_some() {
+        draw_commits(f, app, body);
+        return;
+    }
+
+    let [tags_area, repos_area, detail_area] = Layout::horizontal([
+        Constraint::Percentage(20),
+        Constraint::Percentage(40),
+        Constraint::Percentage(40),
+    ])
+    .areas(body);
+
+    let tag_items: Vec<ListItem> = app
+        .tags
+        .iter()
+        .map(|t| {
+            let mark = if app.selected_tags.contains(&t.name) { "[x]" } else { "[ ]" };
+            ListItem::new(format!("{mark} {} ({})", t.name, t.total))
+        })
+        .collect();
+    let tags = List::new(tag_items)
+        .block(pane("Tags".into(), app.focus == Focus::Tags))
+        .highlight_style(Style::new().reversed());
+    f.render_stateful_widget(tags, tags_area, &mut app.tag_state);
+
+    let repo_items: Vec<ListItem> = app
+        .visible
+        .iter()
+        .map(|&i| {
+            let r = &app.repos[i].repo;
+            let mut line = vec![Span::raw(if r.pinned { "★ " } else { "  " }), Span::raw(r.name.clone())];
+            if let Some(lang) = &r.language {
+                line.push(format!("  {lang}").dark_gray());
+            }
+            ListItem::new(Line::from(line))
+        })
+        .collect();
+    let repos = List::new(repo_items)
+        .block(pane("Repos".into(), app.focus == Focus::Repos))
+        .highlight_style(Style::new().reversed());
+    f.render_stateful_widget(repos, repos_area, &mut app.repo_state);
+
+    let detail = match app.selected_repo() {
+        Some(r) => detail_lines(app, r),
+        None => vec![Line::from("no repos")],
+    };
+    f.render_widget(
+        Paragraph::new(detail).wrap(Wrap { trim: false }).block(pane("Detail".into(), false)),
+        detail_area,
+    );
+}
+
+fn detail_lines(app: &App<'_>, r: &db::RepoWithTags) -> Vec<Line<'static>> {
+    let repo = &r.repo;
+    let field = |k: &str, v: String| Line::from(vec![format!("{k:<12}").bold(), Span::raw(v)]);
+    let mut lines = vec![Line::from(repo.name.clone().bold()), field("path", repo.path.clone())];
+    if repo.kind != "normal" {
+        lines.push(field("kind", repo.kind.clone()));
+    }
+    if let Some(v) = &repo.origin_url {
+        lines.push(field("origin", v.clone()));
+    }
+    if let Some(v) = &repo.default_branch {
+        lines.push(field("branch", v.clone()));
+    }
+    if let Some(v) = &repo.language {
+        lines.push(field("language", v.clone()));
+    }
+    if let Some(ts) = repo.last_commit_ts {
+        lines.push(field("last commit", dates::format_ts(ts)));
+    }
+    if let Some(ts) = repo.last_access_ts {
+        lines.push(field("last open", dates::format_ts(ts)));
+    }
+    if !r.tags.is_empty() {
+        lines.push(field("tags", r.tags.join(", ")));
+    }
+    let mut flags = Vec::new();
+    if repo.pinned {
+        flags.push("pinned");
+    }
+    if repo.archived {
+        flags.push("archived");
+    }
+    if !flags.is_empty() {
+        lines.push(field("flags", flags.join(", ")));
+    }
+    if let Some(excerpt) = repo.readme_excerpt.as_deref().filter(|s| !s.trim().is_empty()) {
+        lines.push(Line::default());
+        lines.push(Line::from("README".bold()));
+        lines.extend(excerpt.lines().map(|l| Line::from(l.to_string())));
+    }
+    if let Ok(Some(note)) = app.db.repo_note(&repo.path) {
+        lines.push(Line::default());
+        lines.push(Line::from("Note".bold()));
+        lines.extend(note.lines().map(|l| Line::from(l.to_string())));
+    }
+    lines
+}
+
+fn draw_commits(f: &mut Frame, app: &mut App<'_>, body: Rect) {
+    let [branches_area, commits_area] =
+        Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)]).areas(body);
+    let name = app.browsing.as_ref().map(|r| r.repo.name.clone()).unwrap_or_default();
+
+    let branch_items: Vec<ListItem> = app
+        .branches
+        .iter()
+        .map(|b| {
+            let kind = if b.kind == "remote" { "remote ".dark_gray() } else { Span::raw("") };
+            ListItem::new(Line::from(vec![kind, Span::raw(b.name.clone())]))
+        })
+        .collect();
+    let branches = List::new(branch_items)
+        .block(pane(format!("{name} · branches"), app.focus == Focus::Branches))
+        .highlight_style(Style::new().reversed());
+    f.render_stateful_widget(branches, branches_area, &mut app.branch_state);
+
+    let commit_items: Vec<ListItem> = app
+        .commits
+        .iter()
+        .map(|c| {
+            ListItem::new(Line::from(vec![
+                c.oid.chars().take(8).collect::<String>().yellow(),
+                format!(" {} ", dates::format_ts(c.time)).dark_gray(),
+                format!("{:<16} ", text::take_graphemes(&c.author, 16)).cyan(),
+                Span::raw(c.summary.clone()),
+            ]))
+        })
+        .collect();
+    let more = if app.commits_more { "+" } else { "" };
+    let title = format!(
+        "{} · {}{more} commits",
+        app.commits_ref.as_deref().unwrap_or("-"),
+        app.commits.len()
+    );
+    let commits = List::new(commit_items)
+        .block(pane(title, app.focus == Focus::Commits))
+        .highlight_style(Style::new().reversed());
+    f.render_stateful_widget(commits, commits_area, &mut app.commit_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use tempfile::tempdir;
+
+    fn key(c: KeyCode) -> KeyEvent {
+        KeyEvent::new(c, KeyModifiers::NONE)
+    }
+
+    fn typed(app: &mut App<'_>, s: &str) -> Result<()> {
+        for c in s.chars() {
+            app.on_key(key(KeyCode::Char(c)))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn filter_tag_and_open_from_keys() -> Result<()> {
+        let dir = tempdir()?;
+        let db = db::Db::open(&dir.path().join("t.db"))?;
+        db.init_schema()?;
+        for name in ["api", "payments-api", "web-frontend"] {
+            db.upsert_repos(&[db::RepoMeta {
+                readme_excerpt: Some(format!("{name} readme")),
+                ..db::tests::repo(&format!("/src/{name}"), name)
+            }])?;
+        }
+        let mut app = App::new(&db)?;
+        assert_eq!(app.visible.len(), 3);
+
+        app.on_key(key(KeyCode::Char('/')))?;
+        typed(&mut app, "wfe")?;
+        app.on_key(key(KeyCode::Enter))?;
+        assert_eq!(app.selected_repo().map(|r| r.repo.name.as_str()), Some("web-frontend"));
+
+        app.on_key(key(KeyCode::Char('t')))?;
+        typed(&mut app, "team/web")?;
+        app.on_key(key(KeyCode::Enter))?;
+        assert_eq!(app.db.list_repo_tags("/src/web-frontend")?, vec!["team/web"]);
+
+        // Clear the query, then filter by the `team` namespace from the tag panel.
+        app.on_key(key(KeyCode::Esc))?;
+        assert_eq!(app.visible.len(), 3);
+        app.on_key(key(KeyCode::Tab))?;
+        app.on_key(key(KeyCode::Char(' ')))?;
+        assert_eq!(app.selected_tags.iter().next().map(String::as_str), Some("team"));
+        assert_eq!(app.visible.len(), 1);
+        app.on_key(key(KeyCode::Tab))?;
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 20))?;
+        terminal.draw(|f| draw(f, &mut app))?;
+        let screen: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+        assert!(screen.contains("web-frontend readme"));
+
+        assert_eq!(app.on_key(key(KeyCode::Char('y')))?, Action::Copy("/src/web-frontend".into()));
+        assert_eq!(app.on_key(key(KeyCode::Enter))?, Action::Open("/src/web-frontend".into()));
+        assert_eq!(base64(b"/src/a"), "L3NyYy9h");
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{commits, config, db, scan};
+use anyhow::{bail, Context, Result};
+use notify::event::{CreateKind, ModifyKind, RemoveKind};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Quiet period after the last relevant event before a batch is applied.
+pub const DEFAULT_DEBOUNCE_MS: u64 = 1500;
+/// A tree that never goes quiet (a running build, a long clone) is still applied this often.
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    pub debounce: Duration,
+    /// Rebuild the commit index of repos whose refs changed.
+    pub commit_index: bool,
+}
+
+/// Watches every configured root and keeps the index up to date until the watcher goes
+/// away. `log` receives one line per applied change. Edits to the config file are picked
+/// up with the next batch: roots are watched or dropped to match (a new root is read in
+/// full), and new ignore patterns and tag rules apply to every repo re-read from then on.
+pub fn run(cfg_path: &Path, db_path: &Path, opts: &WatchOptions, log: &dyn Fn(&str)) -> Result<()> {
+    let mut cfg = config::Config::load_or_create(cfg_path)?;
+    let mut roots = watched_roots(&cfg);
+    if roots.is_empty() {
+        bail!("no roots to watch; use `coderoom roots add <dir>` first");
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    for root in &roots {
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .with_context(|| format!("watch {}", root.display()))?;
+        log(&format!("Watching {}", root.display()));
+    }
+    // Watch the directory rather than the file, so a config replaced by a rename is seen too.
+    let cfg_path = std::fs::canonicalize(cfg_path).unwrap_or_else(|_| cfg_path.to_path_buf());
+    if let Some(dir) = cfg_path.parent() {
+        if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            log(&format!("watch {} failed, config edits need a restart: {e}", cfg_path.display()));
+        }
+    }
+
+    let db = db::Db::open(db_path)?;
+    db.init_schema()?;
+    let mut pending = BTreeSet::<PathBuf>::new();
+    let mut config_changed = false;
+    let mut first_event: Option<Instant> = None;
+    let mut last_event = Instant::now();
+    loop {
+        if let Some(first) = first_event {
+            if last_event.elapsed() >= opts.debounce || first.elapsed() >= MAX_BATCH_DELAY {
+                first_event = None;
+                // A config that is missing or half-written keeps the previous one in effect.
+                if std::mem::take(&mut config_changed) && cfg_path.is_file() {
+                    match config::Config::load_or_create(&cfg_path) {
+                        Ok(new_cfg) => {
+                            let new_roots = watched_roots(&new_cfg);
+                            for root in roots.iter().filter(|r| !new_roots.contains(r)) {
+                                let _ = watcher.unwatch(root);
+                                log(&format!("Stopped watching {}", root.display()));
+                            }
+                            for root in new_roots.iter().filter(|r| !roots.contains(r)) {
+                                match watcher.watch(root, RecursiveMode::Recursive) {
+                                    Ok(()) => {
+                                        log(&format!("Watching {}", root.display()));
+                                        pending.insert(root.clone());
+                                    }
+                                    Err(e) => log(&format!("watch {} failed: {e}", root.display())),
+                                }
+                            }
+                            cfg = new_cfg;
+                            roots = new_roots;
+                            log(&format!("Reloaded {}", cfg_path.display()));
+                        }
+                        Err(e) => log(&format!("config reload failed, keeping the previous one: {e:#}")),
+                    }
+                }
+                let batch = std::mem::take(&mut pending);
+                if let Err(e) = apply_batch(&db, &cfg, &roots, batch, opts, log) {
+                    log(&format!("update failed: {e:#}"));
+                }
+            }
+        }
+        let timeout = match first_event {
+            Some(first) => opts
+                .debounce
+                .saturating_sub(last_event.elapsed())
+                .min(MAX_BATCH_DELAY.saturating_sub(first.elapsed())),
+            None => Duration::from_secs(3600),
+        };
+        match rx.recv_timeout(timeout) {
+            Ok(Ok(event)) => {
+                let mut relevant = false;
+                if !matches!(event.kind, EventKind::Access(_)) && event.paths.contains(&cfg_path) {
+                    config_changed = true;
+                    relevant = true;
+                }
+                for path in &event.paths {
+                    if let Some(target) = watch_target(path, &event.kind) {
+                        pending.insert(target);
+                        relevant = true;
+                    }
+                }
+                if relevant {
+                    first_event.get_or_insert_with(Instant::now);
+                    last_event = Instant::now();
+                }
+            }
+            Ok(Err(e)) => log(&format!("watch error: {e}")),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+/// Configured roots that exist, canonicalized so they compare with event paths.
+fn watched_roots(cfg: &config::Config) -> Vec<PathBuf> {
+    cfg.roots
+        .iter()
+        .map(|r| std::fs::canonicalize(r).unwrap_or_else(|_| PathBuf::from(r)))
+        .filter(|r| r.is_dir())
+        .collect()
+}
+
+/// Re-reads the collected targets, grouped by the root they belong to.
+fn apply_batch(
+    db: &db::Db,
+    cfg: &config::Config,
+    roots: &[PathBuf],
+    targets: BTreeSet<PathBuf>,
+    opts: &WatchOptions,
+    log: &dyn Fn(&str),
+) -> Result<()> {
+    // Sorted order puts a directory right before its descendants, which it already covers.
+    let mut by_root = BTreeMap::<&Path, Vec<PathBuf>>::new();
+    let mut covered: Option<PathBuf> = None;
+    for target in targets {
+        if covered.as_ref().is_some_and(|c| target.starts_with(c)) {
+            continue;
+        }
+        covered = Some(target.clone());
+        let root = roots
+            .iter()
+            .filter(|r| target.starts_with(r))
+            .max_by_key(|r| r.as_os_str().len());
+        if let Some(root) = root {
+            by_root.entry(root.as_path()).or_default().push(target);
+        }
+    }
+
+    for (root, targets) in by_root {
+        let ignores = cfg.ignore_rules_for(root);
+        let rules = cfg.tag_rules_for(root)?;
+        let out = scan::refresh_paths(db, root, &targets, &ignores, &rules)?;
+        for c in &out.changes {
+            log(&format!("{}\t{}\t{}", c.kind, c.name, c.path));
+        }
+        for e in &out.errors {
+            log(&format!("error\t{}\t{}\t{}", e.kind, e.path, e.message));
+        }
+        if !opts.commit_index {
+            continue;
+        }
+
+        // Repos whose refs were touched, plus repos that just (re)appeared.
+        let mut reindex = Vec::new();
+        for t in &targets {
+            if let Some(path) = db.resolve_repo_path(&t.to_string_lossy())? {
+                reindex.push(path);
+            }
+        }
+        reindex.extend(
+            out.changes
+                .iter()
+                .filter(|c| matches!(c.kind.as_str(), "new" | "moved" | "restored"))
+                .map(|c| c.path.clone()),
+        );
+        let mut seen = HashSet::new();
+        for path in reindex.into_iter().filter(|p| seen.insert(p.clone())) {
+            let (branch_limit, commit_limit) = cfg.commit_index_limits(&path);
+            let (branches, commits) =
+                commits::build_commit_index_for_repo(&path, branch_limit, commit_limit)?;
+            db.replace_commit_index_for_repo(&path, &branches, &commits)?;
+        }
+    }
+    Ok(())
+}
+
+/// Maps a filesystem event to the directory that needs re-reading, or `None` for noise
+/// such as object writes, index updates and edits inside work trees.
+fn watch_target(path: &Path, kind: &EventKind) -> Option<PathBuf> {
+    if matches!(kind, EventKind::Access(_)) {
+        return None;
+    }
+    let components = path.components().collect::<Vec<_>>();
+    for (i, c) in components.iter().enumerate() {
+        let Component::Normal(name) = c else { continue };
+        let name = name.to_string_lossy();
+        let git_dir = components[..=i].iter().collect::<PathBuf>();
+        let repo_root = if name == ".git" {
+            git_dir.parent()?.to_path_buf()
+        } else if name.ends_with(".git") && scan::is_bare_repo_dir(&git_dir) {
+            git_dir
+        } else {
+            continue;
+        };
+        // Git writes `<ref>.lock` and renames it into place; the rename is what counts.
+        if path.extension().is_some_and(|e| e == "lock") {
+            return None;
+        }
+        return match components.get(i + 1) {
+            // The git dir (or `.git` file) itself appeared or vanished.
+            None => Some(repo_root),
+            Some(next) => match next.as_os_str().to_str() {
+                Some("HEAD" | "packed-refs" | "config" | "refs") => Some(repo_root),
+                // A linked worktree keeps its HEAD in `worktrees/<name>/`.
+                Some("worktrees") if components.len() == i + 4 && path.ends_with("HEAD") => {
+                    worktree_checkout(path.parent()?)
+                }
+                _ => None,
+            },
+        };
+    }
+
+    let name = path.file_name()?.to_string_lossy();
+    if scan::README_CANDIDATES.contains(&name.as_ref()) {
+        let parent = path.parent()?;
+        return parent.join(".git").exists().then(|| parent.to_path_buf());
+    }
+    match kind {
+        EventKind::Create(CreateKind::Folder | CreateKind::Any)
+        | EventKind::Remove(RemoveKind::Folder | RemoveKind::Any) => Some(path.to_path_buf()),
+        // Renames of plain files (editors saving via a temp file) are not interesting.
+        EventKind::Modify(ModifyKind::Name(_)) if !path.is_file() => Some(path.to_path_buf()),
+        _ => None,
+    }
+}
+
+/// Work tree of a linked worktree, from the `gitdir` file in its admin directory, which
+/// points at `<worktree>/.git`.
+fn worktree_checkout(admin_dir: &Path) -> Option<PathBuf> {
+    let gitdir = std::fs::read_to_string(admin_dir.join("gitdir")).ok()?;
+    let checkout = admin_dir.join(gitdir.trim()).parent()?.to_path_buf();
+    Some(std::fs::canonicalize(&checkout).unwrap_or(checkout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::DataChange;
+    use tempfile::tempdir;
+
+    #[test]
+    fn events_map_to_repo_roots_and_new_directories() -> Result<()> {
+        let dir = tempdir()?;
+        let repo = dir.path().join("app");
+        git2::Repository::init(&repo)?;
+        let write = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        let create_file = EventKind::Create(CreateKind::File);
+
+        let target = |rel: &str, kind: EventKind| watch_target(&dir.path().join(rel), &kind);
+        assert_eq!(target("app/.git/refs/heads/main", write), Some(repo.clone()));
+        assert_eq!(target("app/.git/HEAD", write), Some(repo.clone()));
+        assert_eq!(target("app/.git", EventKind::Remove(RemoveKind::Folder)), Some(repo.clone()));
+        assert_eq!(target("app/README.md", write), Some(repo.clone()));
+        assert_eq!(target("app/.git/refs/heads/main.lock", create_file), None);
+        assert_eq!(target("app/.git/objects/ab/cdef", create_file), None);
+        assert_eq!(target("app/.git/index", write), None);
+        assert_eq!(target("app/src/main.rs", write), None);
+        assert_eq!(
+            target("cloned", EventKind::Create(CreateKind::Folder)),
+            Some(dir.path().join("cloned"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn worktree_head_updates_map_to_the_worktree() -> Result<()> {
+        let dir = tempdir()?;
+        let root = std::fs::canonicalize(dir.path())?;
+        let repo = git2::Repository::init(root.join("app"))?;
+        let sig = git2::Signature::now("t", "t@example.com")?;
+        let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])?;
+        repo.worktree("wt", &root.join("app-wt"), None)?;
+        let write = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+
+        let target = |rel: &str| watch_target(&root.join(rel), &write);
+        assert_eq!(target("app/.git/worktrees/wt/HEAD"), Some(root.join("app-wt")));
+        assert_eq!(target("app/.git/worktrees/wt/index"), None);
+        assert_eq!(target("app/.git/worktrees/wt/logs/HEAD"), None);
+        assert_eq!(target("app/.git/worktrees/gone/HEAD"), None);
+        Ok(())
+    }
+}
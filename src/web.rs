@@ -1,23 +1,29 @@
-use crate::{commits, config, db, scan};
+use crate::{commits, config, dates, db, jobs, query, scan, tag_rules};
 use anyhow::{Context, Result};
 use axum::{
-    extract::{Query, State},
+    extract::{Path as UrlPath, Query, State},
     http::{header, StatusCode},
-    response::{Html, IntoResponse},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse,
+    },
     routing::{get, post},
     Json, Router,
 };
-use git2::{BranchType, Repository};
+use git2::Repository;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use tokio_stream::{wrappers::WatchStream, Stream, StreamExt};
 
 #[derive(Clone)]
 pub struct AppState {
     pub cfg_path: PathBuf,
     pub db_path: PathBuf,
+    pub jobs: jobs::JobManager,
 }
 
 pub async fn serve(state: AppState, host: String, port: u16) -> Result<()> {
@@ -29,14 +35,24 @@ pub async fn serve(state: AppState, host: String, port: u16) -> Result<()> {
         .route("/api/roots/pick", post(api_roots_pick))
         .route("/api/roots/add", post(api_roots_add))
         .route("/api/roots/remove", post(api_roots_remove))
+        .route("/api/roots/settings", post(api_roots_settings))
         .route("/api/ignores/add", post(api_ignores_add))
         .route("/api/ignores/remove", post(api_ignores_remove))
         .route("/api/ignores/reset", post(api_ignores_reset))
         .route("/api/scan", post(api_scan))
+        .route("/api/scan/errors", get(api_scan_errors))
+        .route("/api/scan/runs", get(api_scan_runs))
         .route("/api/prune", post(api_prune))
+        .route("/api/trash", get(api_trash))
+        .route("/api/trash/restore", post(api_trash_restore))
+        .route("/api/trash/purge", post(api_trash_purge))
         .route("/api/repos", get(api_repos))
         .route("/api/search", get(api_search))
         .route("/api/tags", get(api_tags))
+        .route("/api/tags/rename", post(api_tags_rename))
+        .route("/api/tags/merge", post(api_tags_merge))
+        .route("/api/tags/delete", post(api_tags_delete))
+        .route("/api/tags/meta", post(api_tags_meta))
         .route("/api/branches", get(api_branches))
         .route("/api/commits", get(api_commits))
         .route("/api/commit_detail", get(api_commit_detail))
@@ -45,7 +61,17 @@ pub async fn serve(state: AppState, host: String, port: u16) -> Result<()> {
         .route("/api/commit_search", get(api_commit_search))
         .route("/api/repos/tag", post(api_tag_add))
         .route("/api/repos/untag", post(api_tag_remove))
+        .route("/api/repos/notes", get(api_repo_notes))
+        .route("/api/repos/note", post(api_repo_note_set))
+        .route("/api/repos/field", post(api_repo_field_set))
+        .route("/api/repos/pin", post(api_repo_pin))
+        .route("/api/repos/archive", post(api_repo_archive))
+        .route("/api/repos/hide", post(api_repo_hide))
         .route("/api/open", post(api_open))
+        .route("/api/jobs", get(api_jobs))
+        .route("/api/jobs/:id", get(api_job))
+        .route("/api/jobs/:id/events", get(api_job_events))
+        .route("/api/jobs/:id/cancel", post(api_job_cancel))
         .with_state(state);
 
     let addr: SocketAddr = format!("{host}:{port}")
@@ -75,11 +101,15 @@ async fn app_css() -> impl IntoResponse {
 #[derive(Serialize)]
 struct RootsResponse {
     roots: Vec<String>,
+    settings: BTreeMap<String, config::RootSettings>,
 }
 
 async fn api_roots(State(state): State<AppState>) -> Result<Json<RootsResponse>, ApiError> {
     let cfg = config::Config::load_or_create(&state.cfg_path).map_err(ApiError::from)?;
-    Ok(Json(RootsResponse { roots: cfg.roots }))
+    Ok(Json(RootsResponse {
+        roots: cfg.roots,
+        settings: cfg.root_settings,
+    }))
 }
 
 #[derive(Serialize)]
@@ -123,9 +153,27 @@ async fn api_roots_remove(
     Ok(StatusCode::NO_CONTENT)
 }
 
+#[derive(Deserialize)]
+struct RootSettingsBody {
+    root: String,
+    settings: config::RootSettings,
+}
+
+async fn api_roots_settings(
+    State(state): State<AppState>,
+    Json(body): Json<RootSettingsBody>,
+) -> Result<StatusCode, ApiError> {
+    let mut cfg = config::Config::load_or_create(&state.cfg_path).map_err(ApiError::from)?;
+    cfg.set_root_settings(Path::new(&body.root), body.settings)
+        .map_err(ApiError::from)?;
+    cfg.save(&state.cfg_path).map_err(ApiError::from)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 #[derive(Deserialize)]
 struct IgnoreBody {
-    name: String,
+    name: Option<String>,
+    pattern: Option<String>,
 }
 
 async fn api_ignores_add(
@@ -133,7 +181,16 @@ async fn api_ignores_add(
     Json(body): Json<IgnoreBody>,
 ) -> Result<StatusCode, ApiError> {
     let mut cfg = config::Config::load_or_create(&state.cfg_path).map_err(ApiError::from)?;
-    cfg.add_ignore_dir_name(&body.name);
+    match (body.name, body.pattern) {
+        (_, Some(pattern)) => {
+            scan::validate_ignore_pattern(&pattern).map_err(ApiError::from)?;
+            cfg.add_ignore_pattern(&pattern);
+        }
+        (Some(name), None) => {
+            cfg.add_ignore_dir_name(&name);
+        }
+        (None, None) => return Err(ApiError::msg("name or pattern is required".to_string())),
+    }
     cfg.save(&state.cfg_path).map_err(ApiError::from)?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -143,7 +200,11 @@ async fn api_ignores_remove(
     Json(body): Json<IgnoreBody>,
 ) -> Result<StatusCode, ApiError> {
     let mut cfg = config::Config::load_or_create(&state.cfg_path).map_err(ApiError::from)?;
-    cfg.remove_ignore_dir_name(&body.name);
+    if let Some(pattern) = &body.pattern {
+        cfg.remove_ignore_pattern(pattern);
+    } else if let Some(name) = &body.name {
+        cfg.remove_ignore_dir_name(name);
+    }
     cfg.save(&state.cfg_path).map_err(ApiError::from)?;
     Ok(StatusCode::NO_CONTENT)
 }
@@ -161,81 +222,396 @@ struct ScanBody {
     all: Option<bool>,
     max_depth: Option<usize>,
     prune: Option<bool>,
+    full: Option<bool>,
 }
 
 #[derive(Serialize)]
 struct ScanResponse {
     indexed: usize,
+    skipped: usize,
     pruned: usize,
+    errors: usize,
+    changes: usize,
+}
+
+#[derive(Serialize)]
+struct JobStartedResponse {
+    job_id: u64,
+}
+
+/// Key used to stop two jobs from scanning the same root at once.
+fn root_key(root: &str) -> String {
+    std::fs::canonicalize(root)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| root.to_string())
 }
 
 async fn api_scan(
     State(state): State<AppState>,
     Json(body): Json<ScanBody>,
-) -> Result<Json<ScanResponse>, ApiError> {
+) -> Result<Json<JobStartedResponse>, ApiError> {
     let cfg_path = state.cfg_path.clone();
     let db_path = state.db_path.clone();
-    let root = body.root.clone();
+    let cfg = config::Config::load_or_create(&cfg_path).map_err(ApiError::from)?;
     let all = body.all.unwrap_or(false);
-    let max_depth = body.max_depth;
-    let prune = body.prune.unwrap_or(false);
-
-	    let out = tokio::task::spawn_blocking(move || -> Result<ScanResponse> {
-	        let mut cfg = config::Config::load_or_create(&cfg_path)?;
-	        let db = db::Db::open(&db_path)?;
-	        db.init_schema()?;
-	        let ignore_dir_names: HashSet<String> = cfg.ignore_dir_names.iter().cloned().collect();
-
-	        let mut indexed = 0usize;
-	        let mut pruned = 0usize;
-
-	        if all || root.is_none() {
-	            for r in cfg.roots.clone() {
-	                let root_path = PathBuf::from(&r);
-	                let (i, p) = scan_one_root(&db, &root_path, max_depth, prune, &ignore_dir_names)?;
-	                indexed += i;
-	                pruned += p;
-	            }
-	        } else if let Some(root) = root {
-	            let root_path = PathBuf::from(&root);
-	            let (i, p) = scan_one_root(&db, &root_path, max_depth, prune, &ignore_dir_names)?;
-	            indexed += i;
-	            pruned += p;
-	            cfg.add_root(&root_path);
-	        }
-
-        cfg.save(&cfg_path)?;
-        Ok(ScanResponse { indexed, pruned })
+    let opts = scan::ScanOptions {
+        max_depth: body.max_depth,
+        prune: body.prune.unwrap_or(false),
+        concurrency: cfg.scan_concurrency,
+        full: body.full.unwrap_or(false),
+        dry_run: false,
+        tag_rules: tag_rules::TagRules::default(),
+    };
+    let (roots, add_root) = match body.root {
+        Some(root) if !all => (vec![root_key(&root)], true),
+        _ => (cfg.roots.iter().map(|r| root_key(r)).collect::<Vec<_>>(), false),
+    };
+    let label = if add_root { roots[0].clone() } else { "all roots".to_string() };
+
+    let job_roots = roots.clone();
+    let job_id = state
+        .jobs
+        .spawn("scan", &label, job_roots, move |job| {
+            let mut cfg = config::Config::load_or_create(&cfg_path)?;
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+
+            let mut total = scan::ScanOutcome::default();
+            for root in &roots {
+                let root_opts = cfg.scan_options_for(Path::new(root), &opts)?;
+                let ignores = cfg.ignore_rules_for(Path::new(root));
+                let base = total.indexed;
+                let report = |discovered: usize, processed: usize, current: &str| {
+                    job.progress(base + discovered, base + processed, Some(current))
+                };
+                let hooks = scan::ScanHooks {
+                    cancel: Some(job.cancel_flag()),
+                    on_progress: Some(&report),
+                };
+                let out = scan::scan_root_with(&db, Path::new(root), &root_opts, &ignores, &hooks)?;
+                total.indexed += out.indexed;
+                total.skipped += out.skipped;
+                total.pruned += out.pruned;
+                total.errors.extend(out.errors);
+                total.changes.extend(out.changes);
+            }
+            if add_root {
+                cfg.add_root(Path::new(&roots[0]));
+            }
+
+            cfg.save(&cfg_path)?;
+            Ok(serde_json::to_value(ScanResponse {
+                indexed: total.indexed,
+                skipped: total.skipped,
+                pruned: total.pruned,
+                errors: total.errors.len(),
+                changes: total.changes.len(),
+            })?)
+        })
+        .map_err(ApiError::from)?;
+
+    Ok(Json(JobStartedResponse { job_id }))
+}
+
+#[derive(Serialize)]
+struct ScanErrorDto {
+    root: String,
+    path: String,
+    kind: String,
+    message: String,
+    ts: i64,
+}
+
+async fn api_scan_errors(State(state): State<AppState>) -> Result<Json<Vec<ScanErrorDto>>, ApiError> {
+    let db_path = state.db_path.clone();
+    let out = tokio::task::spawn_blocking(move || -> Result<Vec<ScanErrorDto>> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        let items = db
+            .list_scan_errors(500)?
+            .into_iter()
+            .map(|e| ScanErrorDto {
+                root: e.root,
+                path: e.path,
+                kind: e.kind,
+                message: e.message,
+                ts: e.ts,
+            })
+            .collect();
+        Ok(items)
     })
     .await
-    .map_err(|e| ApiError::msg(format!("scan join error: {e}")))?
+    .map_err(|e| ApiError::msg(format!("scan errors join error: {e}")))?
     .map_err(ApiError::from)?;
+    Ok(Json(out))
+}
+
+#[derive(Deserialize)]
+struct ScanRunsQuery {
+    since: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct RepoChangeDto {
+    path: String,
+    name: String,
+    kind: String,
+    old_value: Option<String>,
+    new_value: Option<String>,
+    detail: Option<String>,
+}
 
+#[derive(Serialize)]
+struct ScanRunDto {
+    id: i64,
+    root: String,
+    started_ts: i64,
+    finished_ts: i64,
+    duration_ms: i64,
+    full: bool,
+    indexed: usize,
+    skipped: usize,
+    pruned: usize,
+    errors: usize,
+    changes: Vec<RepoChangeDto>,
+}
+
+async fn api_scan_runs(
+    State(state): State<AppState>,
+    Query(q): Query<ScanRunsQuery>,
+) -> Result<Json<Vec<ScanRunDto>>, ApiError> {
+    let db_path = state.db_path.clone();
+    let since_ts = match q.since.as_deref().filter(|s| !s.trim().is_empty()) {
+        Some(s) => dates::parse_time(s, chrono::Utc::now().timestamp()).map_err(ApiError::from)?,
+        None => 0,
+    };
+    let limit = q.limit.unwrap_or(50).clamp(1, 500);
+
+    let out = tokio::task::spawn_blocking(move || -> Result<Vec<ScanRunDto>> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        let runs = db
+            .list_scan_runs(since_ts, limit)?
+            .into_iter()
+            .map(|r| ScanRunDto {
+                id: r.id,
+                root: r.run.root,
+                started_ts: r.run.started_ts,
+                finished_ts: r.run.finished_ts,
+                duration_ms: r.run.duration_ms,
+                full: r.run.full,
+                indexed: r.run.indexed,
+                skipped: r.run.skipped,
+                pruned: r.run.pruned,
+                errors: r.run.errors,
+                changes: r
+                    .changes
+                    .into_iter()
+                    .map(|c| RepoChangeDto {
+                        path: c.path,
+                        name: c.name,
+                        kind: c.kind,
+                        old_value: c.old_value,
+                        new_value: c.new_value,
+                        detail: c.detail,
+                    })
+                    .collect(),
+            })
+            .collect();
+        Ok(runs)
+    })
+    .await
+    .map_err(|e| ApiError::msg(format!("scan runs join error: {e}")))?
+    .map_err(ApiError::from)?;
     Ok(Json(out))
 }
 
 #[derive(Serialize)]
 struct PruneResponse {
-    deleted: usize,
+    trashed: usize,
 }
 
-async fn api_prune(State(state): State<AppState>) -> Result<Json<PruneResponse>, ApiError> {
+async fn api_prune(State(state): State<AppState>) -> Result<Json<JobStartedResponse>, ApiError> {
     let db_path = state.db_path.clone();
-    let out = tokio::task::spawn_blocking(move || -> Result<PruneResponse> {
+    // Every indexed repo may be trashed, so no scan may run alongside.
+    let cfg = config::Config::load_or_create(&state.cfg_path).map_err(ApiError::from)?;
+    let roots = cfg.roots.iter().map(|r| root_key(r)).collect();
+    let job_id = state
+        .jobs
+        .spawn("prune", "missing repos", roots, move |_job| {
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
+            let trashed = db.prune_missing_paths(false)?.len();
+            Ok(serde_json::to_value(PruneResponse { trashed })?)
+        })
+        .map_err(ApiError::from)?;
+    Ok(Json(JobStartedResponse { job_id }))
+}
+
+#[derive(Serialize)]
+struct TrashItemDto {
+    path: String,
+    name: String,
+    kind: String,
+    removed_ts: Option<i64>,
+    removed_reason: Option<String>,
+    tags: Vec<String>,
+}
+
+async fn api_trash(State(state): State<AppState>) -> Result<Json<Vec<TrashItemDto>>, ApiError> {
+    let db_path = state.db_path.clone();
+    let out = tokio::task::spawn_blocking(move || -> Result<Vec<TrashItemDto>> {
         let db = db::Db::open(&db_path)?;
         db.init_schema()?;
-        let deleted = db.prune_missing_paths()?;
-        Ok(PruneResponse { deleted })
+        let mut items = Vec::new();
+        for r in db.list_trash()? {
+            let tags = db.list_repo_tags(&r.path)?;
+            items.push(TrashItemDto {
+                path: r.path,
+                name: r.name,
+                kind: r.kind,
+                removed_ts: r.removed_ts,
+                removed_reason: r.removed_reason,
+                tags,
+            });
+        }
+        Ok(items)
     })
     .await
-    .map_err(|e| ApiError::msg(format!("prune join error: {e}")))?
+    .map_err(|e| ApiError::msg(format!("trash join error: {e}")))?
     .map_err(ApiError::from)?;
     Ok(Json(out))
 }
 
+#[derive(Deserialize)]
+struct TrashBody {
+    /// Omitted on purge to empty the whole trash.
+    path: Option<String>,
+}
+
+async fn api_trash_restore(
+    State(state): State<AppState>,
+    Json(body): Json<TrashBody>,
+) -> Result<StatusCode, ApiError> {
+    let db_path = state.db_path.clone();
+    let cfg_path = state.cfg_path.clone();
+    let path = body.path.context("path is required")?;
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        let path = db.restore_repo(&path)?.context("repo not found in trash")?;
+        // A restored hidden repo is scanned again.
+        let mut cfg = config::Config::load_or_create(&cfg_path)?;
+        if cfg.remove_excluded_repo(&path) {
+            cfg.save(&cfg_path)?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| ApiError::msg(format!("trash restore join error: {e}")))?
+    .map_err(ApiError::from)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Serialize)]
+struct PurgeResponse {
+    purged: usize,
+}
+
+async fn api_trash_purge(
+    State(state): State<AppState>,
+    Json(body): Json<TrashBody>,
+) -> Result<Json<PurgeResponse>, ApiError> {
+    let db_path = state.db_path.clone();
+    let purged = tokio::task::spawn_blocking(move || -> Result<usize> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        db.purge_trash(body.path.as_deref(), None)
+    })
+    .await
+    .map_err(|e| ApiError::msg(format!("trash purge join error: {e}")))?
+    .map_err(ApiError::from)?;
+    Ok(Json(PurgeResponse { purged }))
+}
+
+async fn api_jobs(State(state): State<AppState>) -> Json<Vec<jobs::JobSnapshot>> {
+    Json(state.jobs.list())
+}
+
+async fn api_job(
+    State(state): State<AppState>,
+    UrlPath(id): UrlPath<u64>,
+) -> Result<Json<jobs::JobSnapshot>, ApiError> {
+    let job = state.jobs.get(id).context("job not found")?;
+    Ok(Json(job))
+}
+
+/// Streams job snapshots as SSE `message` events; the stream ends once the job finishes.
+async fn api_job_events(
+    State(state): State<AppState>,
+    UrlPath(id): UrlPath<u64>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let rx = state.jobs.subscribe(id).context("job not found")?;
+    let stream = WatchStream::new(rx).map(|job| {
+        Ok(Event::default()
+            .json_data(&job)
+            .unwrap_or_else(|_| Event::default().data("{}")))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Serialize)]
+struct JobCancelResponse {
+    cancelled: bool,
+}
+
+async fn api_job_cancel(
+    State(state): State<AppState>,
+    UrlPath(id): UrlPath<u64>,
+) -> Json<JobCancelResponse> {
+    Json(JobCancelResponse {
+        cancelled: state.jobs.cancel(id),
+    })
+}
+
+/// Builds a repo filter from comma-separated `tag` (all of), `any` (one of) and `not`
+/// tag lists and a `field` list of `key=value` / `key` entries.
+fn repo_filter(
+    tag: Option<&str>,
+    any: Option<&str>,
+    not: Option<&str>,
+    field: Option<&str>,
+    archived: Option<bool>,
+) -> Result<db::RepoFilter> {
+    let split = |v: Option<&str>| {
+        v.unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    Ok(db::RepoFilter {
+        all: split(tag),
+        any: split(any),
+        not: split(not),
+        fields: split(field)
+            .iter()
+            .map(|f| db::parse_field_filter(f))
+            .collect::<Result<_>>()?,
+        include_archived: archived.unwrap_or(false),
+        ..db::RepoFilter::default()
+    })
+}
+
 #[derive(Deserialize)]
 struct ReposQuery {
     tag: Option<String>,
+    any: Option<String>,
+    not: Option<String>,
+    field: Option<String>,
+    archived: Option<bool>,
     recent: Option<bool>,
     page: Option<usize>,
     per_page: Option<usize>,
@@ -252,8 +628,26 @@ struct RepoDto {
     last_access_ts: Option<i64>,
     readme_excerpt: Option<String>,
     origin_url: Option<String>,
+    kind: String,
+    parent_path: Option<String>,
+    language: Option<String>,
+    pinned: bool,
+    archived: bool,
+    worktrees: Vec<WorktreeDto>,
     tags: Vec<String>,
     matched_in: Option<Vec<String>>,
+    /// Search only: bm25 rank, lower is better.
+    rank: Option<f64>,
+    /// Search only: HTML of each matched field (name, tag, readme, notes, path), escaped
+    /// with the matched words in `<mark>`; README and notes are cut to a snippet.
+    highlights: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Serialize)]
+struct WorktreeDto {
+    path: String,
+    name: String,
+    default_branch: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -268,7 +662,14 @@ async fn api_repos(
     State(state): State<AppState>,
     Query(q): Query<ReposQuery>,
 ) -> Result<Json<PagedReposResponse>, ApiError> {
-    let tag = q.tag.clone();
+    let filter = repo_filter(
+        q.tag.as_deref(),
+        q.any.as_deref(),
+        q.not.as_deref(),
+        q.field.as_deref(),
+        q.archived,
+    )
+    .map_err(ApiError::from)?;
     let recent = q.recent.unwrap_or(false);
     let page = q.page.unwrap_or(1);
     let per_page = q.per_page.unwrap_or(25);
@@ -277,11 +678,28 @@ async fn api_repos(
     let out = tokio::task::spawn_blocking(move || -> Result<PagedReposResponse> {
         let db = db::Db::open(&db_path)?;
         db.init_schema()?;
-        let paged = db.list_repos_with_tags_paged(tag.as_deref(), recent, page, per_page)?;
+        let paged = db.list_repos_with_tags_paged(&filter, recent, page, per_page)?;
+        let parents = paged
+            .items
+            .iter()
+            .filter(|r| r.repo.kind != "worktree")
+            .map(|r| r.repo.path.clone())
+            .collect::<Vec<_>>();
+        let mut worktrees = db.worktrees_by_parent(&parents)?;
         let items = paged
             .items
             .into_iter()
             .map(|r| RepoDto {
+                worktrees: worktrees
+                    .remove(&r.repo.path)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|w| WorktreeDto {
+                        path: w.path,
+                        name: w.name,
+                        default_branch: w.default_branch,
+                    })
+                    .collect(),
                 id: r.repo.id,
                 path: r.repo.path,
                 name: r.repo.name,
@@ -291,8 +709,15 @@ async fn api_repos(
                 last_access_ts: r.repo.last_access_ts,
                 readme_excerpt: r.repo.readme_excerpt,
                 origin_url: r.repo.origin_url,
+                kind: r.repo.kind,
+                parent_path: r.repo.parent_path,
+                language: r.repo.language,
+                pinned: r.repo.pinned,
+                archived: r.repo.archived,
                 tags: r.tags,
                 matched_in: None,
+                rank: None,
+                highlights: None,
             })
             .collect::<Vec<_>>();
         Ok(PagedReposResponse {
@@ -318,6 +743,12 @@ struct SearchQuery {
     in_path: Option<bool>,
     in_readme: Option<bool>,
     in_tags: Option<bool>,
+    in_notes: Option<bool>,
+    tag: Option<String>,
+    any: Option<String>,
+    not: Option<String>,
+    field: Option<String>,
+    archived: Option<bool>,
 }
 
 async fn api_search(
@@ -325,44 +756,48 @@ async fn api_search(
     Query(q): Query<SearchQuery>,
 ) -> Result<Json<PagedReposResponse>, ApiError> {
     let db_path = state.db_path.clone();
-    let query = q.q.clone();
     let page = q.page.unwrap_or(1);
     let per_page = q.per_page.unwrap_or(25);
-    let in_name = q.in_name.unwrap_or(true);
-    let in_path = q.in_path.unwrap_or(true);
-    let in_readme = q.in_readme.unwrap_or(true);
-    let in_tags = q.in_tags.unwrap_or(true);
+    let mut search = query::parse_repo(&q.q).map_err(ApiError::from)?;
+    // `in:` in the query wins over the scope checkboxes.
+    if search.columns.is_empty() {
+        search.columns = query::scope_columns(&[
+            ("name", q.in_name.unwrap_or(true)),
+            ("tags", q.in_tags.unwrap_or(true)),
+            ("readme", q.in_readme.unwrap_or(true)),
+            ("notes", q.in_notes.unwrap_or(true)),
+            ("path", q.in_path.unwrap_or(true)),
+        ]);
+    }
+    let params = repo_filter(
+        q.tag.as_deref(),
+        q.any.as_deref(),
+        q.not.as_deref(),
+        q.field.as_deref(),
+        q.archived,
+    )
+    .map_err(ApiError::from)?;
+    search.filter.all.extend(params.all);
+    search.filter.any = params.any;
+    search.filter.not.extend(params.not);
+    search.filter.fields.extend(params.fields);
+    search.filter.include_archived = params.include_archived;
 
     let out = tokio::task::spawn_blocking(move || -> Result<PagedReposResponse> {
         let db = db::Db::open(&db_path)?;
         db.init_schema()?;
-        let paged = db.search_repos_with_tags_paged_filtered(
-            &query, in_name, in_path, in_readme, in_tags, page, per_page,
-        )?;
-        let qlow = query.to_lowercase();
+        let paged = db.search_repos_with_tags_paged_filtered(&search, page, per_page)?;
         let items = paged
             .items
             .into_iter()
             .map(|r| {
+                // Best-weighted field first; the UI labels tag hits `tag`.
                 let mut matched = Vec::<String>::new();
-                if in_name && r.repo.name.to_lowercase().contains(&qlow) {
-                    matched.push("name".to_string());
-                }
-                if in_path && r.repo.path.to_lowercase().contains(&qlow) {
-                    matched.push("path".to_string());
-                }
-                if in_readme {
-                    if let Some(ex) = &r.repo.readme_excerpt {
-                        if ex.to_lowercase().contains(&qlow) {
-                            matched.push("readme".to_string());
-                        }
-                    }
-                }
-                if in_tags && r.tags.iter().any(|t| t.to_lowercase().contains(&qlow)) {
-                    matched.push("tag".to_string());
-                }
-                if matched.is_empty() {
-                    matched.push("repo".to_string());
+                let mut highlights = BTreeMap::new();
+                for (field, text) in &r.highlights {
+                    let field = if *field == "tags" { "tag" } else { field };
+                    matched.push(field.to_string());
+                    highlights.insert(field.to_string(), marked_html(text));
                 }
                 RepoDto {
                     id: r.repo.id,
@@ -374,8 +809,16 @@ async fn api_search(
                     last_access_ts: r.repo.last_access_ts,
                     readme_excerpt: r.repo.readme_excerpt,
                     origin_url: r.repo.origin_url,
+                    kind: r.repo.kind,
+                    parent_path: r.repo.parent_path,
+                    language: r.repo.language,
+                    pinned: r.repo.pinned,
+                    archived: r.repo.archived,
+                    worktrees: Vec::new(),
                     tags: r.tags,
                     matched_in: Some(matched),
+                    rank: Some(r.rank),
+                    highlights: Some(highlights),
                 }
             })
             .collect::<Vec<_>>();
@@ -397,6 +840,9 @@ async fn api_search(
 struct TagCountDto {
     name: String,
     count: usize,
+    total: usize,
+    color: Option<String>,
+    description: Option<String>,
 }
 
 async fn api_tags(State(state): State<AppState>) -> Result<Json<Vec<TagCountDto>>, ApiError> {
@@ -407,7 +853,13 @@ async fn api_tags(State(state): State<AppState>) -> Result<Json<Vec<TagCountDto>
         let rows = db.list_tags_with_count()?;
         Ok(rows
             .into_iter()
-            .map(|(name, count)| TagCountDto { name, count })
+            .map(|t| TagCountDto {
+                name: t.name,
+                count: t.count,
+                total: t.total,
+                color: t.color,
+                description: t.description,
+            })
             .collect())
     })
     .await
@@ -421,6 +873,8 @@ struct ConfigDto {
     commit_index_branches: usize,
     commit_index_commits_per_branch: usize,
     ignore_dir_names: Vec<String>,
+    ignore_patterns: Vec<String>,
+    my_identities: Vec<String>,
 }
 
 async fn api_config(State(state): State<AppState>) -> Result<Json<ConfigDto>, ApiError> {
@@ -429,6 +883,8 @@ async fn api_config(State(state): State<AppState>) -> Result<Json<ConfigDto>, Ap
         commit_index_branches: cfg.commit_index_branches,
         commit_index_commits_per_branch: cfg.commit_index_commits_per_branch,
         ignore_dir_names: cfg.ignore_dir_names,
+        ignore_patterns: cfg.ignore_patterns,
+        my_identities: cfg.my_identities,
     }))
 }
 
@@ -450,7 +906,7 @@ struct CommitIndexRebuildResponse {
 async fn api_commit_index_rebuild(
     State(state): State<AppState>,
     Json(body): Json<CommitIndexRebuildBody>,
-) -> Result<Json<CommitIndexRebuildResponse>, ApiError> {
+) -> Result<Json<JobStartedResponse>, ApiError> {
     let cfg_path = state.cfg_path.clone();
     let db_path = state.db_path.clone();
     let repo_path = body.repo_path.clone();
@@ -458,56 +914,76 @@ async fn api_commit_index_rebuild(
     let set_branches = body.commit_index_branches;
     let set_commits = body.commit_index_commits_per_branch;
 
-    let out = tokio::task::spawn_blocking(move || -> Result<CommitIndexRebuildResponse> {
-        let mut cfg = config::Config::load_or_create(&cfg_path)?;
-        if let Some(v) = set_branches {
-            cfg.commit_index_branches = v.max(1).min(200);
-        }
-        if let Some(v) = set_commits {
-            cfg.commit_index_commits_per_branch = v.max(1).min(500);
-        }
-        cfg.save(&cfg_path)?;
+    let label = match &repo_path {
+        Some(p) if !all => p.clone(),
+        _ => "all repos".to_string(),
+    };
+    // Held against scans of the roots whose repos get re-indexed.
+    let cfg = config::Config::load_or_create(&cfg_path).map_err(ApiError::from)?;
+    let roots = match &repo_path {
+        Some(p) if !all => cfg.root_of(Path::new(p)).map(root_key).into_iter().collect(),
+        _ => cfg.roots.iter().map(|r| root_key(r)).collect(),
+    };
 
-        let db = db::Db::open(&db_path)?;
-        db.init_schema()?;
+    let job_id = state
+        .jobs
+        .spawn("commit_index", &label, roots, move |job| {
+            let mut cfg = config::Config::load_or_create(&cfg_path)?;
+            if let Some(v) = set_branches {
+                cfg.commit_index_branches = v.clamp(1, 200);
+            }
+            if let Some(v) = set_commits {
+                cfg.commit_index_commits_per_branch = v.clamp(1, 500);
+            }
+            cfg.save(&cfg_path)?;
 
-        let targets: Vec<String> = if all || repo_path.is_none() {
-            db.list_repo_paths()?
-        } else {
-            vec![repo_path.unwrap()]
-        };
+            let db = db::Db::open(&db_path)?;
+            db.init_schema()?;
 
-        let mut repos_indexed = 0usize;
-        for p in targets {
-            if !Path::new(&p).exists() {
-                continue;
+            let targets: Vec<String> = match repo_path {
+                Some(p) if !all => vec![p],
+                _ => db.list_repo_paths()?,
+            };
+
+            let mut repos_indexed = 0usize;
+            for (i, p) in targets.iter().enumerate() {
+                if job.is_cancelled() {
+                    anyhow::bail!("commit index rebuild cancelled");
+                }
+                job.progress(targets.len(), i, Some(p));
+                if !Path::new(p).exists() {
+                    continue;
+                }
+                let (branch_limit, commit_limit) = cfg.commit_index_limits(p);
+                let (branches, commits) =
+                    commits::build_commit_index_for_repo(p, branch_limit, commit_limit)?;
+                db.replace_commit_index_for_repo(p, &branches, &commits)?;
+                repos_indexed += 1;
             }
-            let (branches, commits) = commits::build_commit_index_for_repo(
-                &p,
-                cfg.commit_index_branches,
-                cfg.commit_index_commits_per_branch,
-            )?;
-            db.replace_commit_index_for_repo(&p, &branches, &commits)?;
-            repos_indexed += 1;
-        }
 
-        Ok(CommitIndexRebuildResponse {
-            repos_indexed,
-            branches: cfg.commit_index_branches,
-            commits_per_branch: cfg.commit_index_commits_per_branch,
+            Ok(serde_json::to_value(CommitIndexRebuildResponse {
+                repos_indexed,
+                branches: cfg.commit_index_branches,
+                commits_per_branch: cfg.commit_index_commits_per_branch,
+            })?)
         })
-    })
-    .await
-    .map_err(|e| ApiError::msg(format!("commit index join error: {e}")))?
-    .map_err(ApiError::from)?;
+        .map_err(ApiError::from)?;
 
-    Ok(Json(out))
+    Ok(Json(JobStartedResponse { job_id }))
 }
 
 #[derive(Deserialize)]
 struct CommitSearchQuery {
+    #[serde(default)]
     q: String,
     branch: Option<String>,
+    /// Same as `author:`, `email:`, `after:` and `before:` in `q`.
+    author: Option<String>,
+    email: Option<String>,
+    after: Option<String>,
+    before: Option<String>,
+    /// Only commits by `my_identities` (same as `is:mine`).
+    mine: Option<bool>,
     in_summary: Option<bool>,
     in_message: Option<bool>,
     page: Option<usize>,
@@ -524,8 +1000,16 @@ struct CommitHitDto {
     oid: String,
     time: Option<i64>,
     summary: Option<String>,
+    author: Option<String>,
+    email: Option<String>,
+    /// HTML: the best-matching part of the message (or the summary), escaped, with the
+    /// matched words in `<mark>`.
     snippet: Option<String>,
     matched_in: Vec<String>,
+    /// bm25 rank weighted by recency, lower is better.
+    rank: f64,
+    /// HTML of each matched field (`summary` whole, `message` cut to a snippet).
+    highlights: BTreeMap<String, String>,
 }
 
 #[derive(Serialize)]
@@ -541,25 +1025,39 @@ async fn api_commit_search(
     Query(q): Query<CommitSearchQuery>,
 ) -> Result<Json<CommitSearchResponse>, ApiError> {
     let db_path = state.db_path.clone();
-    let query = q.q.clone();
-    let branch = q.branch.clone();
-    let in_summary = q.in_summary.unwrap_or(true);
-    let in_message = q.in_message.unwrap_or(true);
+    let cfg_path = state.cfg_path.clone();
     let page = q.page.unwrap_or(1);
     let per_page = q.per_page.unwrap_or(25);
 
     let out = tokio::task::spawn_blocking(move || -> Result<CommitSearchResponse> {
+        let now = chrono::Utc::now().timestamp();
+        let me = config::Config::load_or_create(&cfg_path)?.my_identities;
+        let mut search = query::parse_commit(&q.q, now, &me)?;
+        if search.columns.is_empty() {
+            search.columns = query::scope_columns(&[
+                ("summary", q.in_summary.unwrap_or(true)),
+                ("message", q.in_message.unwrap_or(true)),
+            ]);
+        }
+        let param = |v: &Option<String>| v.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+        search.filter.branches.any.extend(param(&q.branch));
+        search.filter.authors.any.extend(param(&q.author));
+        search.filter.emails.any.extend(param(&q.email));
+        if let Some(after) = param(&q.after) {
+            let after = dates::parse_time(&after, now).map_err(|e| anyhow::anyhow!("in after: {e:#}"))?;
+            search.filter.after = Some(after);
+        }
+        if let Some(before) = param(&q.before) {
+            let before = dates::parse_time_end(&before, now).map_err(|e| anyhow::anyhow!("in before: {e:#}"))?;
+            search.filter.before = Some(before);
+        }
+        if q.mine.unwrap_or(false) {
+            query::add_mine(&mut search.filter, &me, false)?;
+        }
+
         let db = db::Db::open(&db_path)?;
         db.init_schema()?;
-        let paged = db.search_commits_paged(
-            &query,
-            branch.as_deref(),
-            in_summary,
-            in_message,
-            page,
-            per_page,
-        )?;
-        let qlow = query.to_lowercase();
+        let paged = db.search_commits_paged(&search, page, per_page)?;
         Ok(CommitSearchResponse {
             total: paged.total,
             page,
@@ -568,26 +1066,20 @@ async fn api_commit_search(
                 .items
                 .into_iter()
                 .map(|c| {
-                    let mut matched = Vec::<String>::new();
-                    if in_summary {
-                        if let Some(s) = &c.summary {
-                            if s.to_lowercase().contains(&qlow) {
-                                matched.push("summary".to_string());
-                            }
-                        }
-                    }
-                    if in_message {
-                        if let Some(m) = &c.message {
-                            if m.to_lowercase().contains(&qlow) {
-                                matched.push("message".to_string());
-                            }
-                        }
-                    }
+                    let highlights = c
+                        .highlights
+                        .iter()
+                        .map(|(field, text)| (field.to_string(), marked_html(text)))
+                        .collect::<BTreeMap<_, _>>();
+                    let mut matched = c.highlights.iter().map(|(f, _)| f.to_string()).collect::<Vec<_>>();
                     if matched.is_empty() {
                         matched.push("commit".to_string());
                     }
-
-                    let snippet = make_snippet(c.summary.as_deref(), c.message.as_deref(), &qlow);
+                    let snippet = highlights
+                        .get("message")
+                        .or_else(|| highlights.get("summary"))
+                        .cloned()
+                        .or_else(|| c.summary.as_deref().map(marked_html));
 
                     CommitHitDto {
                         repo_name: c.repo_name,
@@ -598,8 +1090,12 @@ async fn api_commit_search(
                         oid: c.oid,
                         time: c.time,
                         summary: c.summary,
+                        author: c.author,
+                        email: c.email,
                         snippet,
                         matched_in: matched,
+                        rank: c.rank,
+                        highlights,
                     }
                 })
                 .collect(),
@@ -617,53 +1113,15 @@ struct BranchesQuery {
     repo_path: String,
 }
 
-#[derive(Serialize)]
-struct BranchDto {
-    kind: String,
-    name: String,
-    refname: String,
-}
-
 async fn api_branches(
     State(_state): State<AppState>,
     Query(q): Query<BranchesQuery>,
-) -> Result<Json<Vec<BranchDto>>, ApiError> {
+) -> Result<Json<Vec<commits::Branch>>, ApiError> {
     let repo_path = q.repo_path.clone();
-    let branches = tokio::task::spawn_blocking(move || -> Result<Vec<BranchDto>> {
-        let repo =
-            Repository::open(&repo_path).with_context(|| format!("open repo {}", repo_path))?;
-        let mut out = Vec::new();
-
-        for (kind, bt) in [("local", BranchType::Local), ("remote", BranchType::Remote)] {
-            let iter = repo.branches(Some(bt))?;
-            for b in iter {
-                let (branch, _) = b?;
-                let Some(name) = branch.name()?.map(|s| s.to_string()) else {
-                    continue;
-                };
-                if kind == "remote" && (name.ends_with("/HEAD") || name == "HEAD") {
-                    continue;
-                }
-                let Some(reference) = branch.get().name().map(|s| s.to_string()) else {
-                    continue;
-                };
-                out.push(BranchDto {
-                    kind: kind.to_string(),
-                    name,
-                    refname: reference,
-                });
-            }
-        }
-
-        out.sort_by(|a, b| {
-            (a.kind.as_str(), a.name.as_str()).cmp(&(b.kind.as_str(), b.name.as_str()))
-        });
-        out.dedup_by(|a, b| a.refname == b.refname);
-        Ok(out)
-    })
-    .await
-    .map_err(|e| ApiError::msg(format!("branches join error: {e}")))?
-    .map_err(ApiError::from)?;
+    let branches = tokio::task::spawn_blocking(move || commits::list_branches(&repo_path))
+        .await
+        .map_err(|e| ApiError::msg(format!("branches join error: {e}")))?
+        .map_err(ApiError::from)?;
 
     Ok(Json(branches))
 }
@@ -676,21 +1134,12 @@ struct CommitsQuery {
     per_page: Option<usize>,
 }
 
-#[derive(Serialize)]
-struct CommitDto {
-    oid: String,
-    summary: String,
-    author: String,
-    email: String,
-    time: i64,
-}
-
 #[derive(Serialize)]
 struct CommitsResponse {
     page: usize,
     per_page: usize,
     has_more: bool,
-    items: Vec<CommitDto>,
+    items: Vec<commits::CommitSummary>,
 }
 
 async fn api_commits(
@@ -704,43 +1153,7 @@ async fn api_commits(
     let offset = (page - 1) * per_page;
 
     let out = tokio::task::spawn_blocking(move || -> Result<CommitsResponse> {
-        let repo =
-            Repository::open(&repo_path).with_context(|| format!("open repo {}", repo_path))?;
-        let obj = repo
-            .revparse_single(&refname)
-            .with_context(|| format!("resolve ref {refname}"))?;
-        let oid = obj.id();
-
-        let mut walk = repo.revwalk()?;
-        walk.set_sorting(git2::Sort::TIME)?;
-        walk.push(oid)?;
-
-        let mut items = Vec::new();
-        let mut idx = 0usize;
-        let mut has_more = false;
-
-        for oid in walk {
-            let oid = oid?;
-            if idx < offset {
-                idx += 1;
-                continue;
-            }
-            if items.len() >= per_page {
-                has_more = true;
-                break;
-            }
-            let commit = repo.find_commit(oid)?;
-            let author = commit.author();
-            items.push(CommitDto {
-                oid: oid.to_string(),
-                summary: commit.summary().unwrap_or("").to_string(),
-                author: author.name().unwrap_or("").to_string(),
-                email: author.email().unwrap_or("").to_string(),
-                time: commit.time().seconds(),
-            });
-            idx += 1;
-        }
-
+        let (items, has_more) = commits::list_commits(&repo_path, &refname, offset, per_page)?;
         Ok(CommitsResponse {
             page,
             per_page,
@@ -844,6 +1257,253 @@ async fn api_tag_remove(
     Ok(StatusCode::NO_CONTENT)
 }
 
+#[derive(Deserialize)]
+struct RepoPathQuery {
+    repo_path: String,
+}
+
+#[derive(Serialize)]
+struct RepoFieldDto {
+    key: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+struct RepoNotesResponse {
+    note: Option<String>,
+    fields: Vec<RepoFieldDto>,
+    lifecycles: &'static [&'static str],
+}
+
+async fn api_repo_notes(
+    State(state): State<AppState>,
+    Query(q): Query<RepoPathQuery>,
+) -> Result<Json<RepoNotesResponse>, ApiError> {
+    let db_path = state.db_path.clone();
+    let out = tokio::task::spawn_blocking(move || -> Result<RepoNotesResponse> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        Ok(RepoNotesResponse {
+            note: db.repo_note(&q.repo_path)?,
+            fields: db
+                .list_repo_fields(&q.repo_path)?
+                .into_iter()
+                .map(|(key, value)| RepoFieldDto { key, value })
+                .collect(),
+            lifecycles: db::LIFECYCLES,
+        })
+    })
+    .await
+    .map_err(|e| ApiError::msg(format!("notes join error: {e}")))?
+    .map_err(ApiError::from)?;
+    Ok(Json(out))
+}
+
+#[derive(Deserialize)]
+struct NoteBody {
+    repo_path: String,
+    body: String,
+}
+
+async fn api_repo_note_set(
+    State(state): State<AppState>,
+    Json(body): Json<NoteBody>,
+) -> Result<StatusCode, ApiError> {
+    let db_path = state.db_path.clone();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        db.set_repo_note(&body.repo_path, &body.body)
+    })
+    .await
+    .map_err(|e| ApiError::msg(format!("note join error: {e}")))?
+    .map_err(ApiError::from)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// An empty `value` removes the field.
+#[derive(Deserialize)]
+struct FieldBody {
+    repo_path: String,
+    key: String,
+    value: String,
+}
+
+async fn api_repo_field_set(
+    State(state): State<AppState>,
+    Json(body): Json<FieldBody>,
+) -> Result<StatusCode, ApiError> {
+    let db_path = state.db_path.clone();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        db.set_repo_field(&body.repo_path, &body.key, &body.value)
+    })
+    .await
+    .map_err(|e| ApiError::msg(format!("field join error: {e}")))?
+    .map_err(ApiError::from)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct RepoFlagBody {
+    repo_path: String,
+    on: bool,
+}
+
+async fn api_repo_pin(
+    State(state): State<AppState>,
+    Json(body): Json<RepoFlagBody>,
+) -> Result<StatusCode, ApiError> {
+    let db_path = state.db_path.clone();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        db.set_repo_pinned(&body.repo_path, body.on)
+    })
+    .await
+    .map_err(|e| ApiError::msg(format!("pin join error: {e}")))?
+    .map_err(ApiError::from)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn api_repo_archive(
+    State(state): State<AppState>,
+    Json(body): Json<RepoFlagBody>,
+) -> Result<StatusCode, ApiError> {
+    let db_path = state.db_path.clone();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        db.set_repo_archived(&body.repo_path, body.on)
+    })
+    .await
+    .map_err(|e| ApiError::msg(format!("archive join error: {e}")))?
+    .map_err(ApiError::from)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Excludes the repo from future scans (`excluded_repos` in `config.toml`) and moves it
+/// to the trash; restoring it from the trash undoes both.
+async fn api_repo_hide(
+    State(state): State<AppState>,
+    Json(body): Json<RepoPathQuery>,
+) -> Result<StatusCode, ApiError> {
+    let db_path = state.db_path.clone();
+    let cfg_path = state.cfg_path.clone();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        if !db.hide_repo(&body.repo_path)? {
+            anyhow::bail!("repo not indexed: {}", body.repo_path);
+        }
+        let mut cfg = config::Config::load_or_create(&cfg_path)?;
+        if cfg.add_excluded_repo(&body.repo_path) {
+            cfg.save(&cfg_path)?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| ApiError::msg(format!("hide join error: {e}")))?
+    .map_err(ApiError::from)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct TagMoveBody {
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize)]
+struct TagMoveResponse {
+    tags: usize,
+}
+
+/// Rename and merge also rewrite tag rules and root auto-tags in `config.toml`.
+async fn tags_move(state: AppState, body: TagMoveBody, merge: bool) -> Result<Json<TagMoveResponse>, ApiError> {
+    let db_path = state.db_path.clone();
+    let cfg_path = state.cfg_path.clone();
+    let tags = tokio::task::spawn_blocking(move || -> Result<usize> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        let n = if merge {
+            db.merge_tags(&body.from, &body.to)?
+        } else {
+            db.rename_tag(&body.from, &body.to)?
+        };
+        let mut cfg = config::Config::load_or_create(&cfg_path)?;
+        if cfg.rename_tag(&db::normalize_tag(&body.from)?, &db::normalize_tag(&body.to)?) {
+            cfg.save(&cfg_path)?;
+        }
+        Ok(n)
+    })
+    .await
+    .map_err(|e| ApiError::msg(format!("tag move join error: {e}")))?
+    .map_err(ApiError::from)?;
+    Ok(Json(TagMoveResponse { tags }))
+}
+
+async fn api_tags_rename(
+    State(state): State<AppState>,
+    Json(body): Json<TagMoveBody>,
+) -> Result<Json<TagMoveResponse>, ApiError> {
+    tags_move(state, body, false).await
+}
+
+async fn api_tags_merge(
+    State(state): State<AppState>,
+    Json(body): Json<TagMoveBody>,
+) -> Result<Json<TagMoveResponse>, ApiError> {
+    tags_move(state, body, true).await
+}
+
+#[derive(Deserialize)]
+struct TagNameBody {
+    name: String,
+}
+
+async fn api_tags_delete(
+    State(state): State<AppState>,
+    Json(body): Json<TagNameBody>,
+) -> Result<Json<TagMoveResponse>, ApiError> {
+    let db_path = state.db_path.clone();
+    let tags = tokio::task::spawn_blocking(move || -> Result<usize> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        db.delete_tag(&body.name)
+    })
+    .await
+    .map_err(|e| ApiError::msg(format!("tag delete join error: {e}")))?
+    .map_err(ApiError::from)?;
+    Ok(Json(TagMoveResponse { tags }))
+}
+
+#[derive(Deserialize)]
+struct TagMetaBody {
+    name: String,
+    color: Option<String>,
+    description: Option<String>,
+}
+
+async fn api_tags_meta(
+    State(state): State<AppState>,
+    Json(body): Json<TagMetaBody>,
+) -> Result<StatusCode, ApiError> {
+    let db_path = state.db_path.clone();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let db = db::Db::open(&db_path)?;
+        db.init_schema()?;
+        db.set_tag_color(&body.name, body.color.as_deref())?;
+        db.set_tag_description(&body.name, body.description.as_deref())?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| ApiError::msg(format!("tag meta join error: {e}")))?
+    .map_err(ApiError::from)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 #[derive(Deserialize)]
 struct OpenBody {
     repo: String,
@@ -873,30 +1533,6 @@ async fn api_open(
     Ok(Json(out))
 }
 
-fn scan_one_root(
-    db: &db::Db,
-    root: &Path,
-    max_depth: Option<usize>,
-    prune: bool,
-    ignore_dir_names: &HashSet<String>,
-) -> Result<(usize, usize)> {
-    let root = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
-    let repos = scan::discover_git_repos(&root, max_depth, ignore_dir_names)
-        .with_context(|| format!("scan root {}", root.display()))?;
-    let mut keep = HashSet::<String>::new();
-    for repo_root in repos {
-        let meta = scan::read_repo_metadata(&repo_root)?;
-        keep.insert(meta.path.clone());
-        db.upsert_repo(&meta)?;
-    }
-    let pruned = if prune {
-        db.prune_under_root(&root.to_string_lossy(), &keep)?
-    } else {
-        0
-    };
-    Ok((keep.len(), pruned))
-}
-
 #[derive(Debug)]
 struct ApiError(anyhow::Error);
 
@@ -1021,24 +1657,22 @@ if ($f.ShowDialog() -eq 'OK') { $f.SelectedPath } else { '' }
     }
 }
 
-fn make_snippet(summary: Option<&str>, message: Option<&str>, qlow: &str) -> Option<String> {
-    let candidates: Vec<&str> = [summary, message].into_iter().flatten().collect();
-    for text in candidates {
-        let lower = text.to_lowercase();
-        if let Some(pos) = lower.find(qlow) {
-            let start = pos.saturating_sub(60);
-            let end = (pos + qlow.len() + 60).min(text.len());
-            let mut s = text[start..end].to_string();
-            if start > 0 {
-                s = format!("…{s}");
-            }
-            if end < text.len() {
-                s.push('…');
-            }
-            return Some(s);
+/// Escapes a search highlight for HTML and turns its match marks into `<mark>` tags.
+fn marked_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 16);
+    for c in text.chars() {
+        match c {
+            db::MARK_START => out.push_str("<mark>"),
+            db::MARK_END => out.push_str("</mark>"),
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
         }
     }
-    None
+    out
 }
 
 const INDEX_HTML: &str = r##"<!doctype html>
@@ -1085,7 +1719,14 @@ const INDEX_HTML: &str = r##"<!doctype html>
         <div class="panel">
           <div class="panel-head">
             <h2 data-i18n="tagsTitle">Tags</h2>
-            <button id="btnClearTag" class="ghost" data-i18n="clearFilterBtn">清除筛选</button>
+            <div class="row">
+              <button id="btnManageTags" class="ghost" data-i18n="manageTagsBtn">管理</button>
+              <button id="btnClearTag" class="ghost" data-i18n="clearFilterBtn">清除筛选</button>
+            </div>
+          </div>
+          <div class="row meta">
+            <label class="checkbox"><input id="tagAny" type="checkbox" /> <span data-i18n="tagMatchAny">任一标签即可</span></label>
+            <span data-i18n="tagFilterHint">点击：包含 → 排除 → 取消</span>
           </div>
           <div id="tags" class="chips"></div>
         </div>
@@ -1115,6 +1756,22 @@ const INDEX_HTML: &str = r##"<!doctype html>
             <button id="ignoreReset" class="ghost" data-i18n="resetBtn">重置</button>
           </div>
           <ul id="ignores" class="list"></ul>
+
+          <div class="hint" style="margin-top:12px;" data-i18n="ignorePatternHint">路径模式（gitignore 语法：锚定路径、**、! 取反）。</div>
+          <div class="row">
+            <input id="ignorePattern" placeholder="~/dev/vendor/**" />
+            <button id="ignorePatternAdd" class="ghost" data-i18n="addBtn">添加</button>
+          </div>
+          <ul id="ignorePatterns" class="list"></ul>
+        </div>
+
+        <div class="panel">
+          <div class="panel-head">
+            <h2 data-i18n="scanErrorsTitle">扫描错误</h2>
+            <div id="scanErrorCount" class="meta"></div>
+          </div>
+          <div class="hint" data-i18n="scanErrorsHint">最近一次扫描中无法读取的路径（权限、损坏的仓库、README 读取失败）。</div>
+          <ul id="scanErrors" class="list"></ul>
         </div>
       </aside>
 
@@ -1150,6 +1807,10 @@ const INDEX_HTML: &str = r##"<!doctype html>
                     <input id="inTags" type="checkbox" checked />
                     <span data-i18n="inTags">标签</span>
                   </label>
+                  <label class="filter-item">
+                    <input id="inNotes" type="checkbox" checked />
+                    <span data-i18n="inNotes">笔记/字段</span>
+                  </label>
                 </div>
                 <div class="filter-group hidden" data-mode="commits">
                   <label class="filter-item">
@@ -1161,19 +1822,40 @@ const INDEX_HTML: &str = r##"<!doctype html>
                     <span data-i18n="inMessage">正文</span>
                   </label>
                   <input id="branchFilter" class="branch-filter" placeholder="分支（可选）" />
+                  <input id="authorFilter" class="branch-filter" placeholder="作者或邮箱（可选）" />
+                  <label class="filter-item">
+                    <span data-i18n="afterLabel">从</span>
+                    <input id="afterFilter" type="date" />
+                  </label>
+                  <label class="filter-item">
+                    <span data-i18n="beforeLabel">到</span>
+                    <input id="beforeFilter" type="date" />
+                  </label>
+                  <label class="filter-item">
+                    <input id="mineOnly" type="checkbox" />
+                    <span data-i18n="mineOnly">只看我的提交</span>
+                  </label>
+                  <button id="btnMyCommits" class="ghost small" data-i18n="myCommits">我的提交</button>
                 </div>
               </div>
               <div class="search-input-row">
-                <input id="q" placeholder="搜索：仓库名 / 路径 / README / 标签" />
+                <input id="q" placeholder="搜索：关键词 tag:标签 lang:语言 &quot;短语&quot; -排除" />
+                <button id="btnSearchHelp" class="ghost" title="?">?</button>
                 <button id="btnSearch" data-i18n="searchBtn">搜索</button>
                 <button id="btnAll" class="ghost" data-i18n="allBtn">全部</button>
               </div>
+              <div id="searchHelp" class="search-help hidden"></div>
             </div>
             <div class="toolbar-right">
               <label class="checkbox"><input id="recent" type="checkbox" /> <span data-i18n="recentFirst">最近访问优先</span></label>
+              <label class="checkbox"><input id="showArchived" type="checkbox" /> <span data-i18n="showArchived">显示已归档</span></label>
               <label class="checkbox"><input id="prune" type="checkbox" /> <span data-i18n="pruneMoved">清理已删除/移动</span></label>
+              <label class="checkbox"><input id="fullScan" type="checkbox" /> <span data-i18n="fullScan">完整扫描</span></label>
               <button id="btnScanAll" data-i18n="scanAllBtn">扫描全部</button>
               <button id="btnPrune" class="ghost" data-i18n="pruneMissingBtn">清理缺失</button>
+              <button id="btnCancelJob" class="ghost danger hidden" data-i18n="cancelJob">取消任务</button>
+              <button id="btnHistory" class="ghost" data-i18n="historyBtn">扫描历史</button>
+              <button id="btnTrash" class="ghost" data-i18n="trashBtn">回收站</button>
             </div>
           </div>
 
@@ -1250,24 +1932,129 @@ const INDEX_HTML: &str = r##"<!doctype html>
       </div>
 	    </div>
 
-	    <div id="repoModal" class="modal hidden" role="dialog" aria-modal="true">
-	      <div class="modal-backdrop" id="repoClose"></div>
+	    <div id="repoModal" class="modal hidden" role="dialog" aria-modal="true">
+	      <div class="modal-backdrop" id="repoClose"></div>
+	      <div class="modal-card">
+	        <div class="modal-head">
+	          <div class="modal-title" data-i18n="repoTitle">仓库详情</div>
+	          <button id="repoX" class="ghost small">×</button>
+	        </div>
+	        <div class="modal-body">
+	          <div id="repoName" class="repo-name"></div>
+	          <div id="repoPath" class="mono truncate" style="margin-top:6px;"></div>
+	          <div id="repoOrigin" class="mono truncate" style="margin-top:6px;"></div>
+	          <div id="repoAbout" class="meta" style="margin-top:10px; white-space: pre-wrap;"></div>
+	          <div class="hint" data-i18n="repoTagsHint" style="margin-top:10px;">标签：</div>
+	          <div id="repoTags" class="badges"></div>
+	          <div class="hint" data-i18n="repoFieldsHint" style="margin-top:10px;">字段：</div>
+	          <div id="repoFields" class="badges"></div>
+	          <div class="row" style="margin-top:6px;">
+	            <input id="repoFieldKey" class="branch-filter" list="repoFieldKeys" placeholder="owner" />
+	            <input id="repoFieldValue" class="branch-filter" list="repoFieldValues" placeholder="alice" />
+	            <button id="repoFieldSet" class="ghost small" data-i18n="fieldSetBtn">设置</button>
+	          </div>
+	          <datalist id="repoFieldKeys">
+	            <option value="owner"></option>
+	            <option value="status"></option>
+	            <option value="ticket"></option>
+	            <option value="lifecycle"></option>
+	          </datalist>
+	          <datalist id="repoFieldValues"></datalist>
+	          <div class="hint" data-i18n="repoNoteHint" style="margin-top:10px;">笔记（Markdown）：</div>
+	          <textarea id="repoNote" rows="6"></textarea>
+	        </div>
+	        <div class="modal-foot">
+	          <button id="repoNoteSave" class="ghost small" data-i18n="noteSaveBtn">保存笔记</button>
+	          <button id="repoCopy" class="ghost small" data-i18n="copy">复制</button>
+	          <button id="repoCommits" class="ghost small" data-i18n="commitsBtn">提交</button>
+	        </div>
+	      </div>
+	    </div>
+
+	    <div id="historyModal" class="modal hidden" role="dialog" aria-modal="true">
+	      <div class="modal-backdrop" id="historyClose"></div>
+	      <div class="modal-card">
+	        <div class="modal-head">
+	          <div class="modal-title" data-i18n="historyTitle">扫描历史</div>
+	          <button id="historyX" class="ghost small">×</button>
+	        </div>
+	        <div class="modal-sub">
+	          <input id="historySince" class="branch-filter" placeholder="7d / 2024-05-01" />
+	          <label class="checkbox"><input id="historyChangedOnly" type="checkbox" checked /> <span data-i18n="historyChangedOnly">只看有变化的扫描</span></label>
+	          <button id="historyLoad" class="ghost small" data-i18n="apply">应用</button>
+	        </div>
+	        <div class="modal-body">
+	          <div id="historyList" class="timeline"></div>
+	        </div>
+	      </div>
+	    </div>
+
+	    <div id="rootSettingsModal" class="modal hidden" role="dialog" aria-modal="true">
+	      <div class="modal-backdrop" id="rootSettingsClose"></div>
+	      <div class="modal-card">
+	        <div class="modal-head">
+	          <div class="modal-title" data-i18n="rootSettingsTitle">Root 设置</div>
+	          <button id="rootSettingsX" class="ghost small">×</button>
+	        </div>
+	        <div class="modal-sub">
+	          <div id="rsRoot" class="mono"></div>
+	          <div class="meta" data-i18n="rootSettingsHint">留空的项沿用全局配置。</div>
+	        </div>
+	        <div class="modal-body settings-form">
+	          <label><span data-i18n="rsLabel">显示名称</span><input id="rsLabel" /></label>
+	          <label><span data-i18n="rsMaxDepth">扫描深度</span><input id="rsMaxDepth" type="number" min="1" /></label>
+	          <label><span data-i18n="rsIgnore">额外忽略目录名（逗号分隔）</span><input id="rsIgnore" placeholder="vendor, third_party" /></label>
+	          <label><span data-i18n="rsIgnorePatterns">额外忽略模式（逗号分隔）</span><input id="rsIgnorePatterns" placeholder="archive/**, *-backup" /></label>
+	          <label><span data-i18n="rsAutoTags">自动标签（逗号分隔）</span><input id="rsAutoTags" placeholder="work" /></label>
+	          <label><span data-i18n="rsCommitBranches">提交索引：分支数</span><input id="rsCommitBranches" type="number" min="1" max="200" /></label>
+	          <label><span data-i18n="rsCommitsPerBranch">提交索引：每分支提交数</span><input id="rsCommitsPerBranch" type="number" min="1" max="500" /></label>
+	        </div>
+	        <div class="modal-foot">
+	          <button id="rsClear" class="ghost" data-i18n="rsClear">清空</button>
+	          <button id="rsSave" data-i18n="save">保存</button>
+	        </div>
+	      </div>
+	    </div>
+
+	    <div id="tagModal" class="modal hidden" role="dialog" aria-modal="true">
+	      <div class="modal-backdrop" id="tagClose"></div>
+	      <div class="modal-card">
+	        <div class="modal-head">
+	          <div class="modal-title" data-i18n="tagManageTitle">管理标签</div>
+	          <button id="tagX" class="ghost small">×</button>
+	        </div>
+	        <div class="modal-sub">
+	          <div class="meta" data-i18n="tagManageHint">用 / 分层（如 team/payments）；重命名、合并、删除会作用于其下的所有子标签。</div>
+	        </div>
+	        <div class="modal-body settings-form">
+	          <label><span data-i18n="tmTag">标签</span><select id="tmTag"></select></label>
+	          <label><span data-i18n="tmColor">颜色</span><input id="tmColor" placeholder="#e11d48" /></label>
+	          <label><span data-i18n="tmDescription">说明</span><input id="tmDescription" /></label>
+	          <label><span data-i18n="tmTarget">新名称 / 合并到</span><input id="tmTarget" list="tmTagList" /></label>
+	          <datalist id="tmTagList"></datalist>
+	        </div>
+	        <div class="modal-foot">
+	          <button id="tmDelete" class="ghost danger" data-i18n="tmDelete">删除</button>
+	          <button id="tmMerge" class="ghost" data-i18n="tmMerge">合并</button>
+	          <button id="tmRename" class="ghost" data-i18n="tmRename">重命名</button>
+	          <button id="tmSave" data-i18n="save">保存</button>
+	        </div>
+	      </div>
+	    </div>
+
+	    <div id="trashModal" class="modal hidden" role="dialog" aria-modal="true">
+	      <div class="modal-backdrop" id="trashClose"></div>
 	      <div class="modal-card">
 	        <div class="modal-head">
-	          <div class="modal-title" data-i18n="repoTitle">仓库详情</div>
-	          <button id="repoX" class="ghost small">×</button>
+	          <div class="modal-title" data-i18n="trashTitle">回收站</div>
+	          <button id="trashX" class="ghost small">×</button>
 	        </div>
-	        <div class="modal-body">
-	          <div id="repoName" class="repo-name"></div>
-	          <div id="repoPath" class="mono truncate" style="margin-top:6px;"></div>
-	          <div id="repoOrigin" class="mono truncate" style="margin-top:6px;"></div>
-	          <div id="repoAbout" class="meta" style="margin-top:10px; white-space: pre-wrap;"></div>
-	          <div class="hint" data-i18n="repoTagsHint" style="margin-top:10px;">标签：</div>
-	          <div id="repoTags" class="badges"></div>
+	        <div class="modal-sub">
+	          <div class="meta" data-i18n="trashHint">清理掉的仓库会先放在这里，恢复后标签与访问记录保持不变。</div>
+	          <button id="trashEmpty" class="ghost small danger" data-i18n="trashEmptyBtn">清空回收站</button>
 	        </div>
-	        <div class="modal-foot">
-	          <button id="repoCopy" class="ghost small" data-i18n="copy">复制</button>
-	          <button id="repoCommits" class="ghost small" data-i18n="commitsBtn">提交</button>
+	        <div class="modal-body">
+	          <div id="trashList" class="timeline"></div>
 	        </div>
 	      </div>
 	    </div>
@@ -1397,6 +2184,21 @@ input:focus {
 input::placeholder { 
   color: rgba(156,163,175,0.6); 
 }
+textarea {
+  padding: 10px 14px;
+  width: 100%;
+  border: 1px solid var(--border);
+  border-radius: 10px;
+  background: rgba(2,6,23,0.6);
+  color: var(--text);
+  outline: none;
+  font: 13px ui-monospace, SFMono-Regular, Menlo, monospace;
+  resize: vertical;
+}
+textarea:focus {
+  border-color: rgba(96,165,250,0.5);
+  box-shadow: 0 0 0 3px rgba(96,165,250,0.1);
+}
 button {
   padding: 10px 16px;
   border: 1px solid var(--border);
@@ -1495,6 +2297,12 @@ button:disabled {
   background: rgba(96,165,250,0.18);
   color: var(--accent);
 }
+.chip.excluded {
+  border-color: rgba(251,113,133,0.55);
+  background: rgba(251,113,133,0.12);
+  color: var(--danger);
+  text-decoration: line-through;
+}
 .search-mode-tabs { display: inline-flex; gap: 0; margin-bottom: 12px; border: 1px solid var(--border); border-radius: 10px; background: rgba(2,6,23,0.35); padding: 2px; }
 .mode-tab { display: inline-flex; align-items: center; padding: 8px 16px; border-radius: 8px; font-size: 13px; color: var(--muted); cursor: pointer; user-select: none; transition: all 0.2s; position: relative; }
 .mode-tab input[type="radio"] { display: none; }
@@ -1545,6 +2353,17 @@ button:disabled {
 }
 .search-input-row { display: flex; gap: 8px; align-items: center; }
 .search-input-row input { flex: 1; min-width: 0; }
+.search-help {
+  margin-top: 8px;
+  padding: 10px 12px;
+  border: 1px solid var(--border);
+  border-radius: 8px;
+  font-size: 12px;
+}
+.search-help-title { color: var(--muted); margin-bottom: 4px; }
+.search-help table { border-collapse: collapse; }
+.search-help td { padding: 2px 12px 2px 0; vertical-align: top; }
+.search-help code { font-family: ui-monospace, SFMono-Regular, Menlo, monospace; }
 
 .toolbar { 
   display: flex; 
@@ -1785,6 +2604,24 @@ button:disabled {
 .badge button:hover {
   color: var(--danger);
 }
+.settings-form { display: grid; gap: 10px; }
+.settings-form label { display: grid; gap: 4px; color: var(--muted); font-size: 12px; }
+.tag-children { flex-basis: 100%; margin-top: 0; padding-left: 14px; border-left: 1px dashed var(--border); }
+.timeline { display: flex; flex-direction: column; gap: 12px; }
+.timeline-run { border-left: 2px solid var(--border); padding-left: 12px; }
+.timeline-run .run-head { display: flex; gap: 8px; flex-wrap: wrap; align-items: baseline; }
+.timeline-run .run-events { margin-top: 6px; display: flex; flex-direction: column; gap: 4px; }
+.timeline-run .run-event { display: flex; gap: 8px; align-items: baseline; font-size: 12px; }
+.worktrees {
+  margin-top: 6px;
+  padding-left: 8px;
+  border-left: 2px solid var(--border);
+}
+.worktree {
+  font-size: 12px;
+  cursor: pointer;
+}
+.worktree .muted { color: var(--muted); }
 .match-badges {
   display: flex;
   flex-wrap: wrap;
@@ -1898,10 +2735,37 @@ const I18N = {
   zh: {
     langBtn: "中文",
     subtitle: "本地仓库管理与索引（离线）",
-    qPlaceholder: "搜索：仓库名 / 路径 / README / 标签",
-    qPlaceholderCommits: "搜索提交内容（需要先重建索引）",
+    qPlaceholder: "搜索：关键词 tag:标签 lang:语言 \"短语\" -排除（? 查看语法）",
+    qPlaceholderCommits: "搜索提交：关键词 author:作者 after:日期 branch:分支（需要先重建索引）",
+    helpTitle: "搜索语法（同类条件重复时任一匹配，tag: 需全部匹配）",
+    helpWord: "关键词按前缀匹配，多个关键词需全部匹配",
+    helpPhrase: "完整短语",
+    helpExclude: "排除关键词、短语或条件",
+    helpTag: "带有该标签",
+    helpLang: "主要语言",
+    helpRoot: "位于该目录下",
+    helpOrigin: "远程地址包含该文本",
+    helpField: "自定义字段（= 或 ~ 包含）",
+    helpIsRepo: "已置顶 / 已归档（默认隐藏归档）",
+    helpInRepo: "只搜索这些字段：name, tag, readme, notes, path",
+    helpAuthor: "作者名或邮箱包含该文本",
+    helpAfter: "在该时间之后（日期或 7d 这样的相对时间）",
+    helpBefore: "在该时间之前",
+    helpBranch: "分支",
+    helpRepo: "仓库名或路径包含该文本",
+    helpIsCommit: "合并提交",
+    helpInCommit: "只搜索标题 / 正文",
     rootPlaceholder: "root 目录（例如：/Users/jim/dev）",
     branchFilterPlaceholder: "分支（可选）",
+    authorFilterPlaceholder: "作者或邮箱（可选）",
+    afterLabel: "从",
+    beforeLabel: "到",
+    mineOnly: "只看我的提交",
+    myCommits: "我的提交",
+    myCommitsTitle: ({ ids }) => `所有仓库中 ${ids} 的提交`,
+    noIdentities: "在 config.toml 中设置 my_identities（你的作者名与邮箱）",
+    helpEmail: "作者邮箱包含该文本",
+    helpMine: "我的提交（config.toml 中的 my_identities）",
     scopeRepos: "仓库",
     scopeCommits: "提交",
     searchIn: "搜索范围：",
@@ -1909,12 +2773,14 @@ const I18N = {
     inPath: "路径",
     inReadme: "README",
     inTags: "标签",
+    inNotes: "笔记/字段",
     inSummary: "摘要",
     inMessage: "正文",
     hit_name: "名称",
     hit_path: "路径",
     hit_readme: "README",
     hit_tag: "标签",
+    hit_notes: "笔记/字段",
     hit_summary: "摘要",
     hit_message: "正文",
     hit_repo: "仓库",
@@ -1927,7 +2793,17 @@ const I18N = {
     searchBtn: "搜索",
     allBtn: "全部",
     recentFirst: "最近访问优先",
+    showArchived: "显示已归档",
+    archivedBadge: "已归档",
+    pinBtn: "置顶",
+    unpinBtn: "取消置顶",
+    archiveBtn: "归档",
+    unarchiveBtn: "取消归档",
+    hideBtn: "隐藏",
+    hideConfirm: ({ path }) => `隐藏 ${path}？以后的扫描会跳过它，并把它移入回收站（从回收站恢复即可取消隐藏）。`,
+    repoHidden: ({ path }) => `已隐藏：${path}`,
     pruneMoved: "清理已删除/移动",
+    fullScan: "完整扫描",
     scanAllBtn: "扫描全部",
     pruneMissingBtn: "清理缺失",
     rootsTitle: "Roots",
@@ -1939,9 +2815,72 @@ const I18N = {
     ready: "就绪",
     scanning: "扫描中…",
     pruning: "清理中…",
-    scanDone: ({ indexed, pruned }) => `扫描完成：indexed=${indexed} pruned=${pruned}`,
-    pruneDone: ({ deleted }) => `清理完成：deleted=${deleted}`,
+    cancelJob: "取消任务",
+    jobProgress: ({ label, discovered, processed, current }) =>
+      `${label}：${processed}/${discovered}` + (current ? ` · ${current}` : ""),
+    jobCancelled: "任务已取消",
+    historyBtn: "扫描历史",
+    historyTitle: "扫描历史",
+    historyChangedOnly: "只看有变化的扫描",
+    historyEmpty: "没有扫描记录",
+    runSummary: ({ indexed, skipped, pruned, errors, duration_ms }) =>
+      `indexed=${indexed} unchanged=${skipped} pruned=${pruned} errors=${errors} · ${duration_ms} ms`,
+    ev_new: "新仓库",
+    ev_moved: "已移动",
+    ev_restored: "已恢复",
+    ev_removed: "移入回收站",
+    ev_head_moved: "HEAD 移动",
+    ev_new_commits: "新提交",
+    ev_branch_changed: "分支变化",
+    ev_origin_changed: "origin 变化",
+    scanDone: ({ indexed, skipped, pruned, errors, changes }) =>
+      `扫描完成：indexed=${indexed} unchanged=${skipped} pruned=${pruned} changes=${changes || 0}` + (errors ? ` errors=${errors}` : ""),
+    scanErrorsTitle: "扫描错误",
+    scanErrorsHint: "最近一次扫描中无法读取的路径（权限、损坏的仓库、README 读取失败）。",
+    scanErrorsNone: "无",
+    pruneDone: ({ trashed }) => `清理完成：${trashed} 个仓库移入回收站`,
+    rootSettingsTitle: "Root 设置",
+    rootSettingsHint: "留空的项沿用全局配置。",
+    rootSettingsSaved: "已保存 root 设置",
+    rsLabel: "显示名称",
+    rsMaxDepth: "扫描深度",
+    rsIgnore: "额外忽略目录名（逗号分隔）",
+    rsIgnorePatterns: "额外忽略模式（逗号分隔）",
+    rsAutoTags: "自动标签（逗号分隔）",
+    rsCommitBranches: "提交索引：分支数",
+    rsCommitsPerBranch: "提交索引：每分支提交数",
+    rsClear: "清空",
+    save: "保存",
+    manageTagsBtn: "管理",
+    tagManageTitle: "管理标签",
+    tagManageHint: "用 / 分层（如 team/payments）；重命名、合并、删除会作用于其下的所有子标签。",
+    tmTag: "标签",
+    tmColor: "颜色",
+    tmDescription: "说明",
+    tmTarget: "新名称 / 合并到",
+    tmRename: "重命名",
+    tmMerge: "合并",
+    tmDelete: "删除",
+    tagsNone: "还没有标签",
+    tagTargetRequired: "请填写新名称或合并目标",
+    tagConfirmDelete: ({ tag }) => `从所有仓库上删除标签 ${tag}（及其子标签）？`,
+    tagSaved: "已保存标签",
+    tagRenamed: ({ tags }) => `已重命名 ${tags} 个标签`,
+    tagMerged: ({ tags }) => `已合并 ${tags} 个标签`,
+    tagDeleted: ({ tags }) => `已删除 ${tags} 个标签`,
+    trashBtn: "回收站",
+    trashTitle: "回收站",
+    trashHint: "清理掉的仓库会先放在这里，恢复后标签与访问记录保持不变。",
+    trashEmpty: "回收站是空的",
+    trashEmptyBtn: "清空回收站",
+    trashRestore: "恢复",
+    trashPurge: "彻底删除",
+    trashConfirmEmpty: "彻底删除回收站中的全部仓库（包括标签关联与提交索引）？",
+    trashRestored: ({ name }) => `已恢复：${name}`,
+    trashPurged: ({ purged }) => `已彻底删除 ${purged} 个仓库`,
     filterTag: ({ tag }) => `按标签过滤：${tag}`,
+    tagMatchAny: "任一标签即可",
+    tagFilterHint: "点击：包含 → 排除 → 取消",
     allRepos: "全部仓库",
     searching: "搜索中…",
     searchResult: ({ q }) => `搜索结果：${q}`,
@@ -1965,9 +2904,19 @@ const I18N = {
     colActions: "操作",
     commitsTitle: "提交记录",
     commitsBtn: "提交",
+    kind_worktree: "工作树",
+    kind_submodule: "子模块",
+    kind_bare: "裸仓库",
+    worktrees: "工作树",
     branch: "分支",
     repoTitle: "仓库详情",
     repoTagsHint: "标签：",
+    repoFieldsHint: "字段（点击按该字段筛选）：",
+    repoNoteHint: "笔记（Markdown）：",
+    fieldSetBtn: "设置",
+    noteSaveBtn: "保存笔记",
+    noteSaved: "笔记已保存",
+    filterField: ({ field }) => `按字段过滤：${field}`,
     commitDetailTitle: "提交详情",
     commitSearchBtn: "提交搜索",
     commitSearchTitle: "提交搜索",
@@ -1977,6 +2926,7 @@ const I18N = {
     rebuildIndex: "重建索引",
     resetBtn: "重置",
     ignoreHint: "扫描时忽略常见依赖/缓存目录（目录名匹配）。",
+    ignorePatternHint: "路径模式（gitignore 语法：锚定路径、**、! 取反）。",
     perPage: "每页",
     prev: "上一页",
     next: "下一页",
@@ -1985,10 +2935,37 @@ const I18N = {
   en: {
     langBtn: "English",
     subtitle: "Local repo management & index (offline)",
-    qPlaceholder: "Search: name / path / README / tag",
-    qPlaceholderCommits: "Search commit content (rebuild index first)",
+    qPlaceholder: "Search: words tag:x lang:rust \"a phrase\" -exclude (? for syntax)",
+    qPlaceholderCommits: "Search commits: words author:x after:date branch:main (rebuild index first)",
+    helpTitle: "Search syntax (repeated filters match any value; every tag: must match)",
+    helpWord: "Words match as prefixes; all of them must match",
+    helpPhrase: "Exact phrase",
+    helpExclude: "Exclude a word, phrase or filter",
+    helpTag: "Has this tag",
+    helpLang: "Main language",
+    helpRoot: "Under this directory",
+    helpOrigin: "Remote URL contains this text",
+    helpField: "Custom field (= equals, ~ contains)",
+    helpIsRepo: "Pinned / archived (archived is hidden by default)",
+    helpInRepo: "Only search these fields: name, tag, readme, notes, path",
+    helpAuthor: "Author name or email contains this text",
+    helpAfter: "After this time (a date or relative like 7d)",
+    helpBefore: "Before this time",
+    helpBranch: "On this branch",
+    helpRepo: "Repo name or path contains this text",
+    helpIsCommit: "Merge commits",
+    helpInCommit: "Only search summary / message",
     rootPlaceholder: "Root directory (e.g. /Users/jim/dev)",
     branchFilterPlaceholder: "Branch (optional)",
+    authorFilterPlaceholder: "Author or email (optional)",
+    afterLabel: "From",
+    beforeLabel: "To",
+    mineOnly: "Only my commits",
+    myCommits: "My commits",
+    myCommitsTitle: ({ ids }) => `Commits by ${ids} across all repos`,
+    noIdentities: "Set my_identities (your author names and emails) in config.toml",
+    helpEmail: "Author email contains this text",
+    helpMine: "My commits (my_identities in config.toml)",
     scopeRepos: "Repos",
     scopeCommits: "Commits",
     searchIn: "Search in:",
@@ -1996,12 +2973,14 @@ const I18N = {
     inPath: "Path",
     inReadme: "README",
     inTags: "Tags",
+    inNotes: "Notes/fields",
     inSummary: "Summary",
     inMessage: "Message",
     hit_name: "Name",
     hit_path: "Path",
     hit_readme: "README",
     hit_tag: "Tag",
+    hit_notes: "Notes/fields",
     hit_summary: "Summary",
     hit_message: "Message",
     hit_repo: "Repo",
@@ -2014,7 +2993,17 @@ const I18N = {
     searchBtn: "Search",
     allBtn: "All",
     recentFirst: "Recent first",
+    showArchived: "Show archived",
+    archivedBadge: "Archived",
+    pinBtn: "Pin",
+    unpinBtn: "Unpin",
+    archiveBtn: "Archive",
+    unarchiveBtn: "Unarchive",
+    hideBtn: "Hide",
+    hideConfirm: ({ path }) => `Hide ${path}? Future scans skip it and it moves to the trash (restore it from the trash to unhide).`,
+    repoHidden: ({ path }) => `Hidden: ${path}`,
     pruneMoved: "Prune moved/deleted",
+    fullScan: "Full rescan",
     scanAllBtn: "Scan all",
     pruneMissingBtn: "Prune missing",
     rootsTitle: "Roots",
@@ -2026,9 +3015,72 @@ const I18N = {
     ready: "Ready",
     scanning: "Scanning…",
     pruning: "Pruning…",
-    scanDone: ({ indexed, pruned }) => `Scan done: indexed=${indexed} pruned=${pruned}`,
-    pruneDone: ({ deleted }) => `Prune done: deleted=${deleted}`,
+    cancelJob: "Cancel job",
+    jobProgress: ({ label, discovered, processed, current }) =>
+      `${label}: ${processed}/${discovered}` + (current ? ` · ${current}` : ""),
+    jobCancelled: "Job cancelled",
+    historyBtn: "Scan history",
+    historyTitle: "Scan history",
+    historyChangedOnly: "Only scans with changes",
+    historyEmpty: "No scans recorded",
+    runSummary: ({ indexed, skipped, pruned, errors, duration_ms }) =>
+      `indexed=${indexed} unchanged=${skipped} pruned=${pruned} errors=${errors} · ${duration_ms} ms`,
+    ev_new: "new repo",
+    ev_moved: "moved",
+    ev_restored: "restored",
+    ev_removed: "moved to trash",
+    ev_head_moved: "HEAD moved",
+    ev_new_commits: "new commits",
+    ev_branch_changed: "branch changed",
+    ev_origin_changed: "origin changed",
+    scanDone: ({ indexed, skipped, pruned, errors, changes }) =>
+      `Scan done: indexed=${indexed} unchanged=${skipped} pruned=${pruned} changes=${changes || 0}` + (errors ? ` errors=${errors}` : ""),
+    scanErrorsTitle: "Scan errors",
+    scanErrorsHint: "Paths the latest scan could not read (permissions, corrupt repos, unreadable READMEs).",
+    scanErrorsNone: "None",
+    pruneDone: ({ trashed }) => `Prune done: ${trashed} repos moved to the trash`,
+    rootSettingsTitle: "Root settings",
+    rootSettingsHint: "Leave a field empty to use the global setting.",
+    rootSettingsSaved: "Root settings saved",
+    rsLabel: "Label",
+    rsMaxDepth: "Max depth",
+    rsIgnore: "Extra ignored directory names (comma-separated)",
+    rsIgnorePatterns: "Extra ignore patterns (comma-separated)",
+    rsAutoTags: "Auto-tags (comma-separated)",
+    rsCommitBranches: "Commit index: branches",
+    rsCommitsPerBranch: "Commit index: commits per branch",
+    rsClear: "Clear",
+    save: "Save",
+    manageTagsBtn: "Manage",
+    tagManageTitle: "Manage tags",
+    tagManageHint: "Use / for namespaces (e.g. team/payments); rename, merge and delete also apply to the tags below.",
+    tmTag: "Tag",
+    tmColor: "Color",
+    tmDescription: "Description",
+    tmTarget: "New name / merge into",
+    tmRename: "Rename",
+    tmMerge: "Merge",
+    tmDelete: "Delete",
+    tagsNone: "No tags yet",
+    tagTargetRequired: "Enter a new name or a tag to merge into",
+    tagConfirmDelete: ({ tag }) => `Delete tag ${tag} (and the tags below it) from every repo?`,
+    tagSaved: "Tag saved",
+    tagRenamed: ({ tags }) => `Renamed ${tags} tags`,
+    tagMerged: ({ tags }) => `Merged ${tags} tags`,
+    tagDeleted: ({ tags }) => `Deleted ${tags} tags`,
+    trashBtn: "Trash",
+    trashTitle: "Trash",
+    trashHint: "Pruned repos land here first; restoring keeps their tags and access history.",
+    trashEmpty: "The trash is empty",
+    trashEmptyBtn: "Empty trash",
+    trashRestore: "Restore",
+    trashPurge: "Delete forever",
+    trashConfirmEmpty: "Permanently delete every repo in the trash (including tags and commit index)?",
+    trashRestored: ({ name }) => `Restored: ${name}`,
+    trashPurged: ({ purged }) => `Permanently deleted ${purged} repos`,
     filterTag: ({ tag }) => `Filtered by tag: ${tag}`,
+    tagMatchAny: "Match any tag",
+    tagFilterHint: "Click: include → exclude → off",
     allRepos: "All repos",
     searching: "Searching…",
     searchResult: ({ q }) => `Search results: ${q}`,
@@ -2052,9 +3104,19 @@ const I18N = {
     colActions: "Actions",
     commitsTitle: "Commits",
     commitsBtn: "Commits",
+    kind_worktree: "worktree",
+    kind_submodule: "submodule",
+    kind_bare: "bare",
+    worktrees: "Worktrees",
     branch: "Branch",
     repoTitle: "Repository",
     repoTagsHint: "Tags:",
+    repoFieldsHint: "Fields (click to filter by one):",
+    repoNoteHint: "Notes (Markdown):",
+    fieldSetBtn: "Set",
+    noteSaveBtn: "Save notes",
+    noteSaved: "Notes saved",
+    filterField: ({ field }) => `Filtered by field: ${field}`,
     commitDetailTitle: "Commit",
     commitSearchBtn: "Commit search",
     commitSearchTitle: "Commit search",
@@ -2064,6 +3126,7 @@ const I18N = {
     rebuildIndex: "Rebuild index",
     resetBtn: "Reset",
     ignoreHint: "Ignore dependency/cache folders during scan (by directory name).",
+    ignorePatternHint: "Path patterns (gitignore syntax: anchored paths, **, ! negation).",
     perPage: "Per page",
     prev: "Prev",
     next: "Next",
@@ -2106,6 +3169,33 @@ async function api(path, opts = {}) {
 
 function setStatus(s) { $("status").textContent = s; }
 
+let currentJobId = null;
+
+// Starts a background job and follows its progress over SSE; resolves with the job result.
+async function runJob(path, body) {
+  const { job_id } = await api(path, { method: "POST", body: JSON.stringify(body) });
+  currentJobId = job_id;
+  $("btnCancelJob").classList.remove("hidden");
+  try {
+    return await new Promise((resolve, reject) => {
+      const es = new EventSource(`/api/jobs/${job_id}/events`);
+      es.onmessage = (ev) => {
+        const job = JSON.parse(ev.data);
+        if (job.state === "running") {
+          setStatus(t("jobProgress", job));
+          return;
+        }
+        es.close();
+        if (job.state === "done") resolve(job.result);
+        else reject(new Error(job.state === "cancelled" ? t("jobCancelled") : job.error || job.state));
+      };
+    });
+  } finally {
+    currentJobId = null;
+    $("btnCancelJob").classList.add("hidden");
+  }
+}
+
 function toast(msg) {
   const el = $("toast");
   el.textContent = msg;
@@ -2152,7 +3242,51 @@ async function loadCommitIndexConfig() {
   const cfg = await api("/api/config");
   $("idxBranches").value = cfg.commit_index_branches;
   $("idxCommits").value = cfg.commit_index_commits_per_branch;
+  myIdentities = cfg.my_identities || [];
+  applyI18n();
   renderIgnores(cfg.ignore_dir_names || []);
+  renderIgnorePatterns(cfg.ignore_patterns || []);
+}
+
+function renderIgnorePatterns(items) {
+  const ul = $("ignorePatterns");
+  if (!ul) return;
+  ul.innerHTML = "";
+  for (const p of items) {
+    const li = document.createElement("li");
+    li.innerHTML = `
+      <div class="mono" title="${escapeHtml(p)}" style="overflow:hidden;text-overflow:ellipsis;white-space:nowrap;">${escapeHtml(p)}</div>
+      <div class="actions-cell">
+        <button class="ghost small danger">${t("remove")}</button>
+      </div>
+    `;
+    li.querySelector("button").onclick = async () => {
+      await api("/api/ignores/remove", { method: "POST", body: JSON.stringify({ pattern: p }) });
+      await loadCommitIndexConfig();
+    };
+    ul.appendChild(li);
+  }
+}
+
+function renderScanErrors(items) {
+  const ul = $("scanErrors");
+  if (!ul) return;
+  $("scanErrorCount").textContent = items.length ? String(items.length) : t("scanErrorsNone");
+  ul.innerHTML = "";
+  for (const e of items) {
+    const li = document.createElement("li");
+    li.innerHTML = `
+      <div style="min-width:0;">
+        <div class="mono" title="${escapeHtml(e.path)}" style="overflow:hidden;text-overflow:ellipsis;white-space:nowrap;">${escapeHtml(e.path)}</div>
+        <div class="meta wrap clamp2" title="${escapeHtml(e.message + "\n" + fmtTs(e.ts))}"><span class="match-badge">${escapeHtml(e.kind)}</span> ${escapeHtml(e.message)}</div>
+      </div>
+    `;
+    ul.appendChild(li);
+  }
+}
+
+async function loadScanErrors() {
+  renderScanErrors(await api("/api/scan/errors"));
 }
 
 function renderIgnores(items) {
@@ -2181,15 +3315,16 @@ async function rebuildCommitIndexAll() {
   const branches = parseInt($("idxBranches").value, 10);
   const commits = parseInt($("idxCommits").value, 10);
   $("idxStatus").textContent = t("scanning");
-  const out = await api("/api/commit_index/rebuild", {
-    method: "POST",
-    body: JSON.stringify({
+  try {
+    const out = await runJob("/api/commit_index/rebuild", {
       all: true,
       commit_index_branches: branches,
       commit_index_commits_per_branch: commits,
-    }),
-  });
-  toast(`Commit index rebuilt: repos=${out.repos_indexed}`);
+    });
+    toast(`Commit index rebuilt: repos=${out.repos_indexed}`);
+  } catch (e) {
+    toast(t("err", { msg: e.message }));
+  }
   $("idxStatus").textContent = t("ready");
 }
 
@@ -2235,6 +3370,180 @@ function showRepoModal(show) {
   }
 }
 
+function showHistoryModal(show) {
+  const m = $("historyModal");
+  if (show) {
+    m.classList.remove("hidden");
+    lockBodyScroll(true);
+  } else {
+    m.classList.add("hidden");
+    lockBodyScroll(false);
+  }
+}
+
+function shortValue(v) {
+  if (!v) return "-";
+  return /^[0-9a-f]{40}$/.test(v) ? v.slice(0, 10) : v;
+}
+
+async function loadHistory() {
+  const since = $("historySince").value.trim();
+  const q = since ? `?since=${encodeURIComponent(since)}` : "";
+  const box = $("historyList");
+  let runs;
+  try {
+    runs = await api(`/api/scan/runs${q}`);
+  } catch (e) {
+    box.innerHTML = `<div class="meta">${escapeHtml(t("err", { msg: e.message }))}</div>`;
+    return;
+  }
+  if ($("historyChangedOnly").checked) runs = runs.filter((r) => (r.changes || []).length > 0);
+  if (!runs.length) {
+    box.innerHTML = `<div class="meta">${escapeHtml(t("historyEmpty"))}</div>`;
+    return;
+  }
+  box.innerHTML = runs
+    .map((r) => {
+      const events = (r.changes || [])
+        .map((c) => {
+          let what = "";
+          if (c.detail) what = c.detail;
+          else if (!["new", "removed", "restored"].includes(c.kind)) what = `${shortValue(c.old_value)} → ${shortValue(c.new_value)}`;
+          return `<div class="run-event">
+            <span class="match-badge">${escapeHtml(t("ev_" + c.kind))}</span>
+            <span class="repo-name" title="${escapeHtml(c.path)}">${escapeHtml(c.name)}</span>
+            <span class="mono meta">${escapeHtml(what)}</span>
+          </div>`;
+        })
+        .join("");
+      return `<div class="timeline-run">
+        <div class="run-head">
+          <span class="mono">${escapeHtml(fmtTs(r.started_ts))}</span>
+          <span class="mono meta" title="${escapeHtml(r.root)}">${escapeHtml(r.root)}</span>
+          <span class="meta">${escapeHtml(t("runSummary", r))}</span>
+        </div>
+        ${events ? `<div class="run-events">${events}</div>` : ""}
+      </div>`;
+    })
+    .join("");
+}
+
+let rootSettingsRoot = "";
+
+function showRootSettingsModal(show) {
+  const m = $("rootSettingsModal");
+  if (show) {
+    m.classList.remove("hidden");
+    lockBodyScroll(true);
+  } else {
+    m.classList.add("hidden");
+    lockBodyScroll(false);
+  }
+}
+
+function openRootSettings(root, rs) {
+  rootSettingsRoot = root;
+  $("rsRoot").textContent = root;
+  $("rsLabel").value = rs.label || "";
+  $("rsMaxDepth").value = rs.max_depth ?? "";
+  $("rsIgnore").value = (rs.ignore_dir_names || []).join(", ");
+  $("rsIgnorePatterns").value = (rs.ignore_patterns || []).join(", ");
+  $("rsAutoTags").value = (rs.auto_tags || []).join(", ");
+  $("rsCommitBranches").value = rs.commit_index_branches ?? "";
+  $("rsCommitsPerBranch").value = rs.commit_index_commits_per_branch ?? "";
+  showRootSettingsModal(true);
+}
+
+async function saveRootSettings(clear) {
+  const list = (id) => (clear ? [] : $(id).value.split(",").map((s) => s.trim()).filter(Boolean));
+  const num = (id) => {
+    const v = parseInt($(id).value, 10);
+    return clear || Number.isNaN(v) ? null : v;
+  };
+  const settings = {
+    label: clear ? null : $("rsLabel").value.trim() || null,
+    max_depth: num("rsMaxDepth"),
+    ignore_dir_names: list("rsIgnore"),
+    ignore_patterns: list("rsIgnorePatterns"),
+    auto_tags: list("rsAutoTags"),
+    commit_index_branches: num("rsCommitBranches"),
+    commit_index_commits_per_branch: num("rsCommitsPerBranch"),
+  };
+  try {
+    await api("/api/roots/settings", { method: "POST", body: JSON.stringify({ root: rootSettingsRoot, settings }) });
+    showRootSettingsModal(false);
+    toast(t("rootSettingsSaved"));
+    await refreshSidebars();
+  } catch (e) {
+    toast(t("err", { msg: e.message }));
+  }
+}
+
+function showTrashModal(show) {
+  const m = $("trashModal");
+  if (show) {
+    m.classList.remove("hidden");
+    lockBodyScroll(true);
+  } else {
+    m.classList.add("hidden");
+    lockBodyScroll(false);
+  }
+}
+
+async function loadTrash() {
+  const box = $("trashList");
+  let items;
+  try {
+    items = await api("/api/trash");
+  } catch (e) {
+    box.innerHTML = `<div class="meta">${escapeHtml(t("err", { msg: e.message }))}</div>`;
+    return;
+  }
+  $("trashEmpty").disabled = !items.length;
+  if (!items.length) {
+    box.innerHTML = `<div class="meta">${escapeHtml(t("trashEmpty"))}</div>`;
+    return;
+  }
+  box.innerHTML = "";
+  for (const it of items) {
+    const row = document.createElement("div");
+    row.className = "timeline-run";
+    const tags = (it.tags || []).map((tag) => `<span class="chip">${escapeHtml(tag)}</span>`).join("");
+    row.innerHTML = `<div class="run-head">
+        <span class="repo-name">${escapeHtml(it.name)}</span>
+        <span class="mono meta">${escapeHtml(fmtTs(it.removed_ts))}</span>
+        <span class="meta">${escapeHtml(it.removed_reason || "")}</span>
+      </div>
+      <div class="mono meta">${escapeHtml(it.path)}</div>
+      ${tags ? `<div class="chips">${tags}</div>` : ""}
+      <div class="run-events" style="flex-direction: row;">
+        <button class="ghost small" data-act="restore">${escapeHtml(t("trashRestore"))}</button>
+        <button class="ghost small danger" data-act="purge">${escapeHtml(t("trashPurge"))}</button>
+      </div>`;
+    row.querySelector('[data-act="restore"]').onclick = async () => {
+      try {
+        await api("/api/trash/restore", { method: "POST", body: JSON.stringify({ path: it.path }) });
+        toast(t("trashRestored", it));
+        await loadTrash();
+        await refresh();
+      } catch (e) {
+        toast(t("err", { msg: e.message }));
+      }
+    };
+    row.querySelector('[data-act="purge"]').onclick = async () => {
+      try {
+        const out = await api("/api/trash/purge", { method: "POST", body: JSON.stringify({ path: it.path }) });
+        toast(t("trashPurged", out));
+        await loadTrash();
+        await refreshSidebars();
+      } catch (e) {
+        toast(t("err", { msg: e.message }));
+      }
+    };
+    box.appendChild(row);
+  }
+}
+
 function showCommitDetailModal(show) {
   const m = $("commitDetailModal");
   if (show) {
@@ -2260,8 +3569,56 @@ function openRepoDetail(repo) {
   } else {
     $("repoAbout").textContent = about;
   }
-  $("repoTags").innerHTML = (repo.tags || []).map((t0) => `<span class="badge">${escapeHtml(t0)}</span>`).join("");
+  $("repoTags").innerHTML = (repo.tags || []).map((t0) => `<span class="badge"${tagBadgeStyle(t0)}>${escapeHtml(t0)}</span>`).join("");
+  $("repoFields").innerHTML = "";
+  $("repoNote").value = "";
+  $("repoFieldKey").value = "";
+  $("repoFieldValue").value = "";
   showRepoModal(true);
+  loadRepoNotes(repo.path).catch((e) => toast(t("err", { msg: e.message })));
+}
+
+async function loadRepoNotes(path) {
+  const out = await api(`/api/repos/notes?repo_path=${encodeURIComponent(path)}`);
+  if (!repoModalData || repoModalData.path !== path) return;
+  $("repoNote").value = out.note || "";
+  $("repoFieldValues").innerHTML = (out.lifecycles || []).map((v) => `<option value="${escapeHtml(v)}"></option>`).join("");
+  const box = $("repoFields");
+  box.innerHTML = "";
+  for (const f of out.fields || []) {
+    const b = document.createElement("span");
+    b.className = "badge";
+    b.style.cursor = "pointer";
+    b.innerHTML = `${escapeHtml(f.key)}: ${escapeHtml(f.value)}<button title="remove">×</button>`;
+    b.onclick = async () => {
+      fieldFilter = `${f.key}=${f.value}`;
+      viewMode = "list";
+      currentQuery = "";
+      currentPage = 1;
+      $("q").value = "";
+      showRepoModal(false);
+      await loadPage();
+      setStatus(t("filterField", { field: fieldFilter }));
+    };
+    b.querySelector("button").onclick = async (ev) => {
+      ev.stopPropagation();
+      await saveRepoField(f.key, "");
+    };
+    box.appendChild(b);
+  }
+}
+
+async function saveRepoField(key, value) {
+  if (!repoModalData) return;
+  try {
+    await api("/api/repos/field", {
+      method: "POST",
+      body: JSON.stringify({ repo_path: repoModalData.path, key, value }),
+    });
+    await loadRepoNotes(repoModalData.path);
+  } catch (e) {
+    toast(t("err", { msg: e.message }));
+  }
 }
 
 async function openCommitDetail(repoPath, oid) {
@@ -2367,23 +3724,72 @@ async function openCommits(repoPath, defaultBranch, preferredRefname) {
   await loadCommits();
 }
 
+// [syntax, i18n key] rows of the search help popover for each search mode.
+const SEARCH_HELP = {
+  repos: [
+    ["api client", "helpWord"],
+    ['"retry policy"', "helpPhrase"],
+    ["-tag:archived", "helpExclude"],
+    ["tag:backend", "helpTag"],
+    ["lang:rust", "helpLang"],
+    ["root:~/work", "helpRoot"],
+    ["origin:github.com/acme", "helpOrigin"],
+    ["field:owner=alice", "helpField"],
+    ["is:pinned  is:archived", "helpIsRepo"],
+    ["in:name,readme", "helpInRepo"],
+  ],
+  commits: [
+    ["fix timeout", "helpWord"],
+    ['"retry policy"', "helpPhrase"],
+    ["-author:bot", "helpExclude"],
+    ["author:alice", "helpAuthor"],
+    ["email:@acme.com", "helpEmail"],
+    ["is:mine", "helpMine"],
+    ["after:2025-01-01", "helpAfter"],
+    ["before:7d", "helpBefore"],
+    ["branch:main", "helpBranch"],
+    ["repo:payments", "helpRepo"],
+    ["is:merge", "helpIsCommit"],
+    ["in:summary", "helpInCommit"],
+  ],
+};
+
+function renderSearchHelp() {
+  const rows = SEARCH_HELP[$("scopeCommits")?.checked ? "commits" : "repos"];
+  $("searchHelp").innerHTML =
+    `<div class="search-help-title">${escapeHtml(t("helpTitle"))}</div><table>` +
+    rows.map(([syntax, key]) => `<tr><td><code>${escapeHtml(syntax)}</code></td><td>${escapeHtml(t(key))}</td></tr>`).join("") +
+    "</table>";
+}
+
 function applyI18n() {
   const lang = getLang();
   $("btnLang").textContent = I18N[lang].langBtn;
   const scopeCommits = $("scopeCommits")?.checked;
   $("q").placeholder = scopeCommits ? t("qPlaceholderCommits") : t("qPlaceholder");
   $("branchFilter").placeholder = t("branchFilterPlaceholder");
+  $("authorFilter").placeholder = t("authorFilterPlaceholder");
+  $("btnMyCommits").title = myIdentities.length
+    ? t("myCommitsTitle", { ids: myIdentities.join(", ") })
+    : t("noIdentities");
   $("root").placeholder = t("rootPlaceholder");
   document.querySelectorAll("[data-i18n]").forEach((el) => {
     const k = el.getAttribute("data-i18n");
     if (k) el.textContent = t(k);
   });
+  renderSearchHelp();
 }
 
-let activeTag = null;
+// Sidebar tag filter: tag -> "in" | "out"; "in" tags are ANDed unless "match any" is on.
+let tagFilter = {};
+// `key=value` from a field badge in the repo detail modal, or "".
+let fieldFilter = "";
 let viewMode = "list"; // list | search
 let currentQuery = "";
-let commitBranchFilter = "";
+// Commit search filters besides the query: branch, author, after, before, mine.
+let commitFilters = {};
+// `my_identities` from config.toml.
+let myIdentities = [];
 let currentPage = 1;
 let perPage = 25;
 let lastTotal = 0;
@@ -2430,18 +3836,21 @@ function setTableMode(mode) {
   }
 }
 
-function renderRoots(roots) {
+function renderRoots(roots, settings) {
   const ul = $("roots");
   ul.innerHTML = "";
   for (const r of roots) {
+    const rs = settings[r] || {};
     const li = document.createElement("li");
     li.innerHTML = `
-      <div class="mono" title="${escapeHtml(r)}" style="overflow:hidden;text-overflow:ellipsis;white-space:nowrap;">${escapeHtml(r)}</div>
+      <div class="mono" title="${escapeHtml(r)}" style="overflow:hidden;text-overflow:ellipsis;white-space:nowrap;">${escapeHtml(rs.label || r)}</div>
       <div class="actions-cell">
         <button class="ghost small" data-scan="${encodeURIComponent(r)}">${t("scan")}</button>
+        <button class="ghost small" data-settings="${encodeURIComponent(r)}" title="${escapeHtml(t("rootSettingsTitle"))}">⚙</button>
         <button class="ghost small danger" data-root="${encodeURIComponent(r)}">${t("remove")}</button>
       </div>
     `;
+    li.querySelector("button[data-settings]").onclick = () => openRootSettings(r, rs);
     li.querySelector("button.danger").onclick = async () => {
       await api("/api/roots/remove", { method: "POST", body: JSON.stringify({ root: r }) });
       await refresh();
@@ -2451,10 +3860,14 @@ function renderRoots(roots) {
       try {
         setStatus(t("scanning"));
         const prune = $("prune").checked;
-        const out = await api("/api/scan", { method: "POST", body: JSON.stringify({ root: r, prune }) });
-        setStatus(t("scanDone", { indexed: out.indexed, pruned: out.pruned }));
-        toast(t("scanDone", { indexed: out.indexed, pruned: out.pruned }));
+        const full = $("fullScan").checked;
+        const out = await runJob("/api/scan", { root: r, prune, full });
+        setStatus(t("scanDone", out));
+        toast(t("scanDone", out));
         await refresh();
+      } catch (e) {
+        setStatus(t("err", { msg: e.message }));
+        toast(t("err", { msg: e.message }));
       } finally {
         setBusy(false);
       }
@@ -2463,28 +3876,140 @@ function renderRoots(roots) {
   }
 }
 
+let tagRows = [];
+let tagColors = {};
+
+function tagBadgeStyle(tag) {
+  const color = tagColors[tag];
+  return color ? ` style="border-color:${escapeHtml(color)};"` : "";
+}
+
+// Rows come sorted with each namespace right before its children.
 function renderTags(tags) {
+  tagRows = tags;
+  tagColors = {};
+  for (const row of tags) if (row.color) tagColors[row.name] = row.color;
   const box = $("tags");
   box.innerHTML = "";
-  for (const row of tags) {
+  const groups = { "": box };
+  tags.forEach((row, i) => {
     const tag = row.name;
-    const count = row.count || 0;
+    const cut = tag.lastIndexOf("/");
+    const container = groups[cut >= 0 ? tag.slice(0, cut) : ""] || box;
     const c = document.createElement("div");
-    c.className = `chip${activeTag === tag ? " active" : ""}`;
-    c.textContent = `${tag} (${count})`;
+    const state = tagFilter[tag];
+    c.className = `chip${state === "in" ? " active" : state === "out" ? " excluded" : ""}`;
+    if (row.color) c.style.borderLeft = `3px solid ${row.color}`;
+    c.title = row.description || tag;
+    c.textContent = `${tag.slice(cut + 1)} (${row.total || 0})`;
     c.onclick = async () => {
-      activeTag = tag;
-      viewMode = "list";
-      currentQuery = "";
+      if (!state) tagFilter[tag] = "in";
+      else if (state === "in") tagFilter[tag] = "out";
+      else delete tagFilter[tag];
+      if (viewMode === "commit_search") {
+        viewMode = "list";
+        currentQuery = "";
+        $("q").value = "";
+      }
       currentPage = 1;
-      $("q").value = "";
+      renderTags(tagRows);
       await loadPage();
-      setStatus(t("filterTag", { tag }));
+      const desc = describeTagFilter();
+      setStatus(desc ? t("filterTag", { tag: desc }) : t("allRepos"));
     };
-    box.appendChild(c);
+    container.appendChild(c);
+    const next = tags[i + 1];
+    if (next && next.name.startsWith(tag + "/")) {
+      const kids = document.createElement("div");
+      kids.className = "chips tag-children";
+      container.appendChild(kids);
+      groups[tag] = kids;
+    }
+  });
+}
+
+function tagFilterParams() {
+  const pick = (state) => Object.keys(tagFilter).filter((tag) => tagFilter[tag] === state).join(",");
+  const inTags = pick("in");
+  const outTags = pick("out");
+  let q = "";
+  if (inTags) q += `&${$("tagAny").checked ? "any" : "tag"}=${encodeURIComponent(inTags)}`;
+  if (outTags) q += `&not=${encodeURIComponent(outTags)}`;
+  if (fieldFilter) q += `&field=${encodeURIComponent(fieldFilter)}`;
+  if ($("showArchived").checked) q += "&archived=true";
+  return q;
+}
+
+function describeTagFilter() {
+  const inTags = Object.keys(tagFilter).filter((tag) => tagFilter[tag] === "in");
+  const outTags = Object.keys(tagFilter).filter((tag) => tagFilter[tag] === "out");
+  const parts = [];
+  if (inTags.length) parts.push(inTags.join($("tagAny").checked ? " OR " : " AND "));
+  parts.push(...outTags.map((tag) => `NOT ${tag}`));
+  if (fieldFilter) parts.push(fieldFilter);
+  return parts.join(" AND ");
+}
+
+function showTagModal(show) {
+  const m = $("tagModal");
+  if (show) {
+    m.classList.remove("hidden");
+    lockBodyScroll(true);
+  } else {
+    m.classList.add("hidden");
+    lockBodyScroll(false);
+  }
+}
+
+function fillTagModal(name) {
+  const row = tagRows.find((r) => r.name === name) || {};
+  $("tmColor").value = row.color || "";
+  $("tmDescription").value = row.description || "";
+  $("tmTarget").value = "";
+}
+
+function openTagModal() {
+  if (!tagRows.length) {
+    toast(t("tagsNone"));
+    return;
+  }
+  $("tmTag").innerHTML = tagRows
+    .map((r) => `<option value="${escapeHtml(r.name)}">${escapeHtml(r.name)} (${r.total || 0})</option>`)
+    .join("");
+  $("tmTagList").innerHTML = tagRows.map((r) => `<option value="${escapeHtml(r.name)}"></option>`).join("");
+  const picked = Object.keys(tagFilter).find((tag) => tagRows.some((r) => r.name === tag));
+  $("tmTag").value = picked || tagRows[0].name;
+  fillTagModal($("tmTag").value);
+  showTagModal(true);
+}
+
+async function tagAction(path, body, doneKey) {
+  const tag = $("tmTag").value;
+  try {
+    const out = await api(path, { method: "POST", body: JSON.stringify(body) });
+    showTagModal(false);
+    toast(t(doneKey, out || {}));
+    if (path !== "/api/tags/meta") {
+      for (const name of Object.keys(tagFilter)) {
+        if (name === tag || name.startsWith(tag + "/")) delete tagFilter[name];
+      }
+    }
+    await refreshSidebars();
+    await loadPage();
+  } catch (e) {
+    toast(t("err", { msg: e.message }));
   }
 }
 
+async function moveTag(path, doneKey) {
+  const to = $("tmTarget").value.trim();
+  if (!to) {
+    toast(t("tagTargetRequired"));
+    return;
+  }
+  await tagAction(path, { from: $("tmTag").value, to }, doneKey);
+}
+
 function fmtTs(ts) {
   if (!ts) return t("never");
   const d = new Date(ts * 1000);
@@ -2502,10 +4027,9 @@ function renderCommitHits(items) {
       const label = hitLabel(m);
       return `<span class="match-badge">${escapeHtml(label)}</span>`;
     }).join("");
-    const snippet = (c.snippet || c.summary || "").trim();
-    const hasSummary = c.matched_in && c.matched_in.includes("summary");
-    const hasMessage = c.matched_in && c.matched_in.includes("message");
-    
+    // `snippet` is server-side highlighted HTML.
+    const snippetHtml = c.snippet || escapeHtml(c.summary || "");
+
     tr.innerHTML = `
       <td>
         <div class="repo-name wrap clamp2" title="${escapeHtml(c.repo_name + "\n" + c.repo_path)}">${highlightHtml(c.repo_name, currentQuery)}</div>
@@ -2514,7 +4038,8 @@ function renderCommitHits(items) {
       <td>
         <div class="commit-content">
           ${matched ? `<div class="match-badges">${matched}</div>` : ""}
-          <div class="commit-snippet wrap clamp3" title="${escapeHtml(c.summary || "")}">${highlightHtml(snippet, currentQuery)}</div>
+          <div class="commit-snippet wrap clamp3" title="${escapeHtml(c.summary || "")}">${snippetHtml}</div>
+          ${c.author ? `<div class="commit-meta" title="${escapeHtml(c.email || "")}">${escapeHtml(c.author)}</div>` : ""}
         </div>
       </td>
       <td><span class="mono branch-name" title="${escapeHtml(c.branch_name || "")}">${escapeHtml(c.branch_name || "")}</span></td>
@@ -2546,28 +4071,44 @@ function renderRepos(repos) {
     const tags = (r.tags || [])
       .map(
         (tg) =>
-          `<span class="badge">${escapeHtml(tg)}<button title="remove" data-rt="${encodeURIComponent(r.path)}" data-tg="${encodeURIComponent(tg)}">×</button></span>`
+          `<span class="badge"${tagBadgeStyle(tg)}>${escapeHtml(tg)}<button title="remove" data-rt="${encodeURIComponent(r.path)}" data-tg="${encodeURIComponent(tg)}">×</button></span>`
       )
       .join("");
     const about = (r.readme_excerpt || "").trim();
     const origin = (r.origin_url || "").trim();
     const matched = (r.matched_in || []).map((m) => `<span class="badge">${escapeHtml(hitLabel(m))}</span>`).join("");
-    const doHighlight = viewMode === "search" && currentQuery.trim().length > 0;
-    const nameHtml = doHighlight ? highlightHtml(r.name, currentQuery) : escapeHtml(r.name);
-    const aboutHtml = doHighlight ? highlightHtml(about, currentQuery) : escapeHtml(about);
-    const originHtml = doHighlight ? highlightHtml(origin, currentQuery) : escapeHtml(origin);
-    const pathHtml = doHighlight ? highlightHtml(r.path, currentQuery) : escapeHtml(r.path);
+    // Search hits come with server-side highlights (already escaped HTML).
+    const hl = (viewMode === "search" && r.highlights) || {};
+    const nameHtml = hl.name || escapeHtml(r.name);
+    const aboutHtml = hl.readme || escapeHtml(about);
+    const originHtml = escapeHtml(origin);
+    const pathHtml = hl.path || escapeHtml(r.path);
+    const notesHtml = hl.notes || "";
+    const kindBadge = r.kind && r.kind !== "normal"
+      ? `<span class="match-badge kind-badge" title="${escapeHtml(r.parent_path || "")}">${escapeHtml(t("kind_" + r.kind))}</span>`
+      : "";
+    const langBadge = r.language ? `<span class="match-badge">${escapeHtml(r.language)}</span>` : "";
+    const archivedBadge = r.archived ? `<span class="match-badge">${escapeHtml(t("archivedBadge"))}</span>` : "";
+    const worktrees = (r.worktrees || [])
+      .map(
+        (w) =>
+          `<div class="mono wrap worktree" data-wt="${encodeURIComponent(w.path)}" title="${escapeHtml(w.path)}">↳ ${escapeHtml(w.default_branch || w.name)} <span class="muted">${escapeHtml(w.path)}</span></div>`
+      )
+      .join("");
     const selCell = bulkMode
       ? `<td><input type="checkbox" class="sel" data-path="${encodeURIComponent(r.path)}" ${bulkSelected.has(r.path) ? "checked" : ""} /></td>`
       : "";
     tr.innerHTML = `
       ${selCell}
       <td>
-        <div class="repo-name wrap clamp2 repo-link" title="${escapeHtml(r.name + (r.path ? "\n" + r.path : ""))}">${nameHtml}</div>
+        <div class="repo-name wrap clamp2 repo-link" title="${escapeHtml(r.name + (r.path ? "\n" + r.path : ""))}">${r.pinned ? "★ " : ""}${nameHtml}</div>
+        ${kindBadge || langBadge || archivedBadge ? `<div class="match-badges" style="margin:4px 0 0;">${kindBadge}${langBadge}${archivedBadge}</div>` : ""}
         ${matched ? `<div class="badges" style="margin-top:6px;">${matched}</div>` : ""}
         ${about ? `<div class="meta wrap clamp2" title="${escapeHtml(about)}">${aboutHtml}</div>` : ""}
+        ${notesHtml ? `<div class="meta wrap clamp2">${notesHtml}</div>` : ""}
         ${origin ? `<div class="mono wrap clamp2 origin" title="${escapeHtml(origin)}">${originHtml}</div>` : ""}
         <div class="mono wrap clamp2" title="${escapeHtml(r.path)}">${pathHtml}</div>
+        ${worktrees ? `<div class="worktrees" title="${escapeHtml(t("worktrees"))}">${worktrees}</div>` : ""}
       </td>
       <td class="tags-cell">
         <div class="badges">${tags}</div>
@@ -2587,6 +4128,9 @@ function renderRepos(repos) {
           <button class="ghost small" data-commits="${encodeURIComponent(r.path)}">${t("commitsBtn")}</button>
           <button class="ghost small" data-open="${encodeURIComponent(r.path)}">${t("open")}</button>
           <button class="ghost small" data-copy="${encodeURIComponent(r.path)}">${t("copy")}</button>
+          <button class="ghost small" data-pin>${t(r.pinned ? "unpinBtn" : "pinBtn")}</button>
+          <button class="ghost small" data-archive>${t(r.archived ? "unarchiveBtn" : "archiveBtn")}</button>
+          <button class="ghost small danger" data-hide>${t("hideBtn")}</button>
         </div>
       </td>
     `;
@@ -2604,6 +4148,12 @@ function renderRepos(repos) {
       await copyToClipboard(r.path);
     };
 
+    tr.querySelectorAll(".worktree").forEach((el) => {
+      el.onclick = async () => {
+        await copyToClipboard(decodeURIComponent(el.dataset.wt || ""));
+      };
+    });
+
     if (origin) {
       tr.querySelector(".origin").onclick = async () => {
         await copyToClipboard(origin);
@@ -2618,6 +4168,25 @@ function renderRepos(repos) {
       await openCommits(r.path, r.default_branch, null);
     };
 
+    const repoAction = async (path, body) => {
+      try {
+        await api(path, { method: "POST", body: JSON.stringify({ repo_path: r.path, ...body }) });
+        await loadPage();
+        return true;
+      } catch (e) {
+        toast(t("err", { msg: e.message }));
+        return false;
+      }
+    };
+    tr.querySelector("button[data-pin]").onclick = () => repoAction("/api/repos/pin", { on: !r.pinned });
+    tr.querySelector("button[data-archive]").onclick = () => repoAction("/api/repos/archive", { on: !r.archived });
+    tr.querySelector("button[data-hide]").onclick = async () => {
+      if (!confirm(t("hideConfirm", { path: r.path }))) return;
+      if (!(await repoAction("/api/repos/hide", {}))) return;
+      toast(t("repoHidden", { path: r.path }));
+      await refreshSidebars();
+    };
+
     tr.querySelector("button[data-open]").onclick = async () => {
       await api("/api/open", { method: "POST", body: JSON.stringify({ repo: r.path }) });
       toast(t("accessRecorded", { path: r.path }));
@@ -2673,9 +4242,10 @@ function updatePager() {
 
 async function refreshSidebars() {
   const roots = await api("/api/roots");
-  renderRoots(roots.roots || []);
+  renderRoots(roots.roots || [], roots.settings || {});
   const tags = await api("/api/tags");
   renderTags(tags);
+  await loadScanErrors();
 }
 
 async function loadPage() {
@@ -2685,13 +4255,16 @@ async function loadPage() {
     const in_path = $("inPath").checked ? "true" : "false";
     const in_readme = $("inReadme").checked ? "true" : "false";
     const in_tags = $("inTags").checked ? "true" : "false";
+    const in_notes = $("inNotes").checked ? "true" : "false";
     const out = await api(
-      `/api/search?q=${encodeURIComponent(currentQuery)}&page=${currentPage}&per_page=${perPage}&in_name=${in_name}&in_path=${in_path}&in_readme=${in_readme}&in_tags=${in_tags}`
+      `/api/search?q=${encodeURIComponent(currentQuery)}&page=${currentPage}&per_page=${perPage}&in_name=${in_name}&in_path=${in_path}&in_readme=${in_readme}&in_tags=${in_tags}&in_notes=${in_notes}${tagFilterParams()}`
     );
     lastTotal = out.total;
     renderRepos(out.items || []);
   } else if (viewMode === "commit_search") {
-    const b = commitBranchFilter ? `&branch=${encodeURIComponent(commitBranchFilter)}` : "";
+    const b = Object.entries(commitFilters)
+      .map(([k, v]) => `&${k}=${encodeURIComponent(v)}`)
+      .join("");
     const in_summary = $("inSummary").checked ? "true" : "false";
     const in_message = $("inMessage").checked ? "true" : "false";
     const out = await api(
@@ -2700,8 +4273,7 @@ async function loadPage() {
     lastTotal = out.total;
     renderCommitHits(out.items || []);
   } else {
-    const tagPart = activeTag ? `&tag=${encodeURIComponent(activeTag)}` : "";
-    const out = await api(`/api/repos?recent=${recent}${tagPart}&page=${currentPage}&per_page=${perPage}`);
+    const out = await api(`/api/repos?recent=${recent}${tagFilterParams()}&page=${currentPage}&per_page=${perPage}`);
     lastTotal = out.total;
     renderRepos(out.items || []);
   }
@@ -2768,16 +4340,37 @@ $("ignoreAdd").onclick = async () => {
   await loadCommitIndexConfig();
 };
 
+$("ignorePatternAdd").onclick = async () => {
+  const pattern = $("ignorePattern").value.trim();
+  if (!pattern) return;
+  try {
+    await api("/api/ignores/add", { method: "POST", body: JSON.stringify({ pattern }) });
+  } catch (e) {
+    toast(t("err", { msg: e.message }));
+    return;
+  }
+  $("ignorePattern").value = "";
+  await loadCommitIndexConfig();
+};
+
 $("ignoreReset").onclick = async () => {
   await api("/api/ignores/reset", { method: "POST", body: "{}" });
   await loadCommitIndexConfig();
 };
 
+$("tagAny").onchange = async () => {
+  if (!Object.values(tagFilter).includes("in")) return;
+  currentPage = 1;
+  await loadPage();
+};
+
 $("btnClearTag").onclick = async () => {
-  activeTag = null;
+  tagFilter = {};
+  fieldFilter = "";
+  renderTags(tagRows);
   viewMode = "list";
   currentQuery = "";
-  commitBranchFilter = "";
+  commitFilters = {};
   currentPage = 1;
   $("q").value = "";
   await loadPage();
@@ -2788,10 +4381,14 @@ $("btnScanAll").onclick = async () => {
   try {
     setStatus(t("scanning"));
     const prune = $("prune").checked;
-    const out = await api("/api/scan", { method: "POST", body: JSON.stringify({ all: true, prune }) });
-    setStatus(t("scanDone", { indexed: out.indexed, pruned: out.pruned }));
-    toast(t("scanDone", { indexed: out.indexed, pruned: out.pruned }));
+    const full = $("fullScan").checked;
+    const out = await runJob("/api/scan", { all: true, prune, full });
+    setStatus(t("scanDone", out));
+    toast(t("scanDone", out));
     await refresh();
+  } catch (e) {
+    setStatus(t("err", { msg: e.message }));
+    toast(t("err", { msg: e.message }));
   } finally {
     setBusy(false);
   }
@@ -2801,22 +4398,33 @@ $("btnPrune").onclick = async () => {
   setBusy(true);
   try {
     setStatus(t("pruning"));
-    const out = await api("/api/prune", { method: "POST", body: "{}" });
-    setStatus(t("pruneDone", { deleted: out.deleted }));
-    toast(t("pruneDone", { deleted: out.deleted }));
+    const out = await runJob("/api/prune", {});
+    setStatus(t("pruneDone", out));
+    toast(t("pruneDone", out));
     await refresh();
+  } catch (e) {
+    setStatus(t("err", { msg: e.message }));
+    toast(t("err", { msg: e.message }));
   } finally {
     setBusy(false);
   }
 };
 
+$("btnCancelJob").onclick = async () => {
+  if (currentJobId == null) return;
+  await api(`/api/jobs/${currentJobId}/cancel`, { method: "POST", body: "{}" });
+};
+
 $("btnAll").onclick = async () => {
-  activeTag = null;
+  tagFilter = {};
+  fieldFilter = "";
+  renderTags(tagRows);
   $("q").value = "";
-  $("branchFilter").value = "";
+  ["branchFilter", "authorFilter", "afterFilter", "beforeFilter"].forEach((id) => ($(id).value = ""));
+  $("mineOnly").checked = false;
   viewMode = "list";
   currentQuery = "";
-  commitBranchFilter = "";
+  commitFilters = {};
   currentPage = 1;
   bulkMode = false;
   bulkSelected.clear();
@@ -2829,12 +4437,18 @@ $("btnSearch").onclick = async () => {
   const q = $("q").value.trim();
   const commits = $("scopeCommits").checked;
   if (commits) {
-    if (!q) return;
+    const filters = readCommitFilters();
+    // Filters alone are a search too (e.g. every commit by an author).
+    if (!q && Object.keys(filters).length === 0) return;
     viewMode = "commit_search";
     currentQuery = q;
-    commitBranchFilter = $("branchFilter").value.trim();
+    commitFilters = filters;
     currentPage = 1;
-    await loadPage();
+    try {
+      await loadPage();
+    } catch (e) {
+      setStatus(t("err", { msg: e.message }));
+    }
     return;
   }
   if (!q) {
@@ -2858,6 +4472,23 @@ $("btnSearch").onclick = async () => {
   }
 };
 
+function readCommitFilters() {
+  const filters = {};
+  for (const [key, id] of [["branch", "branchFilter"], ["author", "authorFilter"], ["after", "afterFilter"], ["before", "beforeFilter"]]) {
+    const v = $(id).value.trim();
+    if (v) filters[key] = v;
+  }
+  if ($("mineOnly").checked) filters.mine = "true";
+  return filters;
+}
+
+// Every commit by `my_identities`, newest first, across all indexed repos.
+$("btnMyCommits").onclick = async () => {
+  $("q").value = "";
+  $("mineOnly").checked = true;
+  await $("btnSearch").onclick();
+};
+
 $("q").addEventListener("keydown", (e) => {
   if (e.key === "Enter") $("btnSearch").click();
 });
@@ -2874,6 +4505,8 @@ function updateSearchUi() {
   applyI18n();
 }
 
+$("btnSearchHelp").onclick = () => $("searchHelp").classList.toggle("hidden");
+
 document.querySelectorAll('input[name="searchMode"]').forEach((radio) => {
   radio.onchange = () => updateSearchUi();
 });
@@ -2882,10 +4515,10 @@ $("q").addEventListener("input", async () => {
   const q = $("q").value.trim();
   const commits = $("scopeCommits").checked;
   if (commits) {
-    if (q.length === 0 && viewMode === "commit_search") {
+    if (q.length === 0 && viewMode === "commit_search" && Object.keys(readCommitFilters()).length === 0) {
       viewMode = "list";
       currentQuery = "";
-      commitBranchFilter = "";
+      commitFilters = {};
       currentPage = 1;
       await loadPage();
       setStatus(t("allRepos"));
@@ -2906,6 +4539,11 @@ $("recent").onchange = async () => {
   await loadPage();
 };
 
+$("showArchived").onchange = async () => {
+  currentPage = 1;
+  await loadPage();
+};
+
 $("perPage").onchange = async () => {
   perPage = parseInt($("perPage").value, 10) || 25;
   currentPage = 1;
@@ -2929,11 +4567,82 @@ document.addEventListener("keydown", (e) => {
   if (e.key === "Escape") showCommitModal(false);
 });
 
+$("btnHistory").onclick = async () => {
+  showHistoryModal(true);
+  await loadHistory();
+};
+$("historyClose").onclick = () => showHistoryModal(false);
+$("historyX").onclick = () => showHistoryModal(false);
+$("historyLoad").onclick = loadHistory;
+$("historyChangedOnly").onchange = loadHistory;
+
+$("rootSettingsClose").onclick = () => showRootSettingsModal(false);
+$("rootSettingsX").onclick = () => showRootSettingsModal(false);
+$("rsSave").onclick = () => saveRootSettings(false);
+$("rsClear").onclick = () => saveRootSettings(true);
+
+$("btnManageTags").onclick = openTagModal;
+$("tagClose").onclick = () => showTagModal(false);
+$("tagX").onclick = () => showTagModal(false);
+$("tmTag").onchange = () => fillTagModal($("tmTag").value);
+$("tmSave").onclick = () =>
+  tagAction(
+    "/api/tags/meta",
+    { name: $("tmTag").value, color: $("tmColor").value.trim() || null, description: $("tmDescription").value.trim() || null },
+    "tagSaved"
+  );
+$("tmRename").onclick = () => moveTag("/api/tags/rename", "tagRenamed");
+$("tmMerge").onclick = () => moveTag("/api/tags/merge", "tagMerged");
+$("tmDelete").onclick = async () => {
+  const tag = $("tmTag").value;
+  if (!confirm(t("tagConfirmDelete", { tag }))) return;
+  await tagAction("/api/tags/delete", { name: tag }, "tagDeleted");
+};
+
+$("btnTrash").onclick = async () => {
+  showTrashModal(true);
+  await loadTrash();
+};
+$("trashClose").onclick = () => showTrashModal(false);
+$("trashX").onclick = () => showTrashModal(false);
+$("trashEmpty").onclick = async () => {
+  if (!confirm(t("trashConfirmEmpty"))) return;
+  try {
+    const out = await api("/api/trash/purge", { method: "POST", body: JSON.stringify({}) });
+    toast(t("trashPurged", out));
+    await loadTrash();
+    await refreshSidebars();
+  } catch (e) {
+    toast(t("err", { msg: e.message }));
+  }
+};
+
 $("repoClose").onclick = () => showRepoModal(false);
 $("repoX").onclick = () => showRepoModal(false);
 $("commitDetailClose").onclick = () => showCommitDetailModal(false);
 $("commitDetailX").onclick = () => showCommitDetailModal(false);
 
+$("repoFieldSet").onclick = async () => {
+  const key = $("repoFieldKey").value.trim();
+  if (!key) return;
+  await saveRepoField(key, $("repoFieldValue").value);
+  $("repoFieldKey").value = "";
+  $("repoFieldValue").value = "";
+};
+
+$("repoNoteSave").onclick = async () => {
+  if (!repoModalData) return;
+  try {
+    await api("/api/repos/note", {
+      method: "POST",
+      body: JSON.stringify({ repo_path: repoModalData.path, body: $("repoNote").value }),
+    });
+    toast(t("noteSaved"));
+  } catch (e) {
+    toast(t("err", { msg: e.message }));
+  }
+};
+
 $("repoCopy").onclick = async () => {
   if (!repoModalData) return;
   await copyToClipboard(repoModalData.path);